@@ -14,10 +14,42 @@ class DaemonClient {
     private let socketPath: String
     private let queue = DispatchQueue(label: "com.secondbrain.daemon-client")
     
-    init() {
-        // Socket path matches daemon config
-        let home = FileManager.default.homeDirectoryForCurrentUser.path
-        self.socketPath = "\(home)/.local/share/second-brain/daemon.sock"
+    init(socketPath: String = DaemonClient.defaultSocketPath()) {
+        self.socketPath = socketPath
+    }
+    
+    /// Where the daemon listens, looked up in the order documented in
+    /// daemon/src/config/paths.rs: $SECOND_BRAIN_SOCKET, then
+    /// $XDG_RUNTIME_DIR/second-brain, then second-brain in the per-user
+    /// temporary directory. That directory comes from confstr rather than
+    /// NSTemporaryDirectory(), since $TMPDIR can differ between the app and
+    /// the daemon launchd started. Everything else the daemon uses can be
+    /// looked up with `getPaths()`.
+    static func defaultSocketPath() -> String {
+        let environment = ProcessInfo.processInfo.environment
+        if let socket = environment["SECOND_BRAIN_SOCKET"], !socket.isEmpty {
+            return socket
+        }
+        let runtimeDir: String
+        if let dir = environment["XDG_RUNTIME_DIR"], dir.hasPrefix("/") {
+            runtimeDir = dir
+        } else {
+            runtimeDir = userTemporaryDirectory() ?? NSTemporaryDirectory()
+        }
+        return URL(fileURLWithPath: runtimeDir)
+            .appendingPathComponent("second-brain")
+            .appendingPathComponent("daemon.sock")
+            .path
+    }
+
+    /// The temporary directory macOS keeps for the user, the same for
+    /// every process they run
+    private static func userTemporaryDirectory() -> String? {
+        let length = confstr(_CS_DARWIN_USER_TEMP_DIR, nil, 0)
+        guard length > 0 else { return nil }
+        var buffer = [CChar](repeating: 0, count: length)
+        guard confstr(_CS_DARWIN_USER_TEMP_DIR, &buffer, length) == length else { return nil }
+        return String(cString: buffer)
     }
     
     // MARK: - Connection Management
@@ -171,6 +203,34 @@ class DaemonClient {
             throw DaemonClientError.unexpectedResponse
         }
     }
+    
+    func getPaths() async throws -> DaemonPaths {
+        try await send(Request.getPaths)
+        let response = try await receive()
+        
+        switch response {
+        case .paths(let paths):
+            return paths
+        case .error(let code, let message):
+            throw DaemonClientError.daemonError(code: code, message: message)
+        default:
+            throw DaemonClientError.unexpectedResponse
+        }
+    }
+    
+    func getHealth() async throws -> DaemonHealth {
+        try await send(Request.getHealth)
+        let response = try await receive()
+        
+        switch response {
+        case .health(let health):
+            return health
+        case .error(let code, let message):
+            throw DaemonClientError.daemonError(code: code, message: message)
+        default:
+            throw DaemonClientError.unexpectedResponse
+        }
+    }
 }
 
 // MARK: - Errors
@@ -31,6 +31,8 @@ enum Request: Encodable {
     case getStatus
     case setMode(mode: DaemonMode)
     case ping
+    case getPaths
+    case getHealth
     
     private enum CodingKeys: String, CodingKey {
         case type, mode
@@ -46,6 +48,10 @@ enum Request: Encodable {
             try container.encode(mode, forKey: .mode)
         case .ping:
             try container.encode("ping", forKey: .type)
+        case .getPaths:
+            try container.encode("get_paths", forKey: .type)
+        case .getHealth:
+            try container.encode("get_health", forKey: .type)
         }
     }
 }
@@ -56,6 +62,8 @@ enum Response: Decodable {
     case status(DaemonStatus)
     case modeChange(mode: DaemonMode, active: Bool)
     case pong
+    case paths(DaemonPaths)
+    case health(DaemonHealth)
     case error(code: String, message: String)
     
     private enum CodingKeys: String, CodingKey {
@@ -77,6 +85,12 @@ enum Response: Decodable {
             self = .modeChange(mode: mode, active: active)
         case "pong":
             self = .pong
+        case "paths":
+            let paths = try DaemonPaths(from: decoder)
+            self = .paths(paths)
+        case "health":
+            let health = try DaemonHealth(from: decoder)
+            self = .health(health)
         case "error":
             let code = try container.decode(String.self, forKey: .code)
             let message = try container.decode(String.self, forKey: .message)
@@ -113,3 +127,47 @@ struct DaemonStatus: Decodable {
         uptimeSecs = try container.decode(UInt64.self, forKey: .uptimeSecs)
     }
 }
+
+// MARK: - Daemon Paths
+
+/// Where the running daemon keeps its files
+struct DaemonPaths: Decodable {
+    let socket: String
+    let configFile: String
+    let dataDir: String
+    let settingsFile: String
+    
+    private enum CodingKeys: String, CodingKey {
+        case socket
+        case configFile = "config_file"
+        case dataDir = "data_dir"
+        case settingsFile = "settings_file"
+    }
+}
+
+// MARK: - Daemon Health
+
+enum HealthState: String, Decodable {
+    case ok
+    case degraded
+    case failed
+}
+
+/// Whether one daemon component works
+struct ComponentHealth: Decodable {
+    let name: String
+    let state: HealthState
+    let message: String
+    let sinceUnixSecs: UInt64
+    
+    private enum CodingKeys: String, CodingKey {
+        case name, state, message
+        case sinceUnixSecs = "since_unix_secs"
+    }
+}
+
+/// Health of every daemon component; `status` is the worst of them
+struct DaemonHealth: Decodable {
+    let status: HealthState
+    let components: [ComponentHealth]
+}
@@ -0,0 +1,145 @@
+//! Command-line interface
+//!
+//! Settings are resolved in this order, first match wins:
+//! 1. flags (`--socket`, `--data-dir`, `--log-level`, `--config`)
+//! 2. environment (`SECOND_BRAIN_SOCKET`, `SECOND_BRAIN_DATA_DIR`,
+//!    `SECOND_BRAIN_LOG_LEVEL` then `RUST_LOG`, `SECOND_BRAIN_CONFIG`)
+//! 3. `config.toml`
+//! 4. built-in defaults
+
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::config::Overrides;
+use crate::state::DiagramFormat;
+
+/// Background daemon for the second-brain voice assistant
+#[derive(Debug, Parser)]
+#[command(
+    name = "second-brain-daemon",
+    version,
+    after_help = "Settings are resolved as: flags > environment variables > config.toml > defaults."
+)]
+pub struct Cli {
+    #[command(flatten)]
+    pub overrides: OverrideArgs,
+
+    /// What to do; runs the daemon if omitted
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Flags that override `config.toml`, accepted by every subcommand
+#[derive(Debug, Args)]
+pub struct OverrideArgs {
+    /// Config file to use instead of the default location
+    #[arg(long, global = true, value_name = "PATH", env = "SECOND_BRAIN_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// IPC socket path
+    #[arg(long, global = true, value_name = "PATH", env = "SECOND_BRAIN_SOCKET")]
+    pub socket: Option<PathBuf>,
+
+    /// Directory for settings and other state kept across restarts
+    #[arg(long, global = true, value_name = "PATH", env = "SECOND_BRAIN_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Log filter, e.g. "debug" or "info,second_brain_daemon::hotkey=trace"
+    /// (falls back to RUST_LOG)
+    #[arg(long, global = true, value_name = "FILTER", env = "SECOND_BRAIN_LOG_LEVEL")]
+    pub log_level: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the daemon (the default)
+    Run,
+    /// Print the effective configuration as TOML
+    PrintConfig,
+    /// Validate config.toml and exit non-zero if it has errors
+    CheckConfig,
+    /// Print version information
+    Version {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Replay a hotkey recording and print the resulting state events as JSONL
+    Replay {
+        /// Recording made with SECOND_BRAIN_RECORD_HOTKEYS
+        recording: PathBuf,
+    },
+    /// Print the state machine as a diagram
+    Diagram {
+        /// mermaid or dot
+        #[arg(default_value = "mermaid")]
+        format: DiagramFormat,
+    },
+    /// Manage provider credentials in the running daemon's secret store
+    Secret {
+        #[command(subcommand)]
+        action: SecretCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretCommand {
+    /// Store a secret, reading its value from stdin so it stays out of
+    /// shell history and process listings
+    Set {
+        /// e.g. "openai.api_key"
+        name: String,
+    },
+    /// List the names of the stored secrets
+    List,
+    /// Remove a secret
+    Delete { name: String },
+}
+
+impl OverrideArgs {
+    /// The overrides to apply on top of `config.toml`
+    ///
+    /// Relative paths are taken relative to the working directory.
+    pub fn resolve(&self) -> std::io::Result<Overrides> {
+        let absolute = |path: &Option<PathBuf>| path.as_deref().map(std::path::absolute).transpose();
+        Ok(Overrides {
+            config_path: absolute(&self.config)?,
+            socket: absolute(&self.socket)?,
+            data_dir: absolute(&self.data_dir)?,
+            log_level: self
+                .log_level
+                .clone()
+                .or_else(|| std::env::var("RUST_LOG").ok())
+                .filter(|level| !level.is_empty()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_after_subcommand() {
+        let cli = Cli::try_parse_from([
+            "second-brain-daemon",
+            "run",
+            "--socket",
+            "/tmp/a.sock",
+            "--log-level",
+            "debug",
+        ])
+        .unwrap();
+        assert!(matches!(cli.command, Some(Command::Run)));
+        assert_eq!(cli.overrides.socket, Some(PathBuf::from("/tmp/a.sock")));
+        assert_eq!(cli.overrides.log_level.as_deref(), Some("debug"));
+    }
+
+    #[test]
+    fn test_no_subcommand_runs() {
+        let cli = Cli::try_parse_from(["second-brain-daemon"]).unwrap();
+        assert!(cli.command.is_none());
+        assert!(Cli::try_parse_from(["second-brain-daemon", "diagram", "svg"]).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! What changed between two versions of `config.toml`
+
+use super::file::ConfigFile;
+
+/// Sections that are only read at startup
+const RESTART_REQUIRED: &[&str] = &["secrets", "paths"];
+
+/// Keys that differ between two configs, as dotted paths
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// Keys that take effect right away
+    pub changed: Vec<String>,
+    /// Keys that only take effect after a restart
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// Compare the running config with a newly loaded one
+    pub fn between(old: &ConfigFile, new: &ConfigFile) -> Self {
+        let old = toml::Table::try_from(old).expect("config serializes to a table");
+        let new = toml::Table::try_from(new).expect("config serializes to a table");
+
+        let mut keys = Vec::new();
+        collect(&old, &new, "", &mut keys);
+
+        let (restart_required, changed) = keys
+            .into_iter()
+            .partition(|key| RESTART_REQUIRED.iter().any(|section| in_section(key, section)));
+        Self {
+            changed,
+            restart_required,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.restart_required.is_empty()
+    }
+
+    /// Whether a key in `section` (e.g. "hotkeys") changed
+    pub fn touches(&self, section: &str) -> bool {
+        self.changed.iter().any(|key| in_section(key, section))
+    }
+}
+
+fn in_section(key: &str, section: &str) -> bool {
+    key.strip_prefix(section)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Push the dotted path of every leaf value that differs, including keys
+/// only one of the tables has
+fn collect(old: &toml::Table, new: &toml::Table, prefix: &str, keys: &mut Vec<String>) {
+    let removed = old.keys().filter(|name| !new.contains_key(*name));
+    for name in new.keys().chain(removed) {
+        let path = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+
+        match (old.get(name), new.get(name)) {
+            (Some(toml::Value::Table(old)), Some(toml::Value::Table(new))) => collect(old, new, &path, keys),
+            (Some(old_value), Some(new_value)) if old_value == new_value => {}
+            _ => keys.push(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lists_changed_keys() {
+        let old = ConfigFile::default();
+        assert!(ConfigDiff::between(&old, &old).is_empty());
+
+        let text = "[hotkeys]\ndictation = [\"f18\"]\n\n[ipc]\nmax_clients = 2\n\n[paths]\nsocket = \"/tmp/sb.sock\"\n";
+        let (new, _) = ConfigFile::parse(text).unwrap();
+        let diff = ConfigDiff::between(&old, &new);
+        assert_eq!(diff.changed, ["hotkeys.dictation", "ipc.max_clients"]);
+        assert_eq!(diff.restart_required, ["paths.socket"]);
+        assert!(diff.touches("hotkeys") && diff.touches("ipc"));
+        assert!(!diff.touches("hotkey") && !diff.touches("paths"));
+    }
+
+    #[test]
+    fn test_diff_lists_removed_keys() {
+        let text = "[profiles.work.hotkeys]\ndictation = [\"f18\"]\n\n[profiles.home.privacy]\nallow_cloud = false\n";
+        let (old, _) = ConfigFile::parse(text).unwrap();
+        let (new, _) = ConfigFile::parse("[profiles.home.privacy]\n").unwrap();
+        let diff = ConfigDiff::between(&old, &new);
+        assert_eq!(diff.changed, ["profiles.home.privacy.allow_cloud", "profiles.work"]);
+        assert!(ConfigDiff::between(&new, &old).touches("profiles"));
+    }
+}
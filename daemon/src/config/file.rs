@@ -0,0 +1,581 @@
+//! The user-edited `config.toml`
+//!
+//! Every section and key is optional and falls back to the defaults
+//! below. Unknown keys (usually typos) are warnings; syntax, type and
+//! validation errors are reported with the line and column they were
+//! found at. A file with errors is never applied: the daemon keeps
+//! running on defaults and reports the errors over IPC, instead of
+//! exiting and being restarted by launchd in a loop.
+//!
+//! `[profiles.<name>]` tables hold overrides for the sections in
+//! `PROFILE_SECTIONS`, applied on top of the rest of the file while that
+//! profile is active.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use toml_edit::{ImDocument, Item, TableLike};
+use tracing_subscriber::EnvFilter;
+
+use crate::hotkey::Bindings;
+use crate::ipc::MAX_MESSAGE_LEN;
+use crate::secrets::SecretBackend;
+use crate::state::EnabledModes;
+
+/// Sections a profile may override
+pub const PROFILE_SECTIONS: &[&str] = &["hotkeys", "modes", "providers", "privacy"];
+
+/// Stands for any key in the tables `unknown_keys` checks against
+const ANY_KEY: &str = "*";
+
+/// Contents of `config.toml`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    /// Chords that trigger each mode
+    pub hotkeys: Bindings,
+    /// Modes enabled before the user changes them over IPC
+    pub modes: EnabledModes,
+    pub timeouts: Timeouts,
+    pub ipc: IpcLimits,
+    pub logging: Logging,
+    pub providers: Providers,
+    pub privacy: Privacy,
+    pub secrets: Secrets,
+    pub paths: Paths,
+    /// Named overrides, switched between at runtime
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+/// How long the daemon waits for things
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeouts {
+    /// Chord capture timeout when the client doesn't give one
+    pub chord_capture_secs: u64,
+}
+
+impl Timeouts {
+    pub fn chord_capture(&self) -> Duration {
+        Duration::from_secs(self.chord_capture_secs)
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self { chord_capture_secs: 30 }
+    }
+}
+
+/// Limits on IPC clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IpcLimits {
+    /// Connections served at once; further clients are turned away
+    pub max_clients: usize,
+    /// Largest request the server reads
+    pub max_message_bytes: usize,
+}
+
+impl Default for IpcLimits {
+    fn default() -> Self {
+        Self {
+            max_clients: 16,
+            max_message_bytes: MAX_MESSAGE_LEN,
+        }
+    }
+}
+
+/// Log output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Logging {
+    /// `tracing` filter, e.g. "info" or "info,second_brain_daemon::hotkey=debug";
+    /// `--log-level`, `SECOND_BRAIN_LOG_LEVEL` and `RUST_LOG` take precedence
+    pub level: String,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+        }
+    }
+}
+
+/// Backends for the transcription and LLM pipelines
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Providers {
+    pub transcription: Provider,
+    pub llm: Provider,
+}
+
+/// One pipeline backend; empty fields mean "not configured"
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Provider {
+    /// Backend name, e.g. "whisper_local" or "openai"
+    pub backend: String,
+    pub model: String,
+    /// Base URL for remote backends
+    pub endpoint: String,
+}
+
+/// What may leave the machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Privacy {
+    /// Whether remote transcription and LLM backends may be used; when
+    /// false only local ones are
+    pub allow_cloud: bool,
+}
+
+impl Default for Privacy {
+    fn default() -> Self {
+        Self { allow_cloud: true }
+    }
+}
+
+/// Where provider credentials are stored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Secrets {
+    pub backend: SecretBackend,
+}
+
+/// Where the daemon keeps its files; empty means the default location
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Paths {
+    /// Directory for settings and other state kept across restarts
+    pub data_dir: PathBuf,
+    /// IPC socket
+    pub socket: PathBuf,
+}
+
+/// How bad a config problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The file was not applied
+    Error,
+    /// The file was applied, but something in it was ignored
+    Warning,
+}
+
+/// A problem found in the config file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// 1-based position in the file, when known
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: impl Into<String>, text: &str, span: Option<Range<usize>>) -> Self {
+        let (line, column) = match span {
+            Some(span) => {
+                let (line, column) = position(text, span.start);
+                (Some(line), Some(column))
+            }
+            None => (None, None),
+        };
+        Self {
+            severity,
+            message: message.into(),
+            line,
+            column,
+        }
+    }
+
+    fn error(message: impl Into<String>, text: &str, span: Option<Range<usize>>) -> Self {
+        Self::new(Severity::Error, message, text, span)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "line {}, column {}: {}", line, column, self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Load `path`, falling back to defaults if it is missing or invalid
+    ///
+    /// The diagnostics say why the file wasn't (fully) applied.
+    pub fn load(path: &Path) -> (Self, Vec<Diagnostic>) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => match Self::parse(&text) {
+                Ok(parsed) => parsed,
+                Err(diagnostics) => (Self::default(), diagnostics),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(e) => {
+                let message = format!("failed to read {}: {}", path.display(), e);
+                (Self::default(), vec![Diagnostic::error(message, "", None)])
+            }
+        }
+    }
+
+    /// Parse and validate a config file
+    ///
+    /// Returns the config with any warnings, or every problem found if
+    /// there were errors.
+    pub fn parse(text: &str) -> Result<(Self, Vec<Diagnostic>), Vec<Diagnostic>> {
+        let document = ImDocument::parse(text)
+            .map_err(|e| vec![Diagnostic::error(e.message(), text, e.span())])?;
+
+        let mut diagnostics = Vec::new();
+        let mut known = toml::Table::try_from(Self::default()).expect("defaults serialize to a table");
+        let profile: toml::Table = known
+            .iter()
+            .filter(|(name, _)| PROFILE_SECTIONS.contains(&name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let profiles = toml::Table::from_iter([(ANY_KEY.to_string(), toml::Value::Table(profile))]);
+        known.insert("profiles".to_string(), toml::Value::Table(profiles));
+        unknown_keys(document.as_table(), &known, "", text, &mut diagnostics);
+
+        let config = match toml::from_str::<Self>(text) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                diagnostics.push(Diagnostic::error(e.message(), text, e.span()));
+                None
+            }
+        };
+
+        if let Some(config) = &config {
+            for (path, message) in config.validate() {
+                let span = span_of(document.as_table(), path);
+                diagnostics.push(Diagnostic::error(message, text, span));
+            }
+            for name in config.profiles.keys() {
+                for (path, message) in config.validate_profile(name) {
+                    let span = span_of(document.as_table(), &path);
+                    diagnostics.push(Diagnostic::error(message, text, span));
+                }
+            }
+        }
+
+        match config {
+            Some(config) if !diagnostics.iter().any(|d| d.severity == Severity::Error) => {
+                Ok((config, diagnostics))
+            }
+            _ => Err(diagnostics),
+        }
+    }
+
+    /// This config with profile `name` applied, or `None` if there is no
+    /// such profile
+    ///
+    /// Keys outside `PROFILE_SECTIONS` are ignored. Profiles from `parse`
+    /// always apply; one that doesn't is treated as missing.
+    pub fn with_profile(&self, name: &str) -> Option<Self> {
+        self.apply_profile(name)?.ok()
+    }
+
+    /// This config as it applies with `profile` active; a profile that
+    /// doesn't exist is ignored
+    pub fn active(&self, profile: Option<&str>) -> Self {
+        profile
+            .and_then(|name| self.with_profile(name))
+            .unwrap_or_else(|| self.clone())
+    }
+
+    fn apply_profile(&self, name: &str) -> Option<Result<Self, toml::de::Error>> {
+        let profile = self.profiles.get(name)?;
+        let mut table = toml::Table::try_from(self).expect("config serializes to a table");
+        for section in PROFILE_SECTIONS {
+            if let Some(overrides) = profile.get(*section) {
+                merge(&mut table, section, overrides);
+            }
+        }
+        Some(toml::Value::Table(table).try_into())
+    }
+
+    /// Problems with profile `name`, with the key they are under
+    ///
+    /// Problems the profile inherits from the rest of the file are left to
+    /// `validate`.
+    fn validate_profile<'a>(&self, name: &'a str) -> Vec<(Vec<&'a str>, String)> {
+        let at = |path: &[&'static str]| {
+            let mut full = vec!["profiles", name];
+            full.extend_from_slice(path);
+            full
+        };
+        match self.apply_profile(name) {
+            Some(Ok(applied)) => applied
+                .validate()
+                .into_iter()
+                .filter(|(path, _)| PROFILE_SECTIONS.contains(&path[0]))
+                .map(|(path, message)| (at(path), format!("profile `{}`: {}", name, message)))
+                .collect(),
+            Some(Err(e)) => vec![(at(&[]), format!("profile `{}`: {}", name, e.message()))],
+            None => Vec::new(),
+        }
+    }
+
+    /// Values that parse but make no sense, with the key they are under
+    fn validate(&self) -> Vec<(&'static [&'static str], String)> {
+        let mut problems = Vec::new();
+
+        let chords = [
+            (&["hotkeys", "dictation"][..], self.hotkeys.dictation),
+            (&["hotkeys", "intelligent"][..], self.hotkeys.intelligent),
+            (&["hotkeys", "agent"][..], self.hotkeys.agent),
+        ];
+        for (path, chord) in chords {
+            if chord.is_empty() {
+                problems.push((path, format!("{} needs at least one key", path.join("."))));
+            }
+        }
+
+        if self.timeouts.chord_capture_secs == 0 {
+            problems.push((
+                &["timeouts", "chord_capture_secs"][..],
+                "chord_capture_secs must be at least 1".to_string(),
+            ));
+        }
+
+        if self.ipc.max_clients == 0 {
+            problems.push((&["ipc", "max_clients"][..], "max_clients must be at least 1".to_string()));
+        }
+        if !(1024..=u32::MAX as usize).contains(&self.ipc.max_message_bytes) {
+            problems.push((
+                &["ipc", "max_message_bytes"][..],
+                format!("max_message_bytes must be between 1024 and {}", u32::MAX),
+            ));
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.logging.level) {
+            problems.push((&["logging", "level"][..], format!("invalid log level: {}", e)));
+        }
+
+        let paths = [
+            (&["paths", "data_dir"][..], &self.paths.data_dir),
+            (&["paths", "socket"][..], &self.paths.socket),
+        ];
+        for (path, value) in paths {
+            if !value.as_os_str().is_empty() && !value.is_absolute() {
+                problems.push((path, format!("{} must be an absolute path", path.join("."))));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Set `key` in `table` to `value`, merging into nested tables
+fn merge(table: &mut toml::Table, key: &str, value: &toml::Value) {
+    match (table.get_mut(key), value) {
+        (Some(toml::Value::Table(existing)), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                merge(existing, key, value);
+            }
+        }
+        _ => {
+            table.insert(key.to_string(), value.clone());
+        }
+    }
+}
+
+/// Warn about keys in `table` that `known` (the serialized defaults) lacks
+fn unknown_keys(
+    table: &dyn TableLike,
+    known: &toml::Table,
+    prefix: &str,
+    text: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (name, item) in table.iter() {
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+
+        match known.get(name).or_else(|| known.get(ANY_KEY)) {
+            None => {
+                let span = table.get_key_value(name).and_then(|(key, _)| key.span());
+                diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    format!("unknown key `{}` ignored", path),
+                    text,
+                    span,
+                ));
+            }
+            Some(toml::Value::Table(known)) => {
+                if let Some(table) = item.as_table_like() {
+                    unknown_keys(table, known, &path, text, diagnostics);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Where the value at `path` is in the file, if it is there at all
+fn span_of(table: &dyn TableLike, path: &[&str]) -> Option<Range<usize>> {
+    let (first, rest) = path.split_first()?;
+    let (key, item) = table.get_key_value(first)?;
+    if rest.is_empty() {
+        return item.span().or_else(|| key.span());
+    }
+    match item {
+        Item::Table(table) => span_of(table, rest),
+        Item::Value(value) => span_of(value.as_inline_table()?, rest),
+        _ => None,
+    }
+}
+
+/// 1-based line and column of a byte offset
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::Chord;
+
+    #[test]
+    fn test_empty_file_uses_defaults() {
+        let (config, diagnostics) = ConfigFile::parse("").unwrap();
+        assert_eq!(config, ConfigFile::default());
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let text = r#"
+[hotkeys]
+dictation = ["f18"]
+
+[modes]
+agent = false
+
+[ipc]
+max_clients = 4
+
+[providers.llm]
+backend = "openai"
+"#;
+        let (config, diagnostics) = ConfigFile::parse(text).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_ne!(config.hotkeys.dictation, Chord::CONTROL);
+        assert_eq!(config.hotkeys.agent, Chord::CONTROL_COMMAND);
+        assert!(!config.modes.agent && config.modes.dictation);
+        assert_eq!(config.ipc.max_clients, 4);
+        assert_eq!(config.ipc.max_message_bytes, MAX_MESSAGE_LEN);
+        assert_eq!(config.providers.llm.backend, "openai");
+    }
+
+    #[test]
+    fn test_unknown_keys_warn_with_position() {
+        let text = "[ipc]\nmax_clients = 4\nmax_clinets = 8\n\n[loging]\nlevel = \"debug\"\n";
+        let (config, diagnostics) = ConfigFile::parse(text).unwrap();
+        assert_eq!(config.ipc.max_clients, 4);
+
+        let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 3, column 1: unknown key `ipc.max_clinets` ignored",
+                "line 5, column 2: unknown key `loging` ignored",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let syntax = ConfigFile::parse("[ipc\n").unwrap_err();
+        assert_eq!(syntax[0].line, Some(1));
+
+        let wrong_type = ConfigFile::parse("[timeouts]\nchord_capture_secs = \"soon\"\n").unwrap_err();
+        assert_eq!(wrong_type[0].severity, Severity::Error);
+        assert_eq!((wrong_type[0].line, wrong_type[0].column), (Some(2), Some(22)));
+
+        let invalid = ConfigFile::parse("[hotkeys]\nagent = []\n\n[ipc]\nmax_clients = 0\n").unwrap_err();
+        let lines: Vec<_> = invalid.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [Some(2), Some(5)]);
+        assert!(invalid[0].message.contains("hotkeys.agent"));
+    }
+
+    #[test]
+    fn test_profiles_override_sections() {
+        let text = r#"
+[modes]
+agent = false
+
+[providers.llm]
+backend = "openai"
+model = "gpt-4o"
+
+[profiles.work.hotkeys]
+dictation = ["f18"]
+
+[profiles.work.providers.llm]
+backend = "ollama"
+
+[profiles.work.privacy]
+allow_cloud = false
+
+[profiles.work.ipc]
+max_clients = 1
+"#;
+        let (config, diagnostics) = ConfigFile::parse(text).unwrap();
+        let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(messages, ["line 18, column 16: unknown key `profiles.work.ipc` ignored"]);
+
+        let work = config.with_profile("work").unwrap();
+        assert_ne!(work.hotkeys.dictation, config.hotkeys.dictation);
+        assert_eq!(work.hotkeys.agent, config.hotkeys.agent);
+        assert!(!work.modes.agent);
+        assert_eq!((work.providers.llm.backend.as_str(), work.providers.llm.model.as_str()), ("ollama", "gpt-4o"));
+        assert!(!work.privacy.allow_cloud && config.privacy.allow_cloud);
+        assert_eq!(work.ipc, config.ipc);
+
+        assert!(config.with_profile("home").is_none());
+        assert_eq!(config.active(Some("home")), config);
+        assert_eq!(config.active(Some("work")), work);
+    }
+
+    #[test]
+    fn test_invalid_profiles_are_errors() {
+        let text = "[profiles.home.hotkeys]\nagent = []\n\n[profiles.work]\nmodes = { agent = \"no\" }\n";
+        let errors = ConfigFile::parse(text).unwrap_err();
+        let lines: Vec<_> = errors.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [Some(2), Some(4)]);
+        assert!(errors[0].message.starts_with("profile `home`: hotkeys.agent"));
+        assert!(errors[1].message.starts_with("profile `work`: "));
+    }
+
+    #[test]
+    fn test_example_file_matches_defaults() {
+        let text = include_str!("../../resources/config.example.toml");
+        let (config, diagnostics) = ConfigFile::parse(text).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(config, ConfigFile::default());
+    }
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let path = std::env::temp_dir().join("second-brain-config-missing.toml");
+        let (config, diagnostics) = ConfigFile::load(&path);
+        assert_eq!(config, ConfigFile::default());
+        assert!(diagnostics.is_empty());
+    }
+}
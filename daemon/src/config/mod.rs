@@ -0,0 +1,203 @@
+//! Configuration loading and management
+//!
+//! `Config` holds where things live plus debug switches from the
+//! environment; the user-edited settings come from `config.toml` in the
+//! config directory, which is re-read when it changes or on SIGHUP.
+//! Command-line flags and environment variables (`Overrides`) take
+//! precedence over the file, which takes precedence over the platform
+//! defaults from `Paths`.
+
+mod diff;
+mod file;
+mod paths;
+mod watch;
+
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use tracing_subscriber::EnvFilter;
+
+pub use diff::ConfigDiff;
+pub use file::{ConfigFile, Diagnostic, Severity};
+pub use paths::Paths;
+pub use watch::ConfigWatcher;
+
+/// Daemon configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Path to the Unix domain socket for IPC
+    pub socket_path: PathBuf,
+    
+    /// Directory for settings and other state kept across restarts
+    pub data_dir: PathBuf,
+
+    /// Path to the persisted runtime settings
+    pub settings_path: PathBuf,
+
+    /// Path to `config.toml`
+    pub config_path: PathBuf,
+
+    /// Settings from `config.toml`, or defaults if it has errors
+    pub file: ConfigFile,
+
+    /// Problems found in `config.toml`
+    pub diagnostics: Vec<Diagnostic>,
+
+    /// Flags and environment variables applied on top of `config.toml`
+    pub overrides: Overrides,
+
+    /// Platform default locations
+    pub paths: Paths,
+
+    /// Optional JSONL file to record the hotkey event stream to
+    /// (set via `SECOND_BRAIN_RECORD_HOTKEYS`)
+    pub record_hotkeys: Option<PathBuf>,
+
+    /// Take hotkey input from `InjectInput` requests instead of the
+    /// keyboard; for end-to-end tests only
+    /// (set via `SECOND_BRAIN_DEBUG_INJECT_INPUT=1`)
+    pub inject_input: bool,
+}
+
+/// Settings given on the command line or in the environment
+///
+/// Each one replaces the matching `config.toml` value.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    /// Use this file instead of the default `config.toml`
+    pub config_path: Option<PathBuf>,
+    /// `paths.socket`
+    pub socket: Option<PathBuf>,
+    /// `paths.data_dir`
+    pub data_dir: Option<PathBuf>,
+    /// `logging.level`
+    pub log_level: Option<String>,
+}
+
+impl Overrides {
+    fn apply(&self, file: &mut ConfigFile) {
+        if let Some(socket) = &self.socket {
+            file.paths.socket = socket.clone();
+        }
+        if let Some(data_dir) = &self.data_dir {
+            file.paths.data_dir = data_dir.clone();
+        }
+        if let Some(level) = &self.log_level {
+            file.logging.level = level.clone();
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from `config.toml`, `overrides` and defaults
+    ///
+    /// Problems in the file end up in `diagnostics`; only invalid
+    /// overrides are errors, since they come from whoever started us.
+    pub fn load(overrides: &Overrides) -> Result<Self> {
+        if let Some(level) = &overrides.log_level {
+            EnvFilter::try_new(level).with_context(|| format!("invalid log level '{}'", level))?;
+        }
+
+        let paths = Paths::from_env()?;
+        let config_path = overrides
+            .config_path
+            .clone()
+            .unwrap_or_else(|| paths.config_file());
+        let (mut file, diagnostics) = ConfigFile::load(&config_path);
+        overrides.apply(&mut file);
+
+        let data_dir = if file.paths.data_dir.as_os_str().is_empty() {
+            paths.state_dir.clone()
+        } else {
+            file.paths.data_dir.clone()
+        };
+
+        let socket_path = if file.paths.socket.as_os_str().is_empty() {
+            paths.socket()
+        } else {
+            file.paths.socket.clone()
+        };
+        let settings_path = data_dir.join("settings.json");
+
+        let record_hotkeys = std::env::var_os("SECOND_BRAIN_RECORD_HOTKEYS")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+
+        let inject_input = std::env::var("SECOND_BRAIN_DEBUG_INJECT_INPUT")
+            .is_ok_and(|value| value == "1");
+
+        Ok(Self {
+            socket_path,
+            data_dir,
+            settings_path,
+            config_path,
+            file,
+            diagnostics,
+            overrides: overrides.clone(),
+            paths,
+            record_hotkeys,
+            inject_input,
+        })
+    }
+
+    /// Re-read `config.toml`, with the overrides applied again
+    pub fn reload_file(&self) -> (ConfigFile, Vec<Diagnostic>) {
+        let (mut file, diagnostics) = ConfigFile::load(&self.config_path);
+        self.overrides.apply(&mut file);
+        (file, diagnostics)
+    }
+
+    /// The config in effect, with default paths filled in
+    pub fn effective(&self) -> ConfigFile {
+        let mut file = self.file.clone();
+        file.paths.data_dir = self.data_dir.clone();
+        file.paths.socket = self.socket_path.clone();
+        file
+    }
+
+    /// Ensure data directory exists
+    pub fn ensure_dirs(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.data_dir)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_load() {
+        let config = Config::load(&Overrides::default()).unwrap();
+        assert!(config.socket_path.to_string_lossy().contains("second-brain"));
+    }
+
+    #[test]
+    fn test_overrides_win() {
+        let dir = std::env::temp_dir().join(format!("second-brain-overrides-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.toml");
+        std::fs::write(&config_path, "[paths]\nsocket = \"/tmp/from-file.sock\"\n\n[logging]\nlevel = \"warn\"\n").unwrap();
+
+        let overrides = Overrides {
+            config_path: Some(config_path.clone()),
+            data_dir: Some(dir.join("data")),
+            log_level: Some("debug".to_string()),
+            ..Default::default()
+        };
+        let config = Config::load(&overrides).unwrap();
+        let (reloaded, _) = config.reload_file();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.socket_path, PathBuf::from("/tmp/from-file.sock"));
+        assert_eq!(config.settings_path, dir.join("data/settings.json"));
+        assert_eq!(config.file.logging.level, "debug");
+        assert_eq!(reloaded, config.file);
+        assert_eq!(config.effective().paths.data_dir, dir.join("data"));
+
+        let invalid = Overrides {
+            log_level: Some("debug,=".to_string()),
+            ..overrides
+        };
+        assert!(Config::load(&invalid).is_err());
+    }
+}
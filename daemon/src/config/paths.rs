@@ -0,0 +1,256 @@
+//! Where the daemon's files live by default
+//!
+//! Follows the XDG base-directory spec where its variables are set, and the
+//! platform's conventions otherwise:
+//!
+//! | kind    | XDG variable      | Linux default          | macOS default                     |
+//! |---------|-------------------|------------------------|-----------------------------------|
+//! | config  | `XDG_CONFIG_HOME` | `~/.config`            | `~/Library/Application Support`   |
+//! | state   | `XDG_STATE_HOME`  | `~/.local/state`       | `~/Library/Application Support`   |
+//! | runtime | `XDG_RUNTIME_DIR` | state directory        | per-user temporary directory      |
+//!
+//! Each gets a `second-brain` subdirectory. The per-user temporary
+//! directory is the one `getconf DARWIN_USER_TEMP_DIR` prints, which is
+//! the same for a launchd agent and an app, unlike `$TMPDIR`. Earlier
+//! versions kept everything in `~/.local/share/second-brain` (and
+//! `config.toml` in `~/.config/second-brain`); `migrate` moves files from
+//! there.
+//!
+//! This is the one place the socket's location is defined; clients look
+//! for it in the same order:
+//!
+//! 1. `$SECOND_BRAIN_SOCKET` (or `--socket` for the daemon and CLI)
+//! 2. `paths.socket` in `config.toml`, read by the daemon and CLI only;
+//!    the app needs `SECOND_BRAIN_SOCKET` set to follow it
+//! 3. `daemon.sock` in the runtime directory above
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+const APP_DIR: &str = "second-brain";
+
+/// Default directories, before `config.toml` and flags are applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    /// Holds `config.toml`
+    pub config_dir: PathBuf,
+    /// Holds `settings.json` and other state kept across restarts
+    pub state_dir: PathBuf,
+    /// Holds the IPC socket
+    pub runtime_dir: PathBuf,
+    /// Where earlier versions kept `settings.json` and the socket
+    pub legacy_data_dir: PathBuf,
+    /// Where earlier versions kept `config.toml`
+    pub legacy_config_dir: PathBuf,
+}
+
+impl Paths {
+    /// Resolve the directories for this platform from the environment
+    pub fn from_env() -> Result<Self> {
+        Self::resolve(cfg!(target_os = "macos"), user_temp_dir(), |name| std::env::var_os(name))
+    }
+
+    fn resolve(
+        macos: bool,
+        user_temp_dir: Option<PathBuf>,
+        var: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<Self> {
+        let home = var("HOME")
+            .filter(|home| !home.is_empty())
+            .map(PathBuf::from)
+            .context("HOME is not set")?;
+        // The spec says relative values are invalid and should be ignored
+        let xdg = |name: &str| {
+            var(name)
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+        };
+
+        let platform_dir = if macos {
+            home.join("Library").join("Application Support")
+        } else {
+            home.join(".config")
+        };
+        let config_dir = xdg("XDG_CONFIG_HOME").unwrap_or(platform_dir).join(APP_DIR);
+
+        let platform_dir = if macos {
+            home.join("Library").join("Application Support")
+        } else {
+            home.join(".local").join("state")
+        };
+        let state_dir = xdg("XDG_STATE_HOME").unwrap_or(platform_dir).join(APP_DIR);
+
+        let runtime_dir = match xdg("XDG_RUNTIME_DIR") {
+            Some(dir) => dir.join(APP_DIR),
+            None if macos => user_temp_dir
+                .or_else(|| var("TMPDIR").map(PathBuf::from).filter(|path| path.is_absolute()))
+                .unwrap_or_else(std::env::temp_dir)
+                .join(APP_DIR),
+            // Without a runtime directory, fall back to one only this user
+            // can write to rather than the shared /tmp
+            None => state_dir.clone(),
+        };
+
+        Ok(Self {
+            config_dir,
+            state_dir,
+            runtime_dir,
+            legacy_data_dir: home.join(".local").join("share").join(APP_DIR),
+            legacy_config_dir: home.join(".config").join(APP_DIR),
+        })
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join("config.toml")
+    }
+
+    pub fn socket(&self) -> PathBuf {
+        self.runtime_dir.join("daemon.sock")
+    }
+
+    /// Move `config.toml` from where earlier versions kept it, unless the
+    /// new location already has one
+    ///
+    /// Returns the files moved as `(from, to)`.
+    pub fn migrate_config(&self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        migrate(&self.legacy_config_dir, &self.config_dir, &["config.toml"])
+    }
+
+    /// Move `settings.json` from where earlier versions kept it into
+    /// `data_dir`, unless it already has one
+    pub fn migrate_state(&self, data_dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+        migrate(&self.legacy_data_dir, data_dir, &["settings.json"])
+    }
+}
+
+/// The temporary directory macOS keeps for the user, whatever `TMPDIR` says
+#[cfg(target_os = "macos")]
+fn user_temp_dir() -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+
+    let mut buffer = vec![0u8; libc::PATH_MAX as usize];
+    // SAFETY: confstr writes at most `buffer.len()` bytes into `buffer`
+    let len = unsafe {
+        libc::confstr(libc::_CS_DARWIN_USER_TEMP_DIR, buffer.as_mut_ptr().cast(), buffer.len())
+    };
+    // Zero on failure; longer than the buffer if it didn't fit
+    if len == 0 || len > buffer.len() {
+        return None;
+    }
+    buffer.truncate(len - 1);
+    Some(PathBuf::from(OsString::from_vec(buffer)))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn user_temp_dir() -> Option<PathBuf> {
+    None
+}
+
+/// Move each of `files` from `from` to `to` if it exists only in `from`
+fn migrate(from: &Path, to: &Path, files: &[&str]) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved = Vec::new();
+    if from == to {
+        return Ok(moved);
+    }
+
+    for name in files {
+        let (old, new) = (from.join(name), to.join(name));
+        if !old.is_file() || new.exists() {
+            continue;
+        }
+        std::fs::create_dir_all(to)
+            .with_context(|| format!("failed to create {}", to.display()))?;
+        // Renaming fails across filesystems; copy and remove instead
+        if std::fs::rename(&old, &new).is_err() {
+            std::fs::copy(&old, &new)
+                .with_context(|| format!("failed to copy {} to {}", old.display(), new.display()))?;
+            std::fs::remove_file(&old)
+                .with_context(|| format!("failed to remove {}", old.display()))?;
+        }
+        moved.push((old, new));
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(macos: bool, vars: &[(&str, &str)]) -> Paths {
+        Paths::resolve(macos, None, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| OsString::from(value))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_platform_defaults() {
+        let linux = resolve(false, &[("HOME", "/home/u")]);
+        assert_eq!(linux.config_file(), PathBuf::from("/home/u/.config/second-brain/config.toml"));
+        assert_eq!(linux.state_dir, PathBuf::from("/home/u/.local/state/second-brain"));
+        assert_eq!(linux.socket(), PathBuf::from("/home/u/.local/state/second-brain/daemon.sock"));
+
+        let macos = resolve(true, &[("HOME", "/Users/u"), ("TMPDIR", "/var/folders/x/T/")]);
+        assert_eq!(
+            macos.config_file(),
+            PathBuf::from("/Users/u/Library/Application Support/second-brain/config.toml")
+        );
+        assert_eq!(macos.state_dir, PathBuf::from("/Users/u/Library/Application Support/second-brain"));
+        assert_eq!(macos.socket(), PathBuf::from("/var/folders/x/T/second-brain/daemon.sock"));
+        assert_eq!(macos.legacy_data_dir, PathBuf::from("/Users/u/.local/share/second-brain"));
+
+        // The user's temporary directory wins over whatever TMPDIR says
+        let vars = [("HOME", "/Users/u"), ("TMPDIR", "/tmp/")];
+        let macos = Paths::resolve(true, Some("/var/folders/x/T/".into()), |name| {
+            vars.iter().find(|(key, _)| *key == name).map(|(_, value)| OsString::from(value))
+        })
+        .unwrap();
+        assert_eq!(macos.socket(), PathBuf::from("/var/folders/x/T/second-brain/daemon.sock"));
+    }
+
+    #[test]
+    fn test_xdg_variables_win() {
+        let vars = [
+            ("HOME", "/home/u"),
+            ("XDG_CONFIG_HOME", "/cfg"),
+            ("XDG_STATE_HOME", "/state"),
+            ("XDG_RUNTIME_DIR", "/run/user/1000"),
+        ];
+        for macos in [false, true] {
+            let paths = resolve(macos, &vars);
+            assert_eq!(paths.config_dir, PathBuf::from("/cfg/second-brain"));
+            assert_eq!(paths.state_dir, PathBuf::from("/state/second-brain"));
+            assert_eq!(paths.socket(), PathBuf::from("/run/user/1000/second-brain/daemon.sock"));
+        }
+
+        // Relative values are ignored
+        let paths = resolve(false, &[("HOME", "/home/u"), ("XDG_CONFIG_HOME", "cfg")]);
+        assert_eq!(paths.config_dir, PathBuf::from("/home/u/.config/second-brain"));
+
+        assert!(Paths::resolve(false, None, |_| None).is_err());
+    }
+
+    #[test]
+    fn test_migrate_moves_missing_files_only() {
+        let dir = std::env::temp_dir().join(format!("second-brain-migrate-{}", std::process::id()));
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        std::fs::create_dir_all(&old).unwrap();
+        std::fs::write(old.join("settings.json"), "{}").unwrap();
+        std::fs::write(old.join("config.toml"), "old").unwrap();
+        std::fs::create_dir_all(&new).unwrap();
+        std::fs::write(new.join("config.toml"), "new").unwrap();
+
+        let moved = migrate(&old, &new, &["settings.json", "config.toml", "missing"]).unwrap();
+        let settings_moved = !old.join("settings.json").exists() && new.join("settings.json").exists();
+        let config_kept = std::fs::read_to_string(new.join("config.toml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(moved, [(old.join("settings.json"), new.join("settings.json"))]);
+        assert!(settings_moved);
+        assert_eq!(config_kept, "new");
+    }
+}
@@ -0,0 +1,69 @@
+//! Notices edits to `config.toml`
+//!
+//! Polls the file's contents rather than relying on filesystem events,
+//! which also catches editors that save by replacing the file and a file
+//! created after the daemon started.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often the file is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches one file for changes
+pub struct ConfigWatcher {
+    path: PathBuf,
+    interval: Duration,
+    /// Contents when last checked, `None` while the file doesn't exist
+    last: Option<Vec<u8>>,
+}
+
+impl ConfigWatcher {
+    /// Watch `path`, taking its current contents as seen
+    pub fn new(path: &Path) -> Self {
+        Self::with_interval(path, POLL_INTERVAL)
+    }
+
+    fn with_interval(path: &Path, interval: Duration) -> Self {
+        Self {
+            path: path.to_owned(),
+            interval,
+            last: std::fs::read(path).ok(),
+        }
+    }
+
+    /// Wait until the file is created, changed or removed
+    pub async fn changed(&mut self) {
+        loop {
+            tokio::time::sleep(self.interval).await;
+            let contents = std::fs::read(&self.path).ok();
+            if contents != self.last {
+                self.last = contents;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_are_noticed() {
+        let path = std::env::temp_dir().join(format!("second-brain-watch-{}.toml", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut watcher = ConfigWatcher::with_interval(&path, Duration::from_millis(10));
+
+        std::fs::write(&path, "[ipc]\n").unwrap();
+        watcher.changed().await;
+
+        // Rewriting the same contents is not a change
+        std::fs::write(&path, "[ipc]\n").unwrap();
+        let unchanged = tokio::time::timeout(Duration::from_millis(50), watcher.changed()).await;
+        assert!(unchanged.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        watcher.changed().await;
+    }
+}
@@ -6,7 +6,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Events emitted by the state machine during transitions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum StateEvent {
     /// Entered dictation mode (Control held)
@@ -0,0 +1,42 @@
+//! Mode bindings: which chord triggers which mode
+
+use serde::{Deserialize, Serialize};
+
+use super::chord::Chord;
+use super::trigger::TriggerKeys;
+
+/// The chords bound to each mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    /// Held for Dictation mode
+    pub dictation: Chord,
+    /// Held for Intelligent mode
+    pub intelligent: Chord,
+    /// Pressed to toggle Agent mode
+    pub agent: Chord,
+    /// Keep bound trigger keys from reaching other apps, where the
+    /// platform allows it
+    pub swallow_trigger_keys: bool,
+}
+
+impl Bindings {
+    /// Every trigger key used by any binding
+    pub fn trigger_keys(&self) -> TriggerKeys {
+        self.dictation
+            .keys()
+            .union(self.intelligent.keys())
+            .union(self.agent.keys())
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            dictation: Chord::CONTROL,
+            intelligent: Chord::CONTROL_OPTION,
+            agent: Chord::CONTROL_COMMAND,
+            swallow_trigger_keys: true,
+        }
+    }
+}
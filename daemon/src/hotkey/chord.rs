@@ -0,0 +1,275 @@
+//! Chords: sets of modifier and trigger keys that start a mode
+//!
+//! A chord is the unit that bindings and the transition table are written
+//! in. Whether a chord is currently pressed is answered by `ModifierState`.
+
+use serde::{Deserialize, Serialize};
+
+use super::trigger::{TriggerKey, TriggerKeys};
+
+/// A single modifier key that can be part of a chord
+///
+/// The plain variants match either side of the keyboard; the `Left`/`Right`
+/// variants match only that physical key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    /// Control key (either side)
+    Control,
+    /// Left Control key
+    LeftControl,
+    /// Right Control key
+    RightControl,
+    /// Option/Alt key (either side)
+    Option,
+    /// Left Option key
+    LeftOption,
+    /// Right Option key
+    RightOption,
+    /// Shift key (either side)
+    Shift,
+    /// Left Shift key
+    LeftShift,
+    /// Right Shift key
+    RightShift,
+    /// Command key (either side)
+    Command,
+    /// Left Command key
+    LeftCommand,
+    /// Right Command key
+    RightCommand,
+    /// Fn/Globe key
+    #[serde(rename = "fn")]
+    Function,
+}
+
+impl Modifier {
+    /// All modifiers, in display order
+    pub const ALL: [Modifier; 13] = [
+        Modifier::Control,
+        Modifier::LeftControl,
+        Modifier::RightControl,
+        Modifier::Option,
+        Modifier::LeftOption,
+        Modifier::RightOption,
+        Modifier::Shift,
+        Modifier::LeftShift,
+        Modifier::RightShift,
+        Modifier::Command,
+        Modifier::LeftCommand,
+        Modifier::RightCommand,
+        Modifier::Function,
+    ];
+
+    /// Short label used when rendering chords
+    pub fn label(&self) -> &'static str {
+        match self {
+            Modifier::Control => "Ctrl",
+            Modifier::LeftControl => "LCtrl",
+            Modifier::RightControl => "RCtrl",
+            Modifier::Option => "Opt",
+            Modifier::LeftOption => "LOpt",
+            Modifier::RightOption => "ROpt",
+            Modifier::Shift => "Shift",
+            Modifier::LeftShift => "LShift",
+            Modifier::RightShift => "RShift",
+            Modifier::Command => "Cmd",
+            Modifier::LeftCommand => "LCmd",
+            Modifier::RightCommand => "RCmd",
+            Modifier::Function => "Fn",
+        }
+    }
+
+    /// The either-side modifier this key belongs to
+    pub fn generic(self) -> Modifier {
+        match self {
+            Modifier::LeftControl | Modifier::RightControl => Modifier::Control,
+            Modifier::LeftOption | Modifier::RightOption => Modifier::Option,
+            Modifier::LeftShift | Modifier::RightShift => Modifier::Shift,
+            Modifier::LeftCommand | Modifier::RightCommand => Modifier::Command,
+            other => other,
+        }
+    }
+
+    /// Whether this names one physical side rather than either side
+    pub fn is_sided(self) -> bool {
+        self.generic() != self
+    }
+
+    const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// A set of modifier and trigger keys that are pressed together
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "Vec<ChordKey>", into = "Vec<ChordKey>")]
+pub struct Chord {
+    bits: u16,
+    keys: TriggerKeys,
+}
+
+/// One entry of a chord's serialized key list
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ChordKey {
+    Modifier(Modifier),
+    Trigger(TriggerKey),
+}
+
+impl Chord {
+    /// Control alone (default Dictation binding)
+    pub const CONTROL: Chord = Chord::new(&[Modifier::Control]);
+    /// Control + Option (default Intelligent binding)
+    pub const CONTROL_OPTION: Chord = Chord::new(&[Modifier::Control, Modifier::Option]);
+    /// Control + Command (default Agent toggle)
+    pub const CONTROL_COMMAND: Chord = Chord::new(&[Modifier::Control, Modifier::Command]);
+
+    /// Create a chord from a list of modifiers
+    pub const fn new(modifiers: &[Modifier]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < modifiers.len() {
+            bits |= modifiers[i].bit();
+            i += 1;
+        }
+        Self {
+            bits,
+            keys: TriggerKeys::new(&[]),
+        }
+    }
+
+    /// Create a chord from modifiers plus trigger keys
+    pub const fn with_keys(modifiers: &[Modifier], keys: &[TriggerKey]) -> Self {
+        Self {
+            keys: TriggerKeys::new(keys),
+            ..Self::new(modifiers)
+        }
+    }
+
+    /// Check whether the chord includes the given modifier
+    pub fn contains(&self, modifier: Modifier) -> bool {
+        self.bits & modifier.bit() != 0
+    }
+
+    /// Check whether holding the chord implies `modifier` is held too
+    ///
+    /// A sided key implies its either-side modifier and vice versa, so
+    /// "Right Option" covers Option, and "Option" covers either side.
+    pub fn covers(&self, modifier: Modifier) -> bool {
+        if self.contains(modifier) {
+            return true;
+        }
+        if modifier.is_sided() {
+            return self.contains(modifier.generic());
+        }
+        self.modifiers()
+            .into_iter()
+            .any(|m| m.is_sided() && m.generic() == modifier)
+    }
+
+    /// Check whether the chord has no keys
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0 && self.keys.is_empty()
+    }
+
+    /// The modifiers in this chord, in display order
+    pub fn modifiers(&self) -> Vec<Modifier> {
+        Modifier::ALL
+            .into_iter()
+            .filter(|m| self.contains(*m))
+            .collect()
+    }
+
+    /// The trigger keys in this chord
+    pub fn keys(&self) -> TriggerKeys {
+        self.keys
+    }
+
+    /// Keys in either chord
+    pub fn union(self, other: Chord) -> Chord {
+        Self {
+            bits: self.bits | other.bits,
+            keys: self.keys.union(other.keys),
+        }
+    }
+}
+
+impl From<Vec<ChordKey>> for Chord {
+    fn from(entries: Vec<ChordKey>) -> Self {
+        let mut chord = Chord::default();
+        for entry in entries {
+            match entry {
+                ChordKey::Modifier(modifier) => chord.bits |= modifier.bit(),
+                ChordKey::Trigger(key) => chord.keys.set(key, true),
+            }
+        }
+        chord
+    }
+}
+
+impl From<Chord> for Vec<ChordKey> {
+    fn from(chord: Chord) -> Self {
+        let modifiers = chord.modifiers().into_iter().map(ChordKey::Modifier);
+        let keys = chord.keys.keys().into_iter().map(ChordKey::Trigger);
+        modifiers.chain(keys).collect()
+    }
+}
+
+impl std::fmt::Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let labels: Vec<&str> = self
+            .modifiers()
+            .iter()
+            .map(Modifier::label)
+            .chain(self.keys.keys().iter().map(TriggerKey::label))
+            .collect();
+        write!(f, "{}", labels.join("+"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chord_display() {
+        assert_eq!(Chord::CONTROL.to_string(), "Ctrl");
+        assert_eq!(Chord::CONTROL_COMMAND.to_string(), "Ctrl+Cmd");
+    }
+
+    #[test]
+    fn test_chord_serialization() {
+        let json = serde_json::to_string(&Chord::CONTROL_OPTION).unwrap();
+        assert_eq!(json, r#"["control","option"]"#);
+
+        let chord: Chord = serde_json::from_str(r#"["command","control"]"#).unwrap();
+        assert_eq!(chord, Chord::CONTROL_COMMAND);
+
+        let chord: Chord = serde_json::from_str(r#"["right_option","fn"]"#).unwrap();
+        assert_eq!(chord.to_string(), "ROpt+Fn");
+    }
+
+    #[test]
+    fn test_trigger_key_chords() {
+        let chord: Chord = serde_json::from_str(r#"["shift","f18"]"#).unwrap();
+        assert_eq!(chord, Chord::with_keys(&[Modifier::Shift], &[TriggerKey::F18]));
+        assert_eq!(chord.to_string(), "Shift+F18");
+        assert_eq!(serde_json::to_string(&chord).unwrap(), r#"["shift","f18"]"#);
+
+        let mouse: Chord = serde_json::from_str(r#"["mouse4"]"#).unwrap();
+        assert!(mouse.modifiers().is_empty() && !mouse.is_empty());
+    }
+
+    #[test]
+    fn test_sided_coverage() {
+        let right_option = Chord::new(&[Modifier::RightOption]);
+        assert!(right_option.covers(Modifier::Option));
+        assert!(!right_option.covers(Modifier::LeftOption));
+
+        let option = Chord::new(&[Modifier::Option]);
+        assert!(option.covers(Modifier::LeftOption));
+        assert!(option.covers(Modifier::RightOption));
+        assert!(!option.covers(Modifier::Shift));
+    }
+}
@@ -0,0 +1,99 @@
+//! Conflicts between bound chords and shortcuts that already exist
+//!
+//! Chords are held on their own, so only shortcuts that fire on modifier
+//! or trigger keys alone can collide: the Globe key, Super opening the
+//! launcher, middle-click paste and the like. Ordinary app shortcuts
+//! (modifiers plus a regular key) are caught at runtime instead, by
+//! counting how often a bound chord is held when a regular key is pressed.
+
+use super::chord::{Chord, Modifier};
+use super::keys::ModifierState;
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+use super::trigger::TriggerKey;
+
+/// A shortcut the OS or common apps trigger with a chord alone
+#[derive(Debug, Clone, Copy)]
+pub struct KnownShortcut {
+    pub chord: Chord,
+    pub description: &'static str,
+}
+
+const fn shortcut(chord: Chord, description: &'static str) -> KnownShortcut {
+    KnownShortcut { chord, description }
+}
+
+#[cfg(target_os = "macos")]
+const PLATFORM_SHORTCUTS: &[KnownShortcut] = &[
+    shortcut(
+        Chord::new(&[Modifier::Function]),
+        "Fn/Globe opens the emoji picker, switches input source or starts Dictation",
+    ),
+    shortcut(
+        Chord::with_keys(&[], &[TriggerKey::CapsLock]),
+        "CapsLock switches input source when \"Use Caps Lock to switch\" is on",
+    ),
+];
+
+#[cfg(target_os = "linux")]
+const PLATFORM_SHORTCUTS: &[KnownShortcut] = &[
+    shortcut(
+        Chord::new(&[Modifier::Command]),
+        "Super opens the GNOME Activities overview or the KDE launcher",
+    ),
+    shortcut(
+        Chord::new(&[Modifier::Option]),
+        "Alt focuses the menu bar in many apps",
+    ),
+    shortcut(
+        Chord::with_keys(&[], &[TriggerKey::Mouse3]),
+        "Middle click pastes the primary selection",
+    ),
+];
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+const PLATFORM_SHORTCUTS: &[KnownShortcut] = &[];
+
+const COMMON_SHORTCUTS: &[KnownShortcut] = &[shortcut(
+    Chord::new(&[Modifier::Shift]),
+    "Shift pressed five times turns on Sticky Keys",
+)];
+
+/// Known shortcuts that `chord` would also trigger
+pub fn system_conflicts(chord: Chord) -> Vec<KnownShortcut> {
+    PLATFORM_SHORTCUTS
+        .iter()
+        .chain(COMMON_SHORTCUTS)
+        .filter(|shortcut| overlaps(chord, shortcut.chord))
+        .copied()
+        .collect()
+}
+
+/// Whether some set of held keys would match both chords exactly
+///
+/// "Option" and "Right Option" overlap, since holding Right Option matches
+/// both; "Control" and "Control + Option" don't.
+pub fn overlaps(a: Chord, b: Chord) -> bool {
+    ModifierState::pressing(a).holds_exactly(b) || ModifierState::pressing(b).holds_exactly(a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlaps() {
+        let option = Chord::new(&[Modifier::Option]);
+        let right_option = Chord::new(&[Modifier::RightOption]);
+        let left_option = Chord::new(&[Modifier::LeftOption]);
+        assert!(overlaps(option, right_option));
+        assert!(overlaps(Chord::CONTROL, Chord::CONTROL));
+        assert!(!overlaps(left_option, right_option));
+        assert!(!overlaps(Chord::CONTROL, Chord::CONTROL_OPTION));
+    }
+
+    #[test]
+    fn test_system_conflicts() {
+        assert!(system_conflicts(Chord::CONTROL).is_empty());
+        assert!(!system_conflicts(Chord::new(&[Modifier::LeftShift])).is_empty());
+    }
+}
@@ -1,24 +1,22 @@
-//! Modifier key definitions and state tracking
+//! Modifier key state tracking
 //!
-//! Provides constants for macOS modifier key flags and a struct
-//! for tracking the current state of modifier keys.
+//! Provides a platform-neutral struct for tracking the current state of
+//! modifier and trigger keys. Each hotkey source translates its native
+//! events into it.
 
-use core_graphics::event::CGEventFlags;
+use serde::{Deserialize, Serialize};
 
-/// Modifier key flag masks from macOS CGEventFlags
-pub mod flags {
-    use core_graphics::event::CGEventFlags;
+use super::chord::{Chord, Modifier};
+use super::trigger::TriggerKeys;
 
-    /// Control key modifier flag
-    pub const CONTROL: CGEventFlags = CGEventFlags::CGEventFlagControl;
-    /// Option/Alt key modifier flag  
-    pub const OPTION: CGEventFlags = CGEventFlags::CGEventFlagAlternate;
-    /// Command key modifier flag
-    pub const COMMAND: CGEventFlags = CGEventFlags::CGEventFlagCommand;
-}
-
-/// Tracks which modifier keys are currently pressed
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Tracks which modifier and trigger keys are currently pressed
+///
+/// The either-side fields (`control`, `option`, `shift`, `command`) are
+/// set whenever a key of that kind is held. The sided fields are only set
+/// by sources that can tell the keys apart, so older recordings without
+/// them still replay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ModifierState {
     /// Control key is held
     pub control: bool,
@@ -26,48 +24,135 @@ pub struct ModifierState {
     pub option: bool,
     /// Command key is held
     pub command: bool,
+    /// Shift key is held
+    pub shift: bool,
+    /// Fn/Globe key is held
+    pub function: bool,
+    /// Left Control key is held
+    pub left_control: bool,
+    /// Right Control key is held
+    pub right_control: bool,
+    /// Left Option key is held
+    pub left_option: bool,
+    /// Right Option key is held
+    pub right_option: bool,
+    /// Left Shift key is held
+    pub left_shift: bool,
+    /// Right Shift key is held
+    pub right_shift: bool,
+    /// Left Command key is held
+    pub left_command: bool,
+    /// Right Command key is held
+    pub right_command: bool,
+    /// Trigger keys that are held (only bound keys are reported)
+    #[serde(skip_serializing_if = "TriggerKeys::is_empty")]
+    pub keys: TriggerKeys,
 }
 
 impl ModifierState {
-    /// Create a new ModifierState from CGEventFlags
-    pub fn from_flags(flags: CGEventFlags) -> Self {
-        Self {
-            control: flags.contains(flags::CONTROL),
-            option: flags.contains(flags::OPTION),
-            command: flags.contains(flags::COMMAND),
+    /// The state of holding exactly the keys of `chord`
+    pub fn pressing(chord: Chord) -> Self {
+        let mut state = Self {
+            keys: chord.keys(),
+            ..Default::default()
+        };
+        for modifier in chord.modifiers() {
+            state.press(modifier);
+        }
+        state
+    }
+
+    fn flag(&mut self, modifier: Modifier) -> &mut bool {
+        match modifier {
+            Modifier::Control => &mut self.control,
+            Modifier::LeftControl => &mut self.left_control,
+            Modifier::RightControl => &mut self.right_control,
+            Modifier::Option => &mut self.option,
+            Modifier::LeftOption => &mut self.left_option,
+            Modifier::RightOption => &mut self.right_option,
+            Modifier::Shift => &mut self.shift,
+            Modifier::LeftShift => &mut self.left_shift,
+            Modifier::RightShift => &mut self.right_shift,
+            Modifier::Command => &mut self.command,
+            Modifier::LeftCommand => &mut self.left_command,
+            Modifier::RightCommand => &mut self.right_command,
+            Modifier::Function => &mut self.function,
         }
     }
 
-    /// Check if all modifiers are released
-    pub fn is_empty(&self) -> bool {
-        !self.control && !self.option && !self.command
+    /// Mark a key as held, including its either-side modifier
+    pub fn press(&mut self, modifier: Modifier) {
+        *self.flag(modifier) = true;
+        *self.flag(modifier.generic()) = true;
     }
 
-    /// Check if only Control is pressed (for Dictation mode)
-    pub fn is_control_only(&self) -> bool {
-        self.control && !self.option && !self.command
+    /// Check if a single modifier is held
+    ///
+    /// An either-side modifier counts as held when either key is.
+    pub fn is_held(&self, modifier: Modifier) -> bool {
+        self.raw(modifier)
+            || (!modifier.is_sided()
+                && Modifier::ALL
+                    .into_iter()
+                    .any(|m| m.is_sided() && m.generic() == modifier && self.raw(m)))
     }
 
-    /// Check if Control + Option are pressed (for Intelligent mode)
-    pub fn is_control_option(&self) -> bool {
-        self.control && self.option && !self.command
+    /// The flag for exactly this key, without either-side logic
+    fn raw(&self, modifier: Modifier) -> bool {
+        let mut state = *self;
+        *state.flag(modifier)
     }
 
-    /// Check if Control + Command are pressed (for Agent toggle)
-    pub fn is_control_command(&self) -> bool {
-        self.control && self.command && !self.option
+    /// The chord made of exactly the held keys
+    ///
+    /// Uses the sided modifier where the source could tell which key is
+    /// held, and the either-side one otherwise.
+    pub fn chord(&self) -> Chord {
+        let modifiers: Vec<Modifier> = Modifier::ALL
+            .into_iter()
+            .filter(|m| self.raw(*m))
+            .filter(|m| {
+                m.is_sided()
+                    || !Modifier::ALL
+                        .into_iter()
+                        .any(|s| s.is_sided() && s.generic() == *m && self.raw(s))
+            })
+            .collect();
+        Chord::with_keys(&modifiers, &self.keys.keys())
+    }
+
+    /// Check if every key of the chord is held (other keys may be too)
+    pub fn holds(&self, chord: Chord) -> bool {
+        self.keys.contains_all(chord.keys())
+            && Modifier::ALL
+                .into_iter()
+                .all(|m| !chord.contains(m) || self.is_held(m))
+    }
+
+    /// Check if exactly the keys of the chord are held
+    ///
+    /// Keys implied by the chord (e.g. Option when it names Right Option)
+    /// don't count as extra.
+    pub fn holds_exactly(&self, chord: Chord) -> bool {
+        self.holds(chord)
+            && self.keys == chord.keys()
+            && Modifier::ALL
+                .into_iter()
+                .all(|m| !self.is_held(m) || chord.covers(m))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hotkey::trigger::TriggerKey;
 
     #[test]
     fn test_empty_state() {
         let state = ModifierState::default();
-        assert!(state.is_empty());
-        assert!(!state.is_control_only());
+        assert!(state.chord().is_empty());
+        assert!(Modifier::ALL.into_iter().all(|m| !state.is_held(m)));
+        assert!(!state.holds_exactly(Chord::CONTROL));
     }
 
     #[test]
@@ -76,11 +161,12 @@ mod tests {
             control: true,
             option: false,
             command: false,
+            ..Default::default()
         };
-        assert!(!state.is_empty());
-        assert!(state.is_control_only());
-        assert!(!state.is_control_option());
-        assert!(!state.is_control_command());
+        assert_ne!(state, ModifierState::default());
+        assert!(state.holds_exactly(Chord::CONTROL));
+        assert!(!state.holds_exactly(Chord::CONTROL_OPTION));
+        assert!(!state.holds_exactly(Chord::CONTROL_COMMAND));
     }
 
     #[test]
@@ -89,10 +175,11 @@ mod tests {
             control: true,
             option: true,
             command: false,
+            ..Default::default()
         };
-        assert!(!state.is_control_only());
-        assert!(state.is_control_option());
-        assert!(!state.is_control_command());
+        assert!(!state.holds_exactly(Chord::CONTROL));
+        assert!(state.holds_exactly(Chord::CONTROL_OPTION));
+        assert!(!state.holds_exactly(Chord::CONTROL_COMMAND));
     }
 
     #[test]
@@ -101,9 +188,98 @@ mod tests {
             control: true,
             option: false,
             command: true,
+            ..Default::default()
+        };
+        assert!(!state.holds_exactly(Chord::CONTROL));
+        assert!(!state.holds_exactly(Chord::CONTROL_OPTION));
+        assert!(state.holds_exactly(Chord::CONTROL_COMMAND));
+    }
+
+    #[test]
+    fn test_holds_chord() {
+        let state = ModifierState {
+            control: true,
+            option: true,
+            command: false,
+            ..Default::default()
+        };
+        assert!(state.holds(Chord::CONTROL));
+        assert!(state.holds(Chord::CONTROL_OPTION));
+        assert!(!state.holds(Chord::CONTROL_COMMAND));
+        assert!(state.holds_exactly(Chord::CONTROL_OPTION));
+        assert!(!state.holds_exactly(Chord::CONTROL));
+    }
+
+    #[test]
+    fn test_sided_keys() {
+        let mut state = ModifierState::default();
+        state.press(Modifier::RightOption);
+        assert!(state.option && state.right_option);
+
+        let right_option = Chord::new(&[Modifier::RightOption]);
+        assert!(state.holds_exactly(right_option));
+        assert!(state.holds_exactly(Chord::new(&[Modifier::Option])));
+        assert!(!state.holds(Chord::new(&[Modifier::LeftOption])));
+
+        // Both sides held is more than "Right Option only"
+        state.press(Modifier::LeftOption);
+        assert!(!state.holds_exactly(right_option));
+    }
+
+    #[test]
+    fn test_sides_unknown() {
+        // Sources that can't tell sides apart only set the either-side flag
+        let state = ModifierState {
+            option: true,
+            ..Default::default()
+        };
+        assert!(state.holds_exactly(Chord::new(&[Modifier::Option])));
+        assert!(!state.holds(Chord::new(&[Modifier::RightOption])));
+    }
+
+    #[test]
+    fn test_shift_is_an_extra_key() {
+        let mut state = ModifierState::default();
+        state.press(Modifier::LeftControl);
+        state.press(Modifier::LeftShift);
+        assert!(state.holds(Chord::CONTROL));
+        assert!(!state.holds_exactly(Chord::CONTROL));
+    }
+
+    #[test]
+    fn test_trigger_keys() {
+        let f18 = Chord::with_keys(&[], &[TriggerKey::F18]);
+        let mut state = ModifierState::default();
+        state.keys.set(TriggerKey::F18, true);
+        assert!(state.holds_exactly(f18));
+        assert!(!state.holds(Chord::CONTROL));
+
+        // Extra modifiers and extra trigger keys both break an exact match
+        state.press(Modifier::LeftControl);
+        assert!(state.holds(f18) && !state.holds_exactly(f18));
+        state = ModifierState::default();
+        state.keys.set(TriggerKey::F18, true);
+        state.keys.set(TriggerKey::Mouse4, true);
+        assert!(!state.holds_exactly(f18));
+    }
+
+    #[test]
+    fn test_chord_of_held_keys() {
+        let mut state = ModifierState {
+            control: true,
+            ..Default::default()
         };
-        assert!(!state.is_control_only());
-        assert!(!state.is_control_option());
-        assert!(state.is_control_command());
+        state.press(Modifier::RightOption);
+        state.keys.set(TriggerKey::F18, true);
+        assert_eq!(state.chord().to_string(), "Ctrl+ROpt+F18");
+        assert!(state.holds_exactly(state.chord()));
+        assert!(ModifierState::default().chord().is_empty());
+    }
+
+    #[test]
+    fn test_old_recordings_deserialize() {
+        let state: ModifierState =
+            serde_json::from_str(r#"{"control":true,"option":false,"command":false}"#).unwrap();
+        assert!(state.control && !state.left_control && !state.shift);
     }
 }
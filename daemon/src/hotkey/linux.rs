@@ -0,0 +1,390 @@
+//! Linux hotkey source reading keyboards through evdev
+//!
+//! Opens every input device that has modifier keys, or any of the bound
+//! trigger keys (so mice are opened when a mouse button is bound), and polls
+//! them from a dedicated thread. Reading `/dev/input` usually requires
+//! membership in the `input` group. Devices plugged in after start are not
+//! picked up. evdev only observes input, so trigger keys are never swallowed.
+
+use std::collections::HashSet;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use evdev::{Device, InputEventKind, Key};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use super::chord::Modifier;
+use super::keys::ModifierState;
+use super::source::{HotkeyError, HotkeyEvent, HotkeySource};
+use super::trigger::{TriggerKey, TriggerKeys};
+
+/// How long each poll waits before checking whether to stop
+const POLL_TIMEOUT_MS: i32 = 100;
+
+/// Hotkey source backed by evdev keyboard devices
+pub struct EvdevSource {
+    event_tx: mpsc::Sender<HotkeyEvent>,
+    triggers: Mutex<TriggerKeys>,
+    running: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EvdevSource {
+    /// Create a new evdev source reporting modifiers and `triggers`
+    pub fn new(event_tx: mpsc::Sender<HotkeyEvent>, triggers: TriggerKeys) -> Self {
+        Self {
+            event_tx,
+            triggers: Mutex::new(triggers),
+            running: Arc::new(AtomicBool::new(false)),
+            thread: Mutex::new(None),
+        }
+    }
+}
+
+impl HotkeySource for EvdevSource {
+    fn name(&self) -> &'static str {
+        "evdev"
+    }
+
+    /// Open the input devices and start polling them on a dedicated thread
+    fn start(&self) -> Result<(), HotkeyError> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err(HotkeyError::AlreadyRunning);
+        }
+
+        // Reap a thread that exited on its own
+        let mut thread = self.thread.lock().unwrap();
+        if let Some(old) = thread.take() {
+            let _ = old.join();
+        }
+
+        let triggers = *self.triggers.lock().unwrap();
+        let devices: Vec<Device> = evdev::enumerate()
+            .filter(|(_, device)| is_keyboard(device) || has_trigger_keys(device, triggers))
+            .map(|(path, device)| {
+                info!(?path, name = device.name().unwrap_or("unknown"), "using input device");
+                device
+            })
+            .collect();
+
+        if devices.is_empty() {
+            self.running.store(false, Ordering::SeqCst);
+            return Err(HotkeyError::NoKeyboards);
+        }
+
+        let event_tx = self.event_tx.clone();
+        let running = Arc::clone(&self.running);
+
+        let handle = thread::Builder::new()
+            .name("hotkey-listener".to_string())
+            .spawn(move || {
+                info!("hotkey listener thread started");
+
+                let held = HeldKeys::new(triggers);
+                if let Err(e) = run_event_loop(devices, held, event_tx, &running) {
+                    error!(?e, "hotkey listener error");
+                }
+
+                running.store(false, Ordering::SeqCst);
+                info!("hotkey listener thread stopped");
+            })
+            .map_err(|e| {
+                self.running.store(false, Ordering::SeqCst);
+                HotkeyError::ThreadSpawn(e.to_string())
+            })?;
+
+        *thread = Some(handle);
+        Ok(())
+    }
+
+    /// Stop the source and wait for the thread, which exits within one
+    /// poll timeout
+    fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.thread.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn set_trigger_keys(&self, keys: TriggerKeys, _swallow: bool) {
+        *self.triggers.lock().unwrap() = keys;
+    }
+}
+
+/// Whether a device looks like a keyboard with modifier keys
+fn is_keyboard(device: &Device) -> bool {
+    device
+        .supported_keys()
+        .is_some_and(|keys| keys.contains(Key::KEY_LEFTCTRL) || keys.contains(Key::KEY_RIGHTCTRL))
+}
+
+/// Whether a device has any of the bound trigger keys or buttons
+fn has_trigger_keys(device: &Device, triggers: TriggerKeys) -> bool {
+    device.supported_keys().is_some_and(|keys| {
+        keys.iter()
+            .filter_map(trigger_for)
+            .any(|trigger| triggers.contains(trigger))
+    })
+}
+
+/// The modifier key a key code stands for, if any
+///
+/// Most keyboards handle Fn in firmware, so `KEY_FN` is rarely reported.
+fn modifier_for(key: Key) -> Option<Modifier> {
+    match key {
+        Key::KEY_LEFTCTRL => Some(Modifier::LeftControl),
+        Key::KEY_RIGHTCTRL => Some(Modifier::RightControl),
+        Key::KEY_LEFTALT => Some(Modifier::LeftOption),
+        Key::KEY_RIGHTALT => Some(Modifier::RightOption),
+        Key::KEY_LEFTSHIFT => Some(Modifier::LeftShift),
+        Key::KEY_RIGHTSHIFT => Some(Modifier::RightShift),
+        Key::KEY_LEFTMETA => Some(Modifier::LeftCommand),
+        Key::KEY_RIGHTMETA => Some(Modifier::RightCommand),
+        Key::KEY_FN => Some(Modifier::Function),
+        _ => None,
+    }
+}
+
+/// The trigger key a key code stands for, if any
+///
+/// Mice report the side buttons either as SIDE/EXTRA or as BACK/FORWARD.
+fn trigger_for(key: Key) -> Option<TriggerKey> {
+    match key {
+        Key::KEY_F13 => Some(TriggerKey::F13),
+        Key::KEY_F14 => Some(TriggerKey::F14),
+        Key::KEY_F15 => Some(TriggerKey::F15),
+        Key::KEY_F16 => Some(TriggerKey::F16),
+        Key::KEY_F17 => Some(TriggerKey::F17),
+        Key::KEY_F18 => Some(TriggerKey::F18),
+        Key::KEY_F19 => Some(TriggerKey::F19),
+        Key::KEY_CAPSLOCK => Some(TriggerKey::CapsLock),
+        Key::BTN_MIDDLE => Some(TriggerKey::Mouse3),
+        Key::BTN_SIDE | Key::BTN_BACK => Some(TriggerKey::Mouse4),
+        Key::BTN_EXTRA | Key::BTN_FORWARD => Some(TriggerKey::Mouse5),
+        _ => None,
+    }
+}
+
+/// Modifier and bound trigger keys currently held, across all devices
+#[derive(Debug, Default)]
+struct HeldKeys {
+    keys: HashSet<(usize, Key)>,
+    triggers: TriggerKeys,
+}
+
+impl HeldKeys {
+    /// Track modifiers plus the given trigger keys
+    fn new(triggers: TriggerKeys) -> Self {
+        Self {
+            keys: HashSet::new(),
+            triggers,
+        }
+    }
+
+    /// Whether events for this key are tracked at all
+    fn tracks(&self, key: Key) -> bool {
+        modifier_for(key).is_some() || trigger_for(key).is_some_and(|t| self.triggers.contains(t))
+    }
+
+    /// Apply a key event from `device`; `value` is 1 for press, 0 for release
+    ///
+    /// Returns whether this was a press of a regular keyboard key, one that
+    /// is neither tracked nor a mouse button.
+    fn apply(&mut self, device: usize, key: Key, value: i32) -> bool {
+        if !self.tracks(key) {
+            return value == 1 && key.code() < Key::BTN_0.code();
+        }
+        match value {
+            0 => {
+                self.keys.remove(&(device, key));
+            }
+            1 => {
+                self.keys.insert((device, key));
+            }
+            // Auto-repeat doesn't change what is held
+            _ => {}
+        }
+        false
+    }
+
+    /// Forget every key held on a device that went away
+    fn release_device(&mut self, device: usize) {
+        self.keys.retain(|(d, _)| *d != device);
+    }
+
+    fn modifiers(&self) -> ModifierState {
+        let mut state = ModifierState::default();
+        for (_, key) in &self.keys {
+            if let Some(modifier) = modifier_for(*key) {
+                state.press(modifier);
+            }
+            if let Some(trigger) = trigger_for(*key) {
+                state.keys.set(trigger, true);
+            }
+        }
+        state
+    }
+}
+
+/// Poll the devices until stopped or every device is gone
+fn run_event_loop(
+    mut devices: Vec<Device>,
+    mut held: HeldKeys,
+    event_tx: mpsc::Sender<HotkeyEvent>,
+    running: &AtomicBool,
+) -> io::Result<()> {
+    let mut fds: Vec<libc::pollfd> = devices
+        .iter()
+        .map(|device| libc::pollfd {
+            fd: device.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+
+    let mut last_state = ModifierState::default();
+
+    while running.load(Ordering::SeqCst) {
+        // SAFETY: `fds` is a valid, exclusively borrowed array of pollfd
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, POLL_TIMEOUT_MS) };
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+
+        let mut key_pressed = false;
+        for (index, (device, pollfd)) in devices.iter_mut().zip(fds.iter_mut()).enumerate() {
+            if pollfd.revents == 0 {
+                continue;
+            }
+
+            let result = device.fetch_events().map(|events| {
+                for event in events {
+                    if let InputEventKind::Key(key) = event.kind() {
+                        key_pressed |= held.apply(index, key, event.value());
+                    }
+                }
+            });
+
+            match result {
+                Ok(()) => {}
+                // Nothing left to read or a signal arrived; poll again
+                Err(e) if matches!(e.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock) => {}
+                Err(e) if device_removed(&e) => {
+                    // Negative fds are ignored by poll
+                    warn!(?e, name = device.name().unwrap_or("unknown"), "input device removed");
+                    pollfd.fd = -1;
+                    held.release_device(index);
+                }
+                Err(e) => {
+                    warn!(?e, name = device.name().unwrap_or("unknown"), "failed to read input device");
+                }
+            }
+        }
+
+        if fds.iter().all(|pollfd| pollfd.fd < 0) {
+            warn!("all input devices are gone");
+            break;
+        }
+
+        let new_state = held.modifiers();
+        if new_state != last_state {
+            debug!(?last_state, ?new_state, "modifier state changed");
+
+            if event_tx.blocking_send(HotkeyEvent::ModifierChanged(new_state)).is_err() {
+                warn!("failed to send modifier event - channel closed?");
+                break;
+            }
+
+            last_state = new_state;
+        }
+
+        if key_pressed
+            && last_state != ModifierState::default()
+            && event_tx.blocking_send(HotkeyEvent::KeyPressed).is_err()
+        {
+            warn!("failed to send key event - channel closed?");
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether reading a device failed because it was unplugged
+fn device_removed(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::ENODEV)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_held_keys_track_both_sides() {
+        let mut held = HeldKeys::default();
+        held.apply(0, Key::KEY_LEFTCTRL, 1);
+        held.apply(0, Key::KEY_RIGHTCTRL, 1);
+        held.apply(0, Key::KEY_LEFTCTRL, 0);
+        assert!(held.modifiers().control);
+
+        let state = held.modifiers();
+        assert!(state.right_control && !state.left_control);
+
+        held.apply(0, Key::KEY_RIGHTCTRL, 0);
+        assert_eq!(held.modifiers(), ModifierState::default());
+    }
+
+    #[test]
+    fn test_held_keys_ignore_other_keys_and_repeats() {
+        let mut held = HeldKeys::default();
+        assert!(held.apply(0, Key::KEY_A, 1));
+        assert!(!held.apply(0, Key::KEY_A, 0));
+        assert!(!held.apply(0, Key::BTN_LEFT, 1));
+        assert!(!held.apply(1, Key::KEY_LEFTMETA, 2));
+        assert_eq!(held.modifiers(), ModifierState::default());
+
+        held.apply(1, Key::KEY_LEFTMETA, 1);
+        held.apply(0, Key::KEY_LEFTALT, 1);
+        let state = held.modifiers();
+        assert!(state.command && state.option && !state.control);
+
+        held.release_device(1);
+        assert!(!held.modifiers().command);
+    }
+
+    #[test]
+    fn test_held_keys_report_bound_triggers_only() {
+        let mut held = HeldKeys::new(TriggerKeys::new(&[TriggerKey::F18, TriggerKey::Mouse4]));
+        held.apply(0, Key::KEY_F17, 1);
+        held.apply(0, Key::BTN_MIDDLE, 1);
+        assert_eq!(held.modifiers(), ModifierState::default());
+
+        held.apply(0, Key::KEY_F18, 1);
+        held.apply(1, Key::BTN_BACK, 1);
+        let keys = held.modifiers().keys;
+        assert!(keys.contains(TriggerKey::F18) && keys.contains(TriggerKey::Mouse4));
+
+        held.apply(0, Key::KEY_F18, 0);
+        assert!(!held.modifiers().keys.contains(TriggerKey::F18));
+    }
+
+    #[test]
+    fn test_only_unplugged_devices_are_removed() {
+        assert!(device_removed(&io::Error::from_raw_os_error(libc::ENODEV)));
+        assert!(!device_removed(&io::Error::from_raw_os_error(libc::EINTR)));
+        assert!(!device_removed(&io::Error::from_raw_os_error(libc::EAGAIN)));
+        assert!(!device_removed(&io::Error::from(io::ErrorKind::WouldBlock)));
+    }
+}
@@ -1,208 +1,173 @@
-//! Global hotkey listener using macOS CGEventTap
+//! Restartable handle around a hotkey source
 //!
-//! Monitors system-wide keyboard events for modifier key changes.
-//! Runs on a dedicated thread with its own CFRunLoop.
-
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread;
-
-use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
-use core_graphics::event::{
-    CGEvent, CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions,
-    CGEventTapPlacement, CGEventType,
-};
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
-
-use super::keys::ModifierState;
-
-/// Events sent from the hotkey listener to the state machine
-#[derive(Debug, Clone)]
-pub enum HotkeyEvent {
-    /// Modifier state has changed
-    ModifierChanged(ModifierState),
-    /// Event tap was disabled by macOS (needs re-registration)
-    TapDisabled,
-}
+//! Owns the platform source together with the bindings it watches for.
+//! Sources join their thread on `stop`, so the listener can be started
+//! again right away, and rebinding only restarts the source when the set
+//! of trigger keys it has to watch changed. `watch` notices when the
+//! source's thread stops on its own, for the supervisor to restart it, and
+//! reports the listener degraded while the OS has its tap disabled.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use tracing::info;
+
+use super::bindings::Bindings;
+use super::source::{HotkeyError, HotkeySource};
+use crate::lifecycle::{HealthRegistry, HealthState};
+use crate::state::StateStore;
 
-/// Global hotkey listener that monitors modifier key press/release events
+/// How often `watch` checks that the source is still running
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A hotkey source plus the bindings it is set up for
 pub struct HotkeyListener {
-    event_tx: mpsc::Sender<HotkeyEvent>,
-    running: Arc<AtomicBool>,
+    source: Box<dyn HotkeySource>,
+    /// The bindings the source is set up for, and their version
+    bindings: Mutex<(Bindings, u64)>,
+    /// Where the listener reports whether it is running
+    store: StateStore,
+    /// Held across a deliberate restart, so `watch` doesn't take it for a
+    /// failure; async, since `watch` holds it on the runtime
+    restarting: tokio::sync::Mutex<()>,
 }
 
 impl HotkeyListener {
-    /// Create a new hotkey listener
-    pub fn new(event_tx: mpsc::Sender<HotkeyEvent>) -> Self {
+    /// Name the listener is supervised and reports its health under
+    pub const COMPONENT: &'static str = "hotkey listener";
+
+    /// Wrap a source created for `bindings`
+    pub fn new(source: Box<dyn HotkeySource>, bindings: Bindings, store: StateStore) -> Self {
         Self {
-            event_tx,
-            running: Arc::new(AtomicBool::new(false)),
+            source,
+            bindings: Mutex::new((bindings, 0)),
+            store,
+            restarting: tokio::sync::Mutex::new(()),
         }
     }
 
-    /// Start the hotkey listener
-    ///
-    /// This spawns a dedicated thread that runs a CFRunLoop to receive
-    /// CGEventTap callbacks. The listener runs until `stop()` is called
-    /// or the program exits.
-    pub fn start(&self) -> Result<(), HotkeyError> {
-        if self.running.swap(true, Ordering::SeqCst) {
-            return Err(HotkeyError::AlreadyRunning);
-        }
-
-        let event_tx = self.event_tx.clone();
-        let running = Arc::clone(&self.running);
-
-        thread::Builder::new()
-            .name("hotkey-listener".to_string())
-            .spawn(move || {
-                info!("hotkey listener thread started");
-                
-                if let Err(e) = run_event_loop(event_tx, running.clone()) {
-                    error!(?e, "hotkey listener error");
-                }
-                
-                running.store(false, Ordering::SeqCst);
-                info!("hotkey listener thread stopped");
-            })
-            .map_err(|e| HotkeyError::ThreadSpawn(e.to_string()))?;
+    /// Short backend name, for logs
+    pub fn name(&self) -> &'static str {
+        self.source.name()
+    }
 
-        Ok(())
+    /// Start the source
+    pub fn start(&self) -> Result<(), HotkeyError> {
+        let result = self.source.start();
+        self.publish();
+        result
     }
 
-    /// Stop the hotkey listener
+    /// Stop the source and wait for its thread to exit
     pub fn stop(&self) {
-        self.running.store(false, Ordering::SeqCst);
-        // The CFRunLoop will exit on the next iteration
-        CFRunLoop::get_main().stop();
+        self.source.stop();
+        self.publish();
     }
 
-    /// Check if the listener is currently running
-    pub fn is_running(&self) -> bool {
-        self.running.load(Ordering::SeqCst)
-    }
-}
-
-/// Errors that can occur in the hotkey listener
-#[derive(Debug, thiserror::Error)]
-pub enum HotkeyError {
-    #[error("hotkey listener is already running")]
-    AlreadyRunning,
-    
-    #[error("failed to create event tap - check Accessibility permissions")]
-    EventTapCreation,
-    
-    #[error("failed to spawn listener thread: {0}")]
-    ThreadSpawn(String),
-    
-    #[error("failed to send event to channel")]
-    ChannelSend,
-}
+    /// Watch for new bindings
+    ///
+    /// A running source is restarted when the trigger keys to watch or
+    /// swallow changed; modifier-only changes need no restart, since
+    /// sources always report every modifier. Bindings older than `version`
+    /// the listener already has are ignored, as rebinds that overlap may
+    /// finish in any order. Blocks, so call it off the async runtime.
+    pub fn rebind(&self, bindings: Bindings, version: u64) -> Result<(), HotkeyError> {
+        // Held until the restart is done, so rebinds apply one at a time
+        let mut current = self.bindings.lock().unwrap_or_else(PoisonError::into_inner);
+        if version <= current.1 {
+            return Ok(());
+        }
+        let (old, _) = std::mem::replace(&mut *current, (bindings, version));
+        let keys = bindings.trigger_keys();
+        if keys == old.trigger_keys() && bindings.swallow_trigger_keys == old.swallow_trigger_keys {
+            return Ok(());
+        }
 
-/// Run the CFRunLoop with the event tap
-fn run_event_loop(
-    event_tx: mpsc::Sender<HotkeyEvent>,
-    running: Arc<AtomicBool>,
-) -> Result<(), HotkeyError> {
-    // Track the last modifier state to detect changes
-    let mut last_state = ModifierState::default();
-
-    // Create a channel to send events from the callback
-    let (callback_tx, callback_rx) = std::sync::mpsc::channel::<CGEventFlags>();
-
-    // CGEventTap callback - must be fast and non-blocking
-    let callback = move |_proxy: core_graphics::event::CGEventTapProxy,
-                         event_type: CGEventType,
-                         event: &CGEvent|
-                         -> Option<CGEvent> {
-        match event_type {
-            CGEventType::FlagsChanged => {
-                let flags = event.get_flags();
-                let _ = callback_tx.send(flags);
-            }
-            CGEventType::TapDisabledByTimeout | CGEventType::TapDisabledByUserInput => {
-                warn!("event tap disabled, will re-enable");
-                // The tap will be re-enabled automatically
-            }
-            _ => {}
+        self.source.set_trigger_keys(keys, bindings.swallow_trigger_keys);
+        if !self.source.is_running() {
+            return Ok(());
         }
-        Some(event.clone())
-    };
-
-    // Create the event tap
-    let tap = CGEventTap::new(
-        CGEventTapLocation::Session,
-        CGEventTapPlacement::HeadInsertEventTap,
-        CGEventTapOptions::ListenOnly,
-        vec![CGEventType::FlagsChanged],
-        callback,
-    )
-    .map_err(|_| {
-        error!("failed to create event tap - is Accessibility permission granted?");
-        HotkeyError::EventTapCreation
-    })?;
-
-    // Enable the tap
-    tap.enable();
-
-    // Create a run loop source and add it to the current run loop
-    let run_loop_source = tap.mach_port.create_runloop_source(0).unwrap();
-    let run_loop = CFRunLoop::get_current();
-    
-    unsafe {
-        run_loop.add_source(&run_loop_source, kCFRunLoopCommonModes);
+
+        info!(source = self.name(), "restarting hotkey listener for new bindings");
+        let _restarting = self.restarting.blocking_lock();
+        self.stop();
+        self.start()
     }
 
-    info!("event tap created and enabled");
-
-    // Process events in a loop
-    while running.load(Ordering::SeqCst) {
-        // Run the loop for a short interval, then check for new events
-        unsafe {
-            CFRunLoop::run_in_mode(
-                kCFRunLoopDefaultMode,
-                std::time::Duration::from_millis(100),
-                true,
-            );
-        }
+    /// Start the source if it isn't running, then return once it stops
+    /// without being told to
+    pub async fn watch(&self, health: &HealthRegistry) -> Result<(), HotkeyError> {
+        self.ensure_started().await?;
+        let mut tap_disabled = false;
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+            {
+                let _restarting = self.restarting.lock().await;
+                if !self.source.is_running() {
+                    self.publish();
+                    return Err(HotkeyError::Stopped);
+                }
+            }
 
-        // Process any events from the callback
-        while let Ok(flags) = callback_rx.try_recv() {
-            let new_state = ModifierState::from_flags(flags);
-            
-            if new_state != last_state {
-                debug!(
-                    ?last_state,
-                    ?new_state,
-                    "modifier state changed"
-                );
-                
-                // Send the event asynchronously
-                // We use try_send since we're not in an async context
-                if event_tx.blocking_send(HotkeyEvent::ModifierChanged(new_state)).is_err() {
-                    warn!("failed to send modifier event - channel closed?");
-                    break;
+            let snapshot = self.store.snapshot();
+            let disabled = snapshot.tap_disabled_count > snapshot.tap_recovered_count;
+            if disabled != tap_disabled {
+                tap_disabled = disabled;
+                if disabled {
+                    health.report(
+                        Self::COMPONENT,
+                        HealthState::Degraded,
+                        "the OS disabled the event tap, hotkeys are missed until it is re-enabled",
+                    );
+                } else {
+                    health.report(Self::COMPONENT, HealthState::Ok, "running, event tap re-enabled");
                 }
-                
-                last_state = new_state;
             }
         }
     }
 
-    // Tap will be automatically cleaned up when it goes out of scope
-    
-    Ok(())
+    async fn ensure_started(&self) -> Result<(), HotkeyError> {
+        let _restarting = self.restarting.lock().await;
+        if self.source.is_running() {
+            return Ok(());
+        }
+        info!(source = self.name(), "starting hotkey listener");
+        self.start()
+    }
+
+    fn publish(&self) {
+        let running = self.source.is_running();
+        self.store.update(|s| s.hotkey_registered = running);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hotkey::SyntheticSource;
+    use tokio::sync::mpsc;
 
     #[test]
-    fn test_listener_creation() {
-        let (tx, _rx) = mpsc::channel(32);
-        let listener = HotkeyListener::new(tx);
-        assert!(!listener.is_running());
+    fn test_restart_and_rebind() {
+        let (tx, _rx) = mpsc::channel(4);
+        let (source, _input) = SyntheticSource::new(tx);
+        let store = StateStore::new();
+        let listener = HotkeyListener::new(Box::new(source), Bindings::default(), store.clone());
+
+        listener.start().unwrap();
+        assert!(store.snapshot().hotkey_registered);
+        listener.stop();
+        assert!(!store.snapshot().hotkey_registered);
+        listener.start().unwrap();
+
+        let bindings: Bindings =
+            serde_json::from_str(r#"{"dictation":["f18"]}"#).unwrap();
+        listener.rebind(bindings, 2).unwrap();
+        assert!(store.snapshot().hotkey_registered);
+        assert_eq!(*listener.bindings.lock().unwrap(), (bindings, 2));
+
+        // An older rebind that finished late is ignored
+        listener.rebind(Bindings::default(), 1).unwrap();
+        assert_eq!(listener.bindings.lock().unwrap().0, bindings);
     }
 }
@@ -0,0 +1,488 @@
+//! macOS hotkey source using CGEventTap
+//!
+//! Monitors system-wide keyboard events for modifier key changes, plus
+//! key and mouse button events for bound trigger keys. Runs on a dedicated
+//! thread with its own CFRunLoop. When trigger keys should be swallowed the
+//! tap is an active one, so it needs the same Accessibility permission but
+//! can drop the trigger key's events before other apps see them.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use core_foundation::base::TCFType;
+use core_foundation::mach_port::CFMachPortRef;
+use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+use core_graphics::event::{
+    CGEvent, CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions,
+    CGEventTapPlacement, CGEventType, EventField,
+};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use super::chord::Modifier;
+use super::keys::ModifierState;
+use super::source::{HotkeyError, HotkeyEvent, HotkeySource};
+use super::trigger::{TriggerKey, TriggerKeys};
+use crate::lifecycle::Backoff;
+
+/// First delay before re-enabling a disabled tap
+const RECOVERY_INITIAL: Duration = Duration::from_millis(50);
+/// Longest delay between re-enable attempts
+const RECOVERY_MAX: Duration = Duration::from_secs(5);
+/// How long the tap must stay enabled before the backoff starts over
+const RECOVERY_RESET_AFTER: Duration = Duration::from_secs(30);
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventTapIsEnabled(tap: CFMachPortRef) -> bool;
+}
+
+/// Modifier key flag masks from macOS CGEventFlags
+mod flags {
+    use core_graphics::event::CGEventFlags;
+
+    /// Control key modifier flag
+    pub const CONTROL: CGEventFlags = CGEventFlags::CGEventFlagControl;
+    /// Option/Alt key modifier flag
+    pub const OPTION: CGEventFlags = CGEventFlags::CGEventFlagAlternate;
+    /// Command key modifier flag
+    pub const COMMAND: CGEventFlags = CGEventFlags::CGEventFlagCommand;
+    /// Shift key modifier flag
+    pub const SHIFT: CGEventFlags = CGEventFlags::CGEventFlagShift;
+    /// Fn/Globe key modifier flag
+    pub const FUNCTION: CGEventFlags = CGEventFlags::CGEventFlagSecondaryFn;
+    /// CapsLock is on (a lock state, not whether the key is held)
+    pub const CAPS_LOCK: CGEventFlags = CGEventFlags::CGEventFlagAlphaShift;
+
+    /// Device-dependent bits telling left and right keys apart
+    /// (`NX_DEVICE*KEYMASK` in IOKit's `IOLLEvent.h`)
+    pub mod device {
+        pub const LEFT_CONTROL: u64 = 0x0000_0001;
+        pub const LEFT_SHIFT: u64 = 0x0000_0002;
+        pub const RIGHT_SHIFT: u64 = 0x0000_0004;
+        pub const LEFT_COMMAND: u64 = 0x0000_0008;
+        pub const RIGHT_COMMAND: u64 = 0x0000_0010;
+        pub const LEFT_OPTION: u64 = 0x0000_0020;
+        pub const RIGHT_OPTION: u64 = 0x0000_0040;
+        pub const RIGHT_CONTROL: u64 = 0x0000_2000;
+    }
+}
+
+/// Create a ModifierState from CGEventFlags
+fn modifiers_from_flags(flags: CGEventFlags) -> ModifierState {
+    modifiers_from_bits(flags.bits())
+}
+
+/// Device-dependent bit for each physical modifier key
+const DEVICE_KEYS: [(u64, Modifier); 8] = [
+    (flags::device::LEFT_CONTROL, Modifier::LeftControl),
+    (flags::device::RIGHT_CONTROL, Modifier::RightControl),
+    (flags::device::LEFT_OPTION, Modifier::LeftOption),
+    (flags::device::RIGHT_OPTION, Modifier::RightOption),
+    (flags::device::LEFT_SHIFT, Modifier::LeftShift),
+    (flags::device::RIGHT_SHIFT, Modifier::RightShift),
+    (flags::device::LEFT_COMMAND, Modifier::LeftCommand),
+    (flags::device::RIGHT_COMMAND, Modifier::RightCommand),
+];
+
+/// Create a ModifierState from raw flag bits
+///
+/// The device bits aren't named by CGEventFlags, so work on the raw value.
+fn modifiers_from_bits(bits: u64) -> ModifierState {
+    let flag = |flag: CGEventFlags| bits & flag.bits() != 0;
+
+    let mut state = ModifierState {
+        control: flag(flags::CONTROL),
+        option: flag(flags::OPTION),
+        command: flag(flags::COMMAND),
+        shift: flag(flags::SHIFT),
+        function: flag(flags::FUNCTION),
+        ..Default::default()
+    };
+    for (mask, modifier) in DEVICE_KEYS {
+        if bits & mask != 0 {
+            state.press(modifier);
+        }
+    }
+    state
+}
+
+/// The trigger key for a virtual key code (`kVK_*` in `Events.h`)
+fn trigger_for_keycode(keycode: i64) -> Option<TriggerKey> {
+    match keycode {
+        0x69 => Some(TriggerKey::F13),
+        0x6B => Some(TriggerKey::F14),
+        0x71 => Some(TriggerKey::F15),
+        0x6A => Some(TriggerKey::F16),
+        0x40 => Some(TriggerKey::F17),
+        0x4F => Some(TriggerKey::F18),
+        0x50 => Some(TriggerKey::F19),
+        _ => None,
+    }
+}
+
+/// The trigger key for a zero-based mouse button number
+fn trigger_for_button(button: i64) -> Option<TriggerKey> {
+    match button {
+        2 => Some(TriggerKey::Mouse3),
+        3 => Some(TriggerKey::Mouse4),
+        4 => Some(TriggerKey::Mouse5),
+        _ => None,
+    }
+}
+
+/// What the tap callback hands to the listener loop
+enum TapEvent {
+    Flags(CGEventFlags),
+    Trigger { key: TriggerKey, pressed: bool },
+    /// A regular key was pressed (which one is not kept)
+    KeyPressed,
+    /// macOS disabled the tap, because a callback was too slow or
+    /// because of user input (e.g. secure input)
+    Disabled,
+}
+
+/// Global hotkey listener that monitors modifier and trigger key events
+pub struct EventTapSource {
+    event_tx: mpsc::Sender<HotkeyEvent>,
+    /// Trigger keys to report, and whether to swallow them
+    triggers: Mutex<(TriggerKeys, bool)>,
+    running: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventTapSource {
+    /// Create a new hotkey listener reporting modifiers and `triggers`
+    ///
+    /// With `swallow` set, events for the trigger keys are dropped instead
+    /// of being passed on to other apps.
+    pub fn new(event_tx: mpsc::Sender<HotkeyEvent>, triggers: TriggerKeys, swallow: bool) -> Self {
+        Self {
+            event_tx,
+            triggers: Mutex::new((triggers, swallow)),
+            running: Arc::new(AtomicBool::new(false)),
+            thread: Mutex::new(None),
+        }
+    }
+}
+
+impl HotkeySource for EventTapSource {
+    fn name(&self) -> &'static str {
+        "cg-event-tap"
+    }
+
+    /// Start the hotkey listener
+    ///
+    /// This spawns a dedicated thread that runs a CFRunLoop to receive
+    /// CGEventTap callbacks, and waits until the tap has been created so
+    /// a missing Accessibility permission is reported here. The listener
+    /// runs until `stop()` is called or the program exits.
+    fn start(&self) -> Result<(), HotkeyError> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err(HotkeyError::AlreadyRunning);
+        }
+
+        // Reap a thread that exited on its own
+        let mut thread = self.thread.lock().unwrap();
+        if let Some(old) = thread.take() {
+            let _ = old.join();
+        }
+
+        let event_tx = self.event_tx.clone();
+        let running = Arc::clone(&self.running);
+        let (triggers, swallow) = *self.triggers.lock().unwrap();
+        let (ready_tx, ready_rx) = std::sync::mpsc::sync_channel(1);
+
+        let handle = thread::Builder::new()
+            .name("hotkey-listener".to_string())
+            .spawn(move || {
+                info!("hotkey listener thread started");
+                
+                if let Err(e) = run_event_loop(event_tx, triggers, swallow, &running, ready_tx) {
+                    error!(?e, "hotkey listener error");
+                }
+                
+                running.store(false, Ordering::SeqCst);
+                info!("hotkey listener thread stopped");
+            })
+            .map_err(|e| {
+                self.running.store(false, Ordering::SeqCst);
+                HotkeyError::ThreadSpawn(e.to_string())
+            })?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => {
+                *thread = Some(handle);
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                let _ = handle.join();
+                Err(e)
+            }
+            // The thread died before creating the tap
+            Err(_) => {
+                let _ = handle.join();
+                Err(HotkeyError::EventTapCreation)
+            }
+        }
+    }
+
+    /// Stop the hotkey listener and wait for its thread to exit
+    ///
+    /// The thread's run loop wakes up at least every 100ms to check the
+    /// running flag, so this returns quickly.
+    fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(handle) = self.thread.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Check if the listener is currently running
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn set_trigger_keys(&self, keys: TriggerKeys, swallow: bool) {
+        *self.triggers.lock().unwrap() = (keys, swallow);
+    }
+}
+
+/// Run the CFRunLoop with the event tap
+fn run_event_loop(
+    event_tx: mpsc::Sender<HotkeyEvent>,
+    triggers: TriggerKeys,
+    swallow: bool,
+    running: &AtomicBool,
+    ready: SyncSender<Result<(), HotkeyError>>,
+) -> Result<(), HotkeyError> {
+    // Track the last state to detect changes
+    let mut last_state = ModifierState::default();
+    let mut modifiers = ModifierState::default();
+    let mut held = TriggerKeys::default();
+
+    // Create a channel to send events from the callback
+    let (callback_tx, callback_rx) = std::sync::mpsc::channel::<TapEvent>();
+
+    // CGEventTap callback - must be fast and non-blocking
+    let callback = move |_proxy: core_graphics::event::CGEventTapProxy,
+                         event_type: CGEventType,
+                         event: &CGEvent|
+                         -> Option<CGEvent> {
+        let trigger = match event_type {
+            CGEventType::FlagsChanged => {
+                let _ = callback_tx.send(TapEvent::Flags(event.get_flags()));
+                None
+            }
+            CGEventType::KeyDown | CGEventType::KeyUp => {
+                let trigger =
+                    trigger_for_keycode(event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE));
+                let repeat = event.get_integer_value_field(EventField::KEYBOARD_EVENT_AUTOREPEAT) != 0;
+                if matches!(event_type, CGEventType::KeyDown)
+                    && !repeat
+                    && !trigger.is_some_and(|key| triggers.contains(key))
+                {
+                    let _ = callback_tx.send(TapEvent::KeyPressed);
+                }
+                trigger
+            }
+            CGEventType::OtherMouseDown | CGEventType::OtherMouseUp => {
+                trigger_for_button(event.get_integer_value_field(EventField::MOUSE_EVENT_BUTTON_NUMBER))
+            }
+            CGEventType::TapDisabledByTimeout | CGEventType::TapDisabledByUserInput => {
+                let _ = callback_tx.send(TapEvent::Disabled);
+                None
+            }
+            _ => None,
+        };
+
+        match trigger.filter(|key| triggers.contains(*key)) {
+            Some(key) => {
+                let pressed = matches!(event_type, CGEventType::KeyDown | CGEventType::OtherMouseDown);
+                let _ = callback_tx.send(TapEvent::Trigger { key, pressed });
+                // Returning nothing drops the event (active taps only)
+                (!swallow).then(|| event.clone())
+            }
+            None => Some(event.clone()),
+        }
+    };
+
+    // Key events are needed for trigger keys and to notice chords used as
+    // app shortcuts; mouse events only when a button is bound
+    let mut events = vec![CGEventType::FlagsChanged, CGEventType::KeyDown, CGEventType::KeyUp];
+    if triggers.has_mouse_buttons() {
+        events.extend([CGEventType::OtherMouseDown, CGEventType::OtherMouseUp]);
+    }
+    let options = if swallow && !triggers.is_empty() {
+        CGEventTapOptions::Default
+    } else {
+        CGEventTapOptions::ListenOnly
+    };
+
+    // Create the event tap
+    let tap = match CGEventTap::new(
+        CGEventTapLocation::Session,
+        CGEventTapPlacement::HeadInsertEventTap,
+        options,
+        events,
+        callback,
+    ) {
+        Ok(tap) => tap,
+        Err(()) => {
+            error!("failed to create event tap - is Accessibility permission granted?");
+            let _ = ready.send(Err(HotkeyError::EventTapCreation));
+            return Err(HotkeyError::EventTapCreation);
+        }
+    };
+
+    // Enable the tap
+    tap.enable();
+
+    // Create a run loop source and add it to the current run loop
+    let run_loop_source = tap.mach_port.create_runloop_source(0).unwrap();
+    let run_loop = CFRunLoop::get_current();
+    
+    unsafe {
+        run_loop.add_source(&run_loop_source, kCFRunLoopCommonModes);
+    }
+
+    info!("event tap created and enabled");
+    let _ = ready.send(Ok(()));
+
+    // While disabled, when to try re-enabling the tap
+    let mut retry_at: Option<Instant> = None;
+    // When the tap last came back, to reset the backoff once it stays up
+    let mut recovered_at: Option<Instant> = None;
+    let mut backoff = Backoff::new(RECOVERY_INITIAL, RECOVERY_MAX);
+
+    // Process events in a loop
+    while running.load(Ordering::SeqCst) {
+        // Run the loop for a short interval, then check for new events
+        unsafe {
+            CFRunLoop::run_in_mode(
+                kCFRunLoopDefaultMode,
+                Duration::from_millis(100),
+                true,
+            );
+        }
+
+        // Process any events from the callback
+        while let Ok(event) = callback_rx.try_recv() {
+            match event {
+                TapEvent::KeyPressed => {
+                    if last_state != ModifierState::default()
+                        && event_tx.blocking_send(HotkeyEvent::KeyPressed).is_err()
+                    {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                TapEvent::Disabled => {
+                    if retry_at.is_some() {
+                        continue;
+                    }
+                    let delay = backoff.next_delay();
+                    warn!(?delay, attempt = backoff.attempts(), "event tap disabled, will re-enable");
+                    retry_at = Some(Instant::now() + delay);
+                    recovered_at = None;
+
+                    // Releases are missed while disabled; start from scratch
+                    modifiers = ModifierState::default();
+                    held = TriggerKeys::default();
+                    last_state = ModifierState::default();
+                    if event_tx.blocking_send(HotkeyEvent::TapDisabled).is_err() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                TapEvent::Flags(event_flags) => {
+                    modifiers = modifiers_from_flags(event_flags);
+                    // CapsLock only shows up as a lock flag on modifier changes
+                    if triggers.contains(TriggerKey::CapsLock) {
+                        held.set(TriggerKey::CapsLock, event_flags.contains(flags::CAPS_LOCK));
+                    }
+                }
+                TapEvent::Trigger { key, pressed } => held.set(key, pressed),
+            }
+            let new_state = ModifierState { keys: held, ..modifiers };
+
+            if new_state != last_state {
+                debug!(
+                    ?last_state,
+                    ?new_state,
+                    "modifier state changed"
+                );
+                
+                // Send the event asynchronously
+                // We use try_send since we're not in an async context
+                if event_tx.blocking_send(HotkeyEvent::ModifierChanged(new_state)).is_err() {
+                    warn!("failed to send modifier event - channel closed?");
+                    return Ok(());
+                }
+                
+                last_state = new_state;
+            }
+        }
+
+        let now = Instant::now();
+        if retry_at.is_some_and(|at| now >= at) {
+            tap.enable();
+            // SAFETY: the mach port belongs to `tap`, which is alive
+            if unsafe { CGEventTapIsEnabled(tap.mach_port.as_concrete_TypeRef()) } {
+                info!(attempts = backoff.attempts(), "event tap re-enabled");
+                retry_at = None;
+                recovered_at = Some(now);
+                if event_tx.blocking_send(HotkeyEvent::TapRecovered).is_err() {
+                    return Ok(());
+                }
+            } else {
+                let delay = backoff.next_delay();
+                warn!(?delay, attempt = backoff.attempts(), "event tap still disabled, retrying");
+                retry_at = Some(now + delay);
+            }
+        }
+        if recovered_at.is_some_and(|at| now.duration_since(at) >= RECOVERY_RESET_AFTER) {
+            backoff.reset();
+            recovered_at = None;
+        }
+    }
+
+    // Tap will be automatically cleaned up when it goes out of scope
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listener_creation() {
+        let (tx, _rx) = mpsc::channel(32);
+        let listener = EventTapSource::new(tx, TriggerKeys::default(), true);
+        assert!(!listener.is_running());
+    }
+
+    #[test]
+    fn test_modifiers_from_flags() {
+        let state = modifiers_from_flags(flags::CONTROL | flags::OPTION);
+        assert!(state.control && state.option && !state.command);
+    }
+
+    #[test]
+    fn test_trigger_codes() {
+        assert_eq!(trigger_for_keycode(0x4F), Some(TriggerKey::F18));
+        assert_eq!(trigger_for_keycode(0x00), None);
+        assert_eq!(trigger_for_button(3), Some(TriggerKey::Mouse4));
+        assert_eq!(trigger_for_button(0), None);
+    }
+
+    #[test]
+    fn test_device_bits_distinguish_sides() {
+        let state = modifiers_from_bits(flags::OPTION.bits() | flags::device::RIGHT_OPTION);
+        assert!(state.option && state.right_option && !state.left_option);
+    }
+}
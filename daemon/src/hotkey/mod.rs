@@ -1,11 +1,31 @@
 //! Hotkey module for global keyboard event listening
 //!
-//! Uses macOS CGEventTap to monitor modifier key press/release events
-//! for triggering mode transitions.
+//! Monitors modifier keys, plus any bound trigger keys and mouse buttons,
+//! for triggering mode transitions. Each platform implements
+//! `HotkeySource`: CGEventTap on macOS, evdev on Linux, and a synthetic
+//! source fed over IPC for end-to-end tests. `HotkeyListener` wraps the
+//! source so it can be restarted and rebound at runtime. The event stream
+//! can be recorded to JSONL for later replay.
 
+mod bindings;
+mod chord;
+mod conflicts;
 mod keys;
+#[cfg(target_os = "linux")]
+mod linux;
 mod listener;
+#[cfg(target_os = "macos")]
+mod macos;
+mod recorder;
+mod source;
+mod synthetic;
+mod trigger;
 
+pub use bindings::Bindings;
+pub use chord::Chord;
+pub use conflicts::{overlaps, system_conflicts};
 pub use keys::ModifierState;
-pub use listener::{HotkeyEvent, HotkeyListener};
-
+pub use listener::HotkeyListener;
+pub use recorder::{read_recording, HotkeyRecorder, RecordedEvent};
+pub use source::{platform_source, HotkeyEvent, HotkeySource};
+pub use synthetic::{SyntheticInput, SyntheticSource};
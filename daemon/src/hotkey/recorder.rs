@@ -0,0 +1,148 @@
+//! Hotkey event recording
+//!
+//! Writes the `HotkeyEvent` stream to a JSONL file, one event per line,
+//! stamped with a monotonic offset from the start of the recording.
+//! Recordings can be replayed into a fresh state machine with
+//! `state::replay`.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::source::HotkeyEvent;
+
+/// A single line of a hotkey recording
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Microseconds since the recording started
+    pub elapsed_us: u64,
+    /// The event as it was delivered to the state machine
+    pub event: HotkeyEvent,
+}
+
+/// Appends hotkey events to a JSONL recording file
+pub struct HotkeyRecorder {
+    writer: LineWriter<File>,
+    started_at: Instant,
+}
+
+impl HotkeyRecorder {
+    /// Create a new recording at `path`, truncating any existing file
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Ok(Self {
+            writer: LineWriter::new(File::create(path)?),
+            started_at: Instant::now(),
+        })
+    }
+
+    /// Record an event that was observed at `at`
+    pub fn record(&mut self, event: &HotkeyEvent, at: Instant) -> std::io::Result<()> {
+        let line = RecordedEvent {
+            elapsed_us: at.saturating_duration_since(self.started_at).as_micros() as u64,
+            event: event.clone(),
+        };
+
+        serde_json::to_writer(&mut self.writer, &line)?;
+        self.writer.write_all(b"\n")
+    }
+}
+
+/// Errors that can occur while reading a recording
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingError {
+    #[error("failed to read recording: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid event on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("timestamp on line {line} goes backwards")]
+    NonMonotonic { line: usize },
+}
+
+/// Read a JSONL recording, skipping blank lines
+pub fn read_recording<R: Read>(reader: R) -> Result<Vec<RecordedEvent>, RecordingError> {
+    let mut events = Vec::new();
+    let mut last_elapsed_us = 0;
+
+    for (index, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let event: RecordedEvent = serde_json::from_str(&line).map_err(|source| {
+            RecordingError::Parse {
+                line: index + 1,
+                source,
+            }
+        })?;
+
+        if event.elapsed_us < last_elapsed_us {
+            return Err(RecordingError::NonMonotonic { line: index + 1 });
+        }
+        last_elapsed_us = event.elapsed_us;
+
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::hotkey::ModifierState;
+
+    #[test]
+    fn test_record_and_read_back() {
+        let path = std::env::temp_dir().join(format!(
+            "second-brain-recording-{}.jsonl",
+            std::process::id()
+        ));
+        let event = HotkeyEvent::ModifierChanged(ModifierState {
+            control: true,
+            option: false,
+            command: false,
+            ..Default::default()
+        });
+
+        let mut recorder = HotkeyRecorder::create(&path).unwrap();
+        let start = recorder.started_at;
+        recorder.record(&event, start + Duration::from_millis(5)).unwrap();
+        recorder.record(&HotkeyEvent::TapDisabled, start + Duration::from_millis(9)).unwrap();
+        drop(recorder);
+
+        let events = read_recording(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].elapsed_us, 5_000);
+        assert_eq!(events[0].event, event);
+        assert_eq!(events[1].event, HotkeyEvent::TapDisabled);
+    }
+
+    #[test]
+    fn test_read_rejects_non_monotonic() {
+        let input = concat!(
+            r#"{"elapsed_us":10,"event":{"type":"tap_disabled"}}"#,
+            "\n",
+            r#"{"elapsed_us":5,"event":{"type":"tap_disabled"}}"#,
+        );
+
+        let err = read_recording(input.as_bytes()).unwrap_err();
+        assert!(matches!(err, RecordingError::NonMonotonic { line: 2 }));
+    }
+}
@@ -0,0 +1,111 @@
+//! Platform-neutral interface to hotkey backends
+//!
+//! A `HotkeySource` watches the keyboard and sends `HotkeyEvent`s to the
+//! state machine. Each platform provides its own source; the rest of the
+//! daemon only sees the events.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use super::bindings::Bindings;
+use super::keys::ModifierState;
+use super::trigger::TriggerKeys;
+
+/// Events sent from a hotkey source to the state machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HotkeyEvent {
+    /// Modifier or trigger key state has changed; held trigger keys are
+    /// named in the state's `keys`
+    ModifierChanged(ModifierState),
+    /// Event tap was disabled by macOS; key events are being missed and
+    /// held keys may have been released unseen
+    TapDisabled,
+    /// Event tap was re-enabled after being disabled
+    TapRecovered,
+    /// A regular key was pressed while modifier or trigger keys were held;
+    /// which key is deliberately not reported
+    KeyPressed,
+}
+
+/// A backend that produces hotkey events
+///
+/// Sources run on their own thread and deliver events over the channel
+/// they were created with. `stop` waits for that thread to exit, so a
+/// stopped source can be started again.
+pub trait HotkeySource: Send + Sync {
+    /// Short backend name, for logs
+    fn name(&self) -> &'static str;
+
+    /// Start delivering events
+    fn start(&self) -> Result<(), HotkeyError>;
+
+    /// Stop delivering events
+    fn stop(&self);
+
+    /// Check if the source is currently running
+    fn is_running(&self) -> bool;
+
+    /// Change which trigger keys are reported and whether they are
+    /// swallowed; takes effect the next time the source starts
+    fn set_trigger_keys(&self, keys: TriggerKeys, swallow: bool) {
+        let _ = (keys, swallow);
+    }
+}
+
+/// Errors that can occur in a hotkey source
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum HotkeyError {
+    #[error("hotkey listener is already running")]
+    AlreadyRunning,
+
+    #[cfg(target_os = "macos")]
+    #[error("failed to create event tap - check Accessibility permissions")]
+    EventTapCreation,
+
+    #[cfg(target_os = "linux")]
+    #[error("no keyboard devices found - is the user in the 'input' group?")]
+    NoKeyboards,
+
+    #[error("failed to spawn listener thread: {0}")]
+    ThreadSpawn(String),
+
+    #[error("hotkey listener stopped unexpectedly")]
+    Stopped,
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    #[error("hotkeys are not supported on this platform")]
+    Unsupported,
+}
+
+/// Create the hotkey source for the current platform
+///
+/// The source reports the trigger keys used by `bindings`, and swallows
+/// them when the bindings ask for it and the platform can.
+pub fn platform_source(
+    event_tx: mpsc::Sender<HotkeyEvent>,
+    bindings: &Bindings,
+) -> Result<Box<dyn HotkeySource>, HotkeyError> {
+    let keys = bindings.trigger_keys();
+
+    #[cfg(target_os = "macos")]
+    return Ok(Box::new(super::macos::EventTapSource::new(
+        event_tx,
+        keys,
+        bindings.swallow_trigger_keys,
+    )));
+
+    #[cfg(target_os = "linux")]
+    {
+        if bindings.swallow_trigger_keys && !keys.is_empty() {
+            tracing::warn!("evdev can't swallow trigger keys; other apps will still see them");
+        }
+        Ok(Box::new(super::linux::EvdevSource::new(event_tx, keys)))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        drop((event_tx, keys));
+        Err(HotkeyError::Unsupported)
+    }
+}
@@ -0,0 +1,104 @@
+//! Synthetic hotkey source for end-to-end testing
+//!
+//! Replaces the platform source when input injection is enabled. Events
+//! come from `InjectInput` IPC requests instead of the keyboard, so the
+//! full daemon can be driven from a test script without Accessibility
+//! permissions or input devices.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+use super::keys::ModifierState;
+use super::source::{HotkeyError, HotkeyEvent, HotkeySource};
+
+/// Hotkey source fed by `SyntheticInput` handles
+pub struct SyntheticSource {
+    input: SyntheticInput,
+}
+
+impl SyntheticSource {
+    /// Create a source and the handle used to inject input into it
+    pub fn new(event_tx: mpsc::Sender<HotkeyEvent>) -> (Self, SyntheticInput) {
+        let input = SyntheticInput {
+            event_tx,
+            running: Arc::new(AtomicBool::new(false)),
+        };
+        (Self { input: input.clone() }, input)
+    }
+}
+
+impl HotkeySource for SyntheticSource {
+    fn name(&self) -> &'static str {
+        "synthetic"
+    }
+
+    fn start(&self) -> Result<(), HotkeyError> {
+        if self.input.running.swap(true, Ordering::SeqCst) {
+            return Err(HotkeyError::AlreadyRunning);
+        }
+        Ok(())
+    }
+
+    fn stop(&self) {
+        self.input.running.store(false, Ordering::SeqCst);
+    }
+
+    fn is_running(&self) -> bool {
+        self.input.running.load(Ordering::SeqCst)
+    }
+}
+
+/// Handle for injecting modifier changes into a `SyntheticSource`
+#[derive(Debug, Clone)]
+pub struct SyntheticInput {
+    event_tx: mpsc::Sender<HotkeyEvent>,
+    running: Arc<AtomicBool>,
+}
+
+impl SyntheticInput {
+    /// Deliver a modifier change as if it came from the keyboard
+    ///
+    /// Returns `false` if the source is stopped or the state machine is gone.
+    pub async fn inject(&self, modifiers: ModifierState) -> bool {
+        self.send(HotkeyEvent::ModifierChanged(modifiers)).await
+    }
+
+    /// Deliver a regular key press, typed with the injected keys still held
+    pub async fn press_key(&self) -> bool {
+        self.send(HotkeyEvent::KeyPressed).await
+    }
+
+    async fn send(&self, event: HotkeyEvent) -> bool {
+        self.running.load(Ordering::SeqCst) && self.event_tx.send(event).await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_inject_only_while_running() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let (source, input) = SyntheticSource::new(tx);
+        let control = ModifierState {
+            control: true,
+            option: false,
+            command: false,
+            ..Default::default()
+        };
+
+        assert!(!input.inject(control).await);
+
+        source.start().unwrap();
+        assert!(input.inject(control).await);
+        assert_eq!(rx.recv().await, Some(HotkeyEvent::ModifierChanged(control)));
+        assert!(input.press_key().await);
+        assert_eq!(rx.recv().await, Some(HotkeyEvent::KeyPressed));
+
+        source.stop();
+        assert!(!input.inject(control).await);
+    }
+}
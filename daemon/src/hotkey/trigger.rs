@@ -0,0 +1,182 @@
+//! Trigger keys: regular keys and mouse buttons that can start a mode
+//!
+//! Unlike modifiers, trigger keys are keys nothing else uses much — the
+//! extra function keys, a CapsLock key, or extra mouse buttons — so they can
+//! be bound on their own and optionally swallowed before other apps see them.
+
+use serde::{Deserialize, Serialize};
+
+/// A non-modifier key or mouse button that can be part of a chord
+///
+/// Mouse buttons are numbered the way users count them: 3 is the middle
+/// button, 4 and 5 are the side (back/forward) buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerKey {
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    CapsLock,
+    /// Middle mouse button
+    Mouse3,
+    /// First side mouse button (usually "back")
+    Mouse4,
+    /// Second side mouse button (usually "forward")
+    Mouse5,
+}
+
+impl TriggerKey {
+    /// All trigger keys, in display order
+    pub const ALL: [TriggerKey; 11] = [
+        TriggerKey::F13,
+        TriggerKey::F14,
+        TriggerKey::F15,
+        TriggerKey::F16,
+        TriggerKey::F17,
+        TriggerKey::F18,
+        TriggerKey::F19,
+        TriggerKey::CapsLock,
+        TriggerKey::Mouse3,
+        TriggerKey::Mouse4,
+        TriggerKey::Mouse5,
+    ];
+
+    /// Short label used when rendering chords
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerKey::F13 => "F13",
+            TriggerKey::F14 => "F14",
+            TriggerKey::F15 => "F15",
+            TriggerKey::F16 => "F16",
+            TriggerKey::F17 => "F17",
+            TriggerKey::F18 => "F18",
+            TriggerKey::F19 => "F19",
+            TriggerKey::CapsLock => "CapsLock",
+            TriggerKey::Mouse3 => "Mouse3",
+            TriggerKey::Mouse4 => "Mouse4",
+            TriggerKey::Mouse5 => "Mouse5",
+        }
+    }
+
+    /// Whether this is a mouse button rather than a keyboard key
+    pub fn is_mouse_button(self) -> bool {
+        matches!(self, TriggerKey::Mouse3 | TriggerKey::Mouse4 | TriggerKey::Mouse5)
+    }
+
+    /// Whether the key reports a lock state rather than being held
+    ///
+    /// On macOS CapsLock only shows up as its lock flag, so a chord with it
+    /// latches on one press and releases on the next.
+    pub fn latches(self) -> bool {
+        cfg!(target_os = "macos") && self == TriggerKey::CapsLock
+    }
+
+    const fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// A set of trigger keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "Vec<TriggerKey>", into = "Vec<TriggerKey>")]
+pub struct TriggerKeys {
+    bits: u16,
+}
+
+impl TriggerKeys {
+    /// Create a set from a list of keys
+    pub const fn new(keys: &[TriggerKey]) -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < keys.len() {
+            bits |= keys[i].bit();
+            i += 1;
+        }
+        Self { bits }
+    }
+
+    /// Check whether the set includes the given key
+    pub fn contains(&self, key: TriggerKey) -> bool {
+        self.bits & key.bit() != 0
+    }
+
+    /// Add a key, or remove it when `held` is false
+    pub fn set(&mut self, key: TriggerKey, held: bool) {
+        if held {
+            self.bits |= key.bit();
+        } else {
+            self.bits &= !key.bit();
+        }
+    }
+
+    /// Check whether every key of `other` is in this set
+    pub fn contains_all(&self, other: TriggerKeys) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    /// Keys in either set
+    pub fn union(self, other: TriggerKeys) -> Self {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    /// Check whether the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Check whether any key in the set is a mouse button
+    pub fn has_mouse_buttons(&self) -> bool {
+        self.keys().into_iter().any(TriggerKey::is_mouse_button)
+    }
+
+    /// The keys in this set, in display order
+    pub fn keys(&self) -> Vec<TriggerKey> {
+        TriggerKey::ALL
+            .into_iter()
+            .filter(|k| self.contains(*k))
+            .collect()
+    }
+}
+
+impl From<Vec<TriggerKey>> for TriggerKeys {
+    fn from(keys: Vec<TriggerKey>) -> Self {
+        Self::new(&keys)
+    }
+}
+
+impl From<TriggerKeys> for Vec<TriggerKey> {
+    fn from(keys: TriggerKeys) -> Self {
+        keys.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_macos_caps_lock_latches() {
+        assert_eq!(TriggerKey::CapsLock.latches(), cfg!(target_os = "macos"));
+        assert!(!TriggerKey::F18.latches());
+        assert!(!TriggerKey::Mouse4.latches());
+    }
+
+    #[test]
+    fn test_trigger_keys_set() {
+        let mut keys = TriggerKeys::default();
+        keys.set(TriggerKey::F18, true);
+        keys.set(TriggerKey::Mouse4, true);
+        assert!(keys.has_mouse_buttons());
+        assert!(keys.contains_all(TriggerKeys::new(&[TriggerKey::F18])));
+
+        keys.set(TriggerKey::Mouse4, false);
+        assert!(!keys.has_mouse_buttons());
+        assert_eq!(serde_json::to_string(&keys).unwrap(), r#"["f18"]"#);
+    }
+}
@@ -0,0 +1,36 @@
+//! Unix domain socket client for talking to a running daemon
+//!
+//! Used by the CLI subcommands; the menu bar app has its own Swift client.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tokio::net::UnixStream;
+
+use super::frame::{read_frame, write_frame, MAX_MESSAGE_LEN};
+use super::protocol::{Request, Response};
+
+/// A connection to the daemon's IPC socket
+pub struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    /// Connect to the daemon listening on `socket_path`
+    pub async fn connect(socket_path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket_path)
+            .await
+            .with_context(|| format!("failed to connect to {}", socket_path.display()))?;
+        Ok(Self { stream })
+    }
+
+    /// Send a request and wait for its response
+    pub async fn request(&mut self, request: &Request) -> Result<Response> {
+        write_frame(&mut self.stream, request).await?;
+
+        let frame = read_frame(&mut self.stream, MAX_MESSAGE_LEN)
+            .await?
+            .context("daemon closed the connection")?;
+        serde_json::from_slice(&frame).context("failed to parse response")
+    }
+}
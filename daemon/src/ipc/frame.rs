@@ -0,0 +1,75 @@
+//! Length-prefixed JSON framing
+//!
+//! Every message is a 4-byte little-endian length followed by that many
+//! bytes of JSON. Shared by the server and the client.
+
+use std::io;
+
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Largest message body the client accepts, and the server's default limit
+pub const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// Read one frame, returning `None` if the peer closed the connection
+///
+/// Frames larger than `max_len` fail with `InvalidData`.
+pub async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, max_len: usize) -> io::Result<Option<Vec<u8>>> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message too large ({} bytes)", len),
+        ));
+    }
+
+    let mut msg_buf = vec![0u8; len];
+    reader.read_exact(&mut msg_buf).await?;
+    Ok(Some(msg_buf))
+}
+
+/// Serialize and write one frame
+pub async fn write_frame<W: AsyncWrite + Unpin, T: Serialize>(
+    writer: &mut W,
+    msg: &T,
+) -> io::Result<()> {
+    let msg_bytes = serde_json::to_vec(msg)?;
+    let msg_len = (msg_bytes.len() as u32).to_le_bytes();
+
+    writer.write_all(&msg_len).await?;
+    writer.write_all(&msg_bytes).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_frame_roundtrip() {
+        let (mut a, mut b) = tokio::io::duplex(64);
+        write_frame(&mut a, &"hello").await.unwrap();
+        drop(a);
+
+        let frame = read_frame(&mut b, MAX_MESSAGE_LEN).await.unwrap().unwrap();
+        assert_eq!(frame, br#""hello""#);
+        assert!(read_frame(&mut b, MAX_MESSAGE_LEN).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_frame_rejected() {
+        let (mut a, mut b) = tokio::io::duplex(64);
+        a.write_all(&(MAX_MESSAGE_LEN as u32 + 1).to_le_bytes()).await.unwrap();
+
+        let err = read_frame(&mut b, MAX_MESSAGE_LEN).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -1,7 +1,12 @@
 //! IPC module for daemon-UI communication
 
+mod client;
+mod diagnostics;
+mod frame;
 mod protocol;
 mod server;
 
-pub use protocol::{Request, Response, DaemonStatus, Mode, Notification};
+pub use client::Client;
+pub use frame::MAX_MESSAGE_LEN;
+pub use protocol::{DaemonPaths, Request, Response};
 pub use server::Server;
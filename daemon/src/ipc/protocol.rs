@@ -2,16 +2,23 @@
 //!
 //! All messages are JSON-encoded, prefixed with a 4-byte little-endian length.
 
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
+use crate::config::Diagnostic;
 use crate::events::StateEvent;
-use crate::state::State;
+use crate::hotkey::{Bindings, Chord, ModifierState};
+use crate::lifecycle::{ComponentHealth, ComponentStatus, HealthState, PauseReason, PauseStatus, QuietHours};
+use crate::secrets::Secret;
+use crate::state::{DiagramFormat, EnabledModes, MachineDescription, State};
 
 /// Current operating mode of the daemon
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Mode {
     /// No active mode, waiting for hotkey
+    #[default]
     Idle,
     /// Dictation mode: low-latency transcription
     Dictation,
@@ -21,9 +28,20 @@ pub enum Mode {
     Agent,
 }
 
-impl Default for Mode {
-    fn default() -> Self {
-        Self::Idle
+impl Mode {
+    /// The state machine state this mode corresponds to
+    pub fn state(self) -> State {
+        match self {
+            Mode::Idle => State::Idle,
+            Mode::Dictation => State::DictationActive,
+            Mode::Intelligent => State::IntelligentActive,
+            Mode::Agent => State::AgentActive,
+        }
+    }
+
+    /// The enabled modes as a list, in display order
+    pub fn enabled(modes: &EnabledModes) -> Vec<Mode> {
+        modes.states().into_iter().map(Mode::from).collect()
     }
 }
 
@@ -42,14 +60,94 @@ pub enum Request {
     
     /// Subscribe to state change notifications
     Subscribe,
+
+    /// Describe the state machine's states, triggers and guards
+    GetStateMachine,
+
+    /// Render the state machine as a diagram, highlighting the current state
+    RenderStateMachine { format: DiagramFormat },
+
+    /// Enable or disable a mode (persisted across restarts)
+    SetModeEnabled { mode: Mode, enabled: bool },
+
+    /// Disarm the hotkeys for `duration_secs`, or until resumed
+    Pause {
+        #[serde(default)]
+        duration_secs: Option<u64>,
+    },
+
+    /// Re-arm the hotkeys (skips the current quiet-hour window)
+    Resume,
+
+    /// Replace the quiet-hour schedules (persisted across restarts)
+    SetQuietHours { schedules: Vec<QuietHours> },
+
+    /// Feed a modifier change to the synthetic hotkey source
+    /// (only when the daemon runs with input injection enabled)
+    InjectInput { modifiers: ModifierState },
+
+    /// Feed a regular key press to the synthetic hotkey source, as if typed
+    /// with the currently injected modifiers held
+    InjectKeyPress,
+
+    /// Get the chords bound to each mode
+    GetBindings,
+
+    /// Rebind the modes (persisted across restarts, applied without
+    /// restarting the daemon)
+    SetBindings { bindings: Bindings },
+
+    /// Wait for the next chord the user presses and report it instead of
+    /// acting on it (onboarding hotkey setup)
+    CaptureChord {
+        #[serde(default)]
+        timeout_secs: Option<u64>,
+    },
+
+    /// Bind a mode to the chord from the last successful capture
+    CommitCapture { mode: Mode },
+
+    /// Get conflicts between the bound chords and existing shortcuts
+    GetHotkeyWarnings,
+
+    /// Get where `config.toml` is and any problems found in it
+    GetConfigStatus,
+
+    /// Get where the daemon keeps its socket, config and state
+    GetPaths,
+
+    /// Get whether each component works, and the daemon overall
+    GetHealth,
+
+    /// Write a diagnostic snapshot of the daemon to a file (as SIGUSR1 does)
+    DumpDiagnostics,
+
+    /// List the profiles in `config.toml` and the active one
+    GetProfiles,
+
+    /// Switch to a profile, or back to the plain config with none
+    /// (persisted across restarts)
+    SetProfile {
+        #[serde(default)]
+        profile: Option<String>,
+    },
+
+    /// Store a provider credential under `name`, replacing any old value
+    SetSecret { name: String, value: Secret },
+
+    /// List the names of the stored secrets; values are never sent
+    ListSecrets,
+
+    /// Remove the secret stored under `name`
+    DeleteSecret { name: String },
 }
 
 /// Responses from daemon to UI
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Response {
-    /// Current daemon status
-    Status(DaemonStatus),
+    /// Current daemon status (boxed, it is much larger than the rest)
+    Status(Box<DaemonStatus>),
     
     /// Mode change notification
     ModeChange { mode: Mode, active: bool },
@@ -59,6 +157,63 @@ pub enum Response {
     
     /// Subscription confirmed
     Subscribed,
+
+    /// State machine description
+    StateMachine(MachineDescription),
+
+    /// Rendered state machine diagram
+    Diagram { format: DiagramFormat, source: String },
+
+    /// Modes that are currently enabled
+    EnabledModes { modes: Vec<Mode> },
+
+    /// Pause state after a pause-related request
+    PauseStatus(PauseStatus),
+
+    /// Injected input was delivered to the state machine
+    InputInjected,
+
+    /// Chords currently bound to each mode, with any conflicts found
+    Bindings {
+        bindings: Bindings,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<HotkeyWarning>,
+    },
+
+    /// The chord pressed during a capture, with its exact keys and sides
+    ChordCaptured { chord: Chord, label: String },
+
+    /// Conflicts between the bound chords and existing shortcuts
+    HotkeyWarnings { warnings: Vec<HotkeyWarning> },
+
+    /// The config file and what was wrong with it; with errors present
+    /// the daemon runs on defaults (`applied` is false)
+    ConfigStatus {
+        path: Option<PathBuf>,
+        applied: bool,
+        diagnostics: Vec<Diagnostic>,
+    },
+
+    /// Where the daemon keeps its files
+    Paths(DaemonPaths),
+
+    /// Health of each component; `status` is the worst of them
+    Health {
+        status: HealthState,
+        components: Vec<ComponentHealth>,
+    },
+
+    /// Where the diagnostic snapshot was written
+    DiagnosticsWritten { path: PathBuf },
+
+    /// Profiles defined in `config.toml`
+    Profiles {
+        active: Option<String>,
+        available: Vec<String>,
+    },
+
+    /// Names of the stored secrets, after any change
+    Secrets { names: Vec<String> },
     
     /// Error response
     Error { code: String, message: String },
@@ -74,7 +229,34 @@ pub enum Notification {
         previous: Mode,
     },
     /// State event occurred
-    StateEvent(StateEvent),
+    StateEvent { event: StateEvent },
+    /// Hotkeys were disarmed
+    Paused {
+        reason: Option<PauseReason>,
+        until_unix_secs: Option<u64>,
+    },
+    /// Hotkeys were re-armed
+    Resumed,
+    /// `config.toml` was re-read; with errors (`applied` false) the
+    /// previous config stays in effect
+    ConfigReloaded {
+        applied: bool,
+        /// Keys whose new values are in effect
+        changed: Vec<String>,
+        /// Keys whose new values need a daemon restart
+        restart_required: Vec<String>,
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// Another profile became active, or the active one was removed from
+    /// `config.toml`
+    ProfileChanged {
+        profile: Option<String>,
+        /// Keys whose values changed with it
+        changed: Vec<String>,
+    },
+    /// The daemon is stopping; the last notification before the connection
+    /// closes
+    ShuttingDown { reason: String },
 }
 
 /// Full daemon status snapshot
@@ -88,17 +270,95 @@ pub struct DaemonStatus {
     
     /// Whether hotkey is registered
     pub hotkey_registered: bool,
+
+    /// Modes the user can currently enter
+    pub enabled_modes: Vec<Mode>,
+
+    /// Whether hotkeys are disarmed, and why
+    pub pause: PauseStatus,
+
+    /// Version of the state snapshot this status was built from
+    #[serde(default)]
+    pub state_version: u64,
+
+    /// Whether a chord capture is waiting for keys
+    #[serde(default)]
+    pub capturing: bool,
+
+    /// How often the OS disabled the hotkey tap (macOS)
+    #[serde(default)]
+    pub tap_disabled_count: u64,
+
+    /// How often the hotkey tap was re-enabled after being disabled
+    #[serde(default)]
+    pub tap_recovered_count: u64,
+
+    /// Errors in `config.toml`; details via `get_config_status`
+    #[serde(default)]
+    pub config_errors: usize,
+
+    /// Active profile from `config.toml`, if any
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Supervised components, with their restarts and last errors
+    #[serde(default)]
+    pub components: Vec<ComponentStatus>,
     
     /// Uptime in seconds
     pub uptime_secs: u64,
 }
 
+/// Locations the running daemon resolved at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonPaths {
+    /// IPC socket
+    pub socket: PathBuf,
+    /// `config.toml`, which may not exist
+    pub config_file: PathBuf,
+    /// Directory for settings and other state kept across restarts
+    pub data_dir: PathBuf,
+    /// Persisted runtime settings
+    pub settings_file: PathBuf,
+}
+
+/// Why a mode's chord may fire when the user didn't mean it to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyWarningKind {
+    /// The chord alone already triggers an OS or common app shortcut
+    SystemShortcut,
+    /// The same keys are also held for another mode
+    OtherMode,
+    /// The chord is often held while typing, i.e. used as an app shortcut
+    UsedAsShortcut,
+    /// The chord has a key that latches, so a mode held with it toggles
+    Latches,
+}
+
+/// A possible conflict for the chord bound to `mode`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotkeyWarning {
+    pub mode: Mode,
+    pub kind: HotkeyWarningKind,
+    pub message: String,
+}
+
 impl Default for DaemonStatus {
     fn default() -> Self {
         Self {
             version: env!("CARGO_PKG_VERSION").to_string(),
             mode: Mode::default(),
             hotkey_registered: false,
+            enabled_modes: Mode::enabled(&EnabledModes::default()),
+            pause: PauseStatus::default(),
+            state_version: 0,
+            capturing: false,
+            tap_disabled_count: 0,
+            tap_recovered_count: 0,
+            config_errors: 0,
+            profile: None,
+            components: Vec::new(),
             uptime_secs: 0,
         }
     }
@@ -128,9 +388,60 @@ mod tests {
         assert!(json.contains("dictation"));
     }
 
+    #[test]
+    fn test_render_request_deserialization() {
+        let json = r#"{"type":"render_state_machine","format":"mermaid"}"#;
+        let req: Request = serde_json::from_str(json).unwrap();
+        assert!(matches!(req, Request::RenderStateMachine { format: DiagramFormat::Mermaid }));
+    }
+
+    #[test]
+    fn test_enabled_modes_listing() {
+        let mut modes = EnabledModes::all();
+        modes.set(State::IntelligentActive, false);
+        assert_eq!(Mode::enabled(&modes), [Mode::Dictation, Mode::Agent]);
+    }
+
+    #[test]
+    fn test_pause_request_defaults_to_indefinite() {
+        let req: Request = serde_json::from_str(r#"{"type":"pause"}"#).unwrap();
+        assert!(matches!(req, Request::Pause { duration_secs: None }));
+    }
+
+    #[test]
+    fn test_inject_input_deserialization() {
+        let json = r#"{"type":"inject_input","modifiers":{"control":true,"option":false,"command":true}}"#;
+        let req: Request = serde_json::from_str(json).unwrap();
+        assert!(matches!(
+            req,
+            Request::InjectInput { modifiers } if modifiers.control && modifiers.command && !modifiers.option
+        ));
+    }
+
+    #[test]
+    fn test_set_bindings_deserialization() {
+        let json = r#"{"type":"set_bindings","bindings":{"dictation":["right_option"],"agent":["f18"]}}"#;
+        let req: Request = serde_json::from_str(json).unwrap();
+        let Request::SetBindings { bindings } = req else {
+            panic!("unexpected request {:?}", req);
+        };
+        assert_eq!(bindings.dictation.to_string(), "ROpt");
+        assert_eq!(bindings.intelligent, Bindings::default().intelligent);
+        assert_eq!(bindings.agent.to_string(), "F18");
+    }
+
+    #[test]
+    fn test_state_event_notification_serialization() {
+        let notification = Notification::StateEvent {
+            event: StateEvent::DictationStarted,
+        };
+        let json = serde_json::to_string(&notification).unwrap();
+        assert_eq!(json, r#"{"type":"state_event","event":{"type":"dictation_started"}}"#);
+    }
+
     #[test]
     fn test_response_serialization() {
-        let resp = Response::Status(DaemonStatus::default());
+        let resp = Response::Status(Box::default());
         let json = serde_json::to_string(&resp).unwrap();
         assert!(json.contains("status"));
     }
@@ -3,52 +3,221 @@
 //! Provides request-response communication and push notifications for
 //! state change events to subscribed clients.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use serde::Serialize;
 use tokio::net::{UnixListener, UnixStream};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
+use crate::config::{ConfigDiff, ConfigFile, Diagnostic, Severity};
 use crate::events::StateEvent;
-use crate::state::State;
+use crate::hotkey::{overlaps, system_conflicts, Bindings, Chord, HotkeyListener, ModifierState, SyntheticInput};
+use crate::lifecycle::{overall, HealthRegistry, HealthState, PauseHandle, PauseRequest, Supervisor};
+use crate::secrets::{SecretError, SecretStore};
+use crate::settings::Settings;
+use crate::state::{
+    render_diagram, Command, EnabledModes, MachineDescription, Snapshot, State, StateStore, TransitionTable,
+};
 
-use super::protocol::{DaemonStatus, Mode, Notification, Request, Response};
+use super::diagnostics::{redacted_config, unix_millis, Connections, Diagnostics};
+use super::frame::{read_frame, write_frame};
+use super::protocol::{DaemonPaths, DaemonStatus, HotkeyWarning, HotkeyWarningKind, Mode, Notification, Request, Response};
+
+/// Key presses under a held chord before it counts as an app shortcut
+const SHORTCUT_USE_WARNING: u64 = 3;
+
+/// How long a cancelled chord capture gets to answer its client, and
+/// connections get to close, at shutdown
+const CANCEL_GRACE: Duration = Duration::from_millis(500);
 
 /// IPC Server handling client connections
 pub struct Server {
     socket_path: PathBuf,
-    listener: Option<UnixListener>,
+    /// Bound ahead of time, taken by `run`
+    listener: Mutex<Option<UnixListener>>,
     state: Arc<RwLock<ServerState>>,
+    /// Cleared at shutdown, when `run` stops accepting connections
+    accepting: watch::Sender<bool>,
+    /// Set once the state machine is gone and its last events have been
+    /// passed on to subscribers
+    events_forwarded: watch::Sender<bool>,
+    /// Set at shutdown once the state machine has stopped: clients finish
+    /// the request in flight, then disconnect
+    closing: watch::Sender<bool>,
+    /// Drops client connections outright, when they don't finish in time
     shutdown_tx: broadcast::Sender<()>,
-    /// Channel for receiving state events to broadcast to subscribed clients
-    event_rx: Option<broadcast::Receiver<StateEvent>>,
+    /// Notifications pushed to subscribed clients
+    notify_tx: broadcast::Sender<Notification>,
+    /// Connections currently being served
+    clients: Arc<watch::Sender<usize>>,
+    /// Requests being processed, chord captures included
+    requests: Arc<watch::Sender<usize>>,
+}
+
+/// A message written to a client: either a response or a notification
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Outgoing {
+    Response(Response),
+    Notification(Notification),
 }
 
 /// Shared server state
 struct ServerState {
-    status: DaemonStatus,
     start_time: std::time::Instant,
-    /// Current internal state (for mode tracking)
-    current_state: State,
+    /// Authoritative daemon state, written by the state machine
+    store: StateStore,
+    /// Persisted runtime settings and where they live
+    settings: Settings,
+    settings_path: Option<PathBuf>,
+    /// Channel for sending commands to the state machine
+    commands: Option<mpsc::Sender<Command>>,
+    /// Handle to the pause controller
+    pause: Option<PauseHandle>,
+    /// Synthetic hotkey input, present only when injection is enabled
+    input: Option<SyntheticInput>,
+    /// The hotkey listener, restarted when bindings change
+    listener: Option<Arc<HotkeyListener>>,
+    /// Counts binding changes, so the listener can tell which is newest
+    bindings_version: u64,
+    /// The client whose chord capture is in progress; the state machine
+    /// captures for one at a time
+    capturing: Option<u64>,
+    /// Each client's chord from its last successful capture, ready to be
+    /// committed
+    captured: HashMap<u64, Chord>,
+    /// `config.toml` as written, without the active profile applied, and
+    /// what was wrong with it
+    config: ConfigFile,
+    config_diagnostics: Vec<Diagnostic>,
+    /// Where the socket, config and state are
+    paths: Option<DaemonPaths>,
+    /// Provider credentials
+    secrets: Option<Arc<dyn SecretStore>>,
+    /// Restarts failed components, and knows how often it had to
+    supervisor: Option<Supervisor>,
+    /// Where components report whether they work
+    health: Option<HealthRegistry>,
+    /// Connected clients and dropped messages, for diagnostics
+    connections: Arc<Connections>,
+}
+
+/// New bindings for the hotkey listener, applied once the state lock is
+/// released since a restart joins the listener thread
+struct Rebind {
+    listener: Arc<HotkeyListener>,
+    bindings: Bindings,
+    version: u64,
+}
+
+/// Decrements the client count and forgets the client when a connection ends
+struct ClientSlot {
+    clients: Arc<watch::Sender<usize>>,
+    connections: Arc<Connections>,
+    id: u64,
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.connections.disconnect(self.id);
+        self.clients.send_modify(|clients| *clients -= 1);
+    }
+}
+
+/// Counts a request as in flight until it is answered or abandoned
+struct InFlight(Arc<watch::Sender<usize>>);
+
+impl InFlight {
+    fn start(requests: &Arc<watch::Sender<usize>>) -> Self {
+        requests.send_modify(|requests| *requests += 1);
+        Self(Arc::clone(requests))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.send_modify(|requests| *requests -= 1);
+    }
+}
+
+/// Remove a socket left behind by a daemon that is gone
+///
+/// A socket something still accepts connections on belongs to a live
+/// process, e.g. a daemon with another data directory, and is left alone.
+/// Only a refused connection shows nobody listens any more; any other
+/// failure, such as not being allowed to connect, leaves the file alone
+/// too.
+fn remove_stale_socket(socket_path: &Path) -> Result<()> {
+    match std::os::unix::net::UnixStream::connect(socket_path) {
+        Ok(_) => anyhow::bail!("{} is in use by another process", socket_path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            warn!(?socket_path, "removing stale socket");
+            std::fs::remove_file(socket_path).context("failed to remove stale socket")
+        }
+        Err(e) => Err(e).with_context(|| {
+            format!("{} exists but could not be checked for a running daemon", socket_path.display())
+        }),
+    }
 }
 
 impl Server {
     /// Create a new IPC server
     pub fn new(socket_path: &Path) -> Result<Self> {
+        let listener = Self::bind(socket_path)?;
+        let (shutdown_tx, _) = broadcast::channel(1);
+        let (notify_tx, _) = broadcast::channel(64);
+
+        let state = Arc::new(RwLock::new(ServerState {
+            start_time: std::time::Instant::now(),
+            store: StateStore::new(),
+            settings: Settings::default(),
+            settings_path: None,
+            commands: None,
+            pause: None,
+            input: None,
+            listener: None,
+            bindings_version: 0,
+            capturing: None,
+            captured: HashMap::new(),
+            config: ConfigFile::default(),
+            config_diagnostics: Vec::new(),
+            paths: None,
+            secrets: None,
+            supervisor: None,
+            health: None,
+            connections: Arc::new(Connections::default()),
+        }));
+
+        Ok(Self {
+            socket_path: socket_path.to_owned(),
+            listener: Mutex::new(Some(listener)),
+            state,
+            accepting: watch::Sender::new(true),
+            events_forwarded: watch::Sender::new(false),
+            closing: watch::Sender::new(false),
+            shutdown_tx,
+            notify_tx,
+            clients: Arc::new(watch::Sender::new(0)),
+            requests: Arc::new(watch::Sender::new(0)),
+        })
+    }
+
+    /// Listen on `socket_path`, replacing a stale socket
+    fn bind(socket_path: &Path) -> Result<UnixListener> {
         // Ensure parent directory exists
         if let Some(parent) = socket_path.parent() {
             std::fs::create_dir_all(parent)
                 .context("failed to create socket directory")?;
         }
 
-        // Remove stale socket if it exists
-        if socket_path.exists() {
-            std::fs::remove_file(socket_path)
-                .context("failed to remove stale socket")?;
-        }
+        remove_stale_socket(socket_path)?;
 
         let listener = UnixListener::bind(socket_path)
             .context("failed to bind Unix socket")?;
@@ -60,64 +229,300 @@ impl Server {
             std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))?;
         }
 
-        let (shutdown_tx, _) = broadcast::channel(1);
+        info!(?socket_path, "IPC server listening");
+        Ok(listener)
+    }
 
-        let state = Arc::new(RwLock::new(ServerState {
-            status: DaemonStatus::default(),
-            start_time: std::time::Instant::now(),
-            current_state: State::Idle,
-        }));
+    /// Push state events and mode changes to subscribed clients
+    ///
+    /// Runs until the state machine is gone, and passes on everything it
+    /// sent before then.
+    pub async fn forward_events(&self, mut event_rx: broadcast::Receiver<StateEvent>) -> Result<()> {
+        let (store, connections) = {
+            let state = self.state.read().await;
+            (state.store.clone(), Arc::clone(&state.connections))
+        };
+        let mut snapshot_rx = store.subscribe();
+        let mut mode = Mode::from(snapshot_rx.borrow_and_update().state);
+        loop {
+            tokio::select! {
+                event = event_rx.recv() => match event {
+                    // Sending only fails when nobody is subscribed
+                    Ok(event) => {
+                        let _ = self.notify_tx.send(Notification::StateEvent { event });
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!(skipped = n, "notification forwarder lagged");
+                        connections.state_events_dropped(n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                Ok(()) = snapshot_rx.changed() => {
+                    let state = snapshot_rx.borrow_and_update().state;
+                    self.notify_mode_change(&mut mode, state);
+                }
+            }
+        }
 
-        info!(?socket_path, "IPC server listening");
+        // The mode the state machine stopped in
+        self.notify_mode_change(&mut mode, store.snapshot().state);
+        self.events_forwarded.send_replace(true);
+        Ok(())
+    }
 
-        Ok(Self {
-            socket_path: socket_path.to_owned(),
-            listener: Some(listener),
-            state,
-            shutdown_tx,
-            event_rx: None,
-        })
+    /// Tell subscribers the mode moved on from `mode` to that of `state`
+    fn notify_mode_change(&self, mode: &mut Mode, state: State) {
+        let new_mode = Mode::from(state);
+        if new_mode != *mode {
+            debug!(?mode, ?new_mode, "mode changed");
+            let _ = self.notify_tx.send(Notification::ModeChanged {
+                mode: new_mode,
+                previous: *mode,
+            });
+            *mode = new_mode;
+        }
     }
 
-    /// Create a new IPC server with state event subscription
-    pub fn with_events(socket_path: &Path, event_rx: broadcast::Receiver<StateEvent>) -> Result<Self> {
-        let mut server = Self::new(socket_path)?;
-        server.event_rx = Some(event_rx);
-        Ok(server)
+    /// Report the state published by the state machine to clients
+    ///
+    /// Mode changes in the store are pushed to subscribers by
+    /// `forward_events`.
+    pub async fn set_store(&self, store: StateStore) {
+        self.state.write().await.store = store;
     }
 
-    /// Update the current mode in server state
-    pub async fn set_state(&self, state: State) {
-        let mut server_state = self.state.write().await;
-        let old_state = server_state.current_state;
-        server_state.current_state = state;
-        server_state.status.mode = state.into();
-        server_state.status.hotkey_registered = true;
-        
-        if old_state != state {
-            info!(
-                from = ?old_state,
-                to = ?state,
-                "IPC server: mode updated"
-            );
+    /// Let clients rebind hotkeys, restarting `listener` when needed
+    pub async fn set_listener(&self, listener: Arc<HotkeyListener>) {
+        self.state.write().await.listener = Some(listener);
+    }
+
+    /// Allow clients to change runtime settings
+    ///
+    /// Changes are persisted to `settings_path` and forwarded to the state
+    /// machine over `commands`.
+    pub async fn set_settings(
+        &self,
+        settings: Settings,
+        settings_path: PathBuf,
+        commands: mpsc::Sender<Command>,
+    ) {
+        let mut state = self.state.write().await;
+        state.settings = settings;
+        state.settings_path = Some(settings_path);
+        state.commands = Some(commands);
+    }
+
+    /// Allow clients to pause and resume the daemon
+    ///
+    /// Pause state changes are pushed to subscribers.
+    pub async fn set_pause(&self, pause: PauseHandle) {
+        let mut status_rx = pause.subscribe();
+        let notify_tx = self.notify_tx.clone();
+
+        tokio::spawn(async move {
+            while status_rx.changed().await.is_ok() {
+                let status = status_rx.borrow_and_update().clone();
+                let notification = if status.paused {
+                    Notification::Paused {
+                        reason: status.reason,
+                        until_unix_secs: status.until_unix_secs,
+                    }
+                } else {
+                    Notification::Resumed
+                };
+                let _ = notify_tx.send(notification);
+            }
+        });
+
+        self.state.write().await.pause = Some(pause);
+    }
+
+    /// Apply limits and timeouts from `config.toml` and report its problems
+    pub async fn set_config(&self, config: ConfigFile, diagnostics: Vec<Diagnostic>) {
+        let mut state = self.state.write().await;
+        state.config = config;
+        state.config_diagnostics = diagnostics;
+        Self::report_config_health(&state);
+    }
+
+    /// Let clients set, list and delete secrets in `store`
+    pub async fn set_secrets(&self, store: Box<dyn SecretStore>) {
+        self.state.write().await.secrets = Some(Arc::from(store));
+    }
+
+    /// Report the components `supervisor` runs in the status
+    pub async fn set_supervisor(&self, supervisor: Supervisor) {
+        self.state.write().await.supervisor = Some(supervisor);
+    }
+
+    /// Answer get_health from `health`, and report the config's health to it
+    pub async fn set_health(&self, health: HealthRegistry) {
+        self.state.write().await.health = Some(health);
+    }
+
+    /// Set the locations reported to clients
+    pub async fn set_paths(&self, paths: DaemonPaths) {
+        self.state.write().await.paths = Some(paths);
+    }
+
+    /// Apply a re-read `config.toml` and tell subscribers what changed
+    ///
+    /// A file with errors is not applied, only its diagnostics are. Changed
+    /// hotkeys and modes are applied and replace those changed over IPC, so
+    /// the latest edit wins; limits and timeouts are read per request
+    /// anyway. Returns the changes if the file was applied.
+    ///
+    /// If the active profile is gone from the file, the daemon switches
+    /// back to no profile.
+    pub async fn reload_config(&self, config: ConfigFile, diagnostics: Vec<Diagnostic>) -> Option<ConfigDiff> {
+        let mut state = self.state.write().await;
+        let mut rebind = None;
+        let applied = !diagnostics.iter().any(|d| d.severity == Severity::Error);
+        let profile = state
+            .settings
+            .profile
+            .clone()
+            .filter(|name| !applied || config.profiles.contains_key(name));
+        let profile_removed = profile != state.settings.profile;
+        let active = config.active(profile.as_deref());
+        let diff = if applied {
+            ConfigDiff::between(&Self::active_config(&state), &active)
+        } else {
+            ConfigDiff::default()
+        };
+
+        if diff.is_empty() && !profile_removed && diagnostics == state.config_diagnostics {
+            // Nothing in effect changed, but keep the file as it now is
+            if applied {
+                state.config = config;
+            }
+            debug!("config unchanged");
+            return None;
         }
+
+        if applied {
+            if profile_removed {
+                warn!(profile = ?state.settings.profile, "active profile removed from config, switching to none");
+                let mut settings = state.settings.clone();
+                settings.profile = None;
+                if let Err(Response::Error { message, .. }) = Self::save_settings(&mut state, settings) {
+                    warn!(message, "failed to persist profile change");
+                }
+            }
+            state.config = config;
+            rebind = Self::apply_config(&mut state, &diff).await;
+            if profile_removed {
+                let _ = self.notify_tx.send(Notification::ProfileChanged {
+                    profile: None,
+                    changed: diff.changed.clone(),
+                });
+            }
+        }
+        state.config_diagnostics = diagnostics.clone();
+        Self::report_config_health(&state);
+        drop(state);
+        if let Err(Response::Error { message, .. }) = Self::rebind(rebind).await {
+            warn!(message, "failed to apply hotkeys from config");
+        }
+
+        info!(applied, changed = ?diff.changed, restart_required = ?diff.restart_required, "config reloaded");
+        let _ = self.notify_tx.send(Notification::ConfigReloaded {
+            applied,
+            changed: diff.changed.clone(),
+            restart_required: diff.restart_required.clone(),
+            diagnostics,
+        });
+
+        applied.then_some(diff)
+    }
+
+    /// Apply the hotkeys and modes of a newly active config
+    ///
+    /// Those it changes replace what was changed over IPC, so the latest
+    /// change wins. Returns the listener's new bindings, to apply once
+    /// `state` is released.
+    async fn apply_config(state: &mut ServerState, diff: &ConfigDiff) -> Option<Rebind> {
+        let mut rebind = None;
+        if diff.touches("hotkeys") {
+            match Self::commit_bindings(state, None).await {
+                Ok(new) => rebind = new,
+                Err(Response::Error { message, .. }) => warn!(message, "failed to apply hotkeys from config"),
+                Err(_) => {}
+            }
+        }
+        if diff.touches("modes") {
+            if let Response::Error { message, .. } = Self::set_enabled_modes(state, None).await {
+                warn!(message, "failed to apply modes from config");
+            }
+        }
+        rebind
     }
 
-    /// Run the server, accepting connections
+    /// `config.toml` with the active profile applied
+    fn active_config(state: &ServerState) -> ConfigFile {
+        state.config.active(state.settings.profile.as_deref())
+    }
+
+    /// Accept `InjectInput` requests and feed them to `input`
+    pub async fn set_input(&self, input: SyntheticInput) {
+        warn!("input injection enabled - hotkeys come from IPC, not the keyboard");
+        self.state.write().await.input = Some(input);
+    }
+
+    /// Run the server, accepting connections until `stop_accepting`
+    ///
+    /// Binds the socket again when run after a previous run failed.
     pub async fn run(&self) -> Result<()> {
-        let listener = self.listener.as_ref()
-            .context("server not initialized")?;
+        let listener = self.listener.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let listener = match listener {
+            Some(listener) => listener,
+            None if !*self.accepting.borrow() => return Ok(()),
+            None => Self::bind(&self.socket_path)?,
+        };
 
+        let mut accepting = self.accepting.subscribe();
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = accepting.wait_for(|accepting| !*accepting) => {
+                    info!("no longer accepting connections");
+                    return Ok(());
+                }
+            };
+            match accepted {
                 Ok((stream, _addr)) => {
-                    debug!("client connected");
+                    let (max_clients, connections) = {
+                        let state = self.state.read().await;
+                        (state.config.ipc.max_clients, Arc::clone(&state.connections))
+                    };
+                    let admitted = self.clients.send_if_modified(|clients| {
+                        let admitted = *clients < max_clients;
+                        *clients += usize::from(admitted);
+                        admitted
+                    });
+                    if !admitted {
+                        warn!(max_clients, "too many clients, turning connection away");
+                        tokio::spawn(Self::reject_client(stream));
+                        continue;
+                    }
+                    let id = connections.connect();
+                    let slot = ClientSlot {
+                        clients: Arc::clone(&self.clients),
+                        connections: Arc::clone(&connections),
+                        id,
+                    };
+
+                    debug!(client = id, "client connected");
                     let state = Arc::clone(&self.state);
+                    let notify_tx = self.notify_tx.clone();
+                    let closing = self.closing.subscribe();
+                    let requests = Arc::clone(&self.requests);
                     let mut shutdown_rx = self.shutdown_tx.subscribe();
-                    
+
                     tokio::spawn(async move {
+                        let _slot = slot;
                         tokio::select! {
-                            result = Self::handle_client(stream, state) => {
+                            result = Self::handle_client(stream, Arc::clone(&state), notify_tx, closing, requests, connections, id) => {
                                 if let Err(e) = result {
                                     warn!(?e, "client handler error");
                                 }
@@ -126,6 +531,7 @@ impl Server {
                                 debug!("client handler shutting down");
                             }
                         }
+                        Self::forget_captures(&state, id).await;
                     });
                 }
                 Err(e) => {
@@ -135,91 +541,973 @@ impl Server {
         }
     }
 
+    /// Tell a client over the connection limit why it is being dropped
+    async fn reject_client(mut stream: UnixStream) {
+        let response = Response::Error {
+            code: "too_many_clients".to_string(),
+            message: "the daemon is serving too many clients".to_string(),
+        };
+        let _ = write_frame(&mut stream, &Outgoing::Response(response)).await;
+    }
+
     /// Handle a single client connection
-    async fn handle_client(mut stream: UnixStream, state: Arc<RwLock<ServerState>>) -> Result<()> {
-        let mut len_buf = [0u8; 4];
-        let mut is_subscribed = false;
+    ///
+    /// Responses and notifications share one writer so frames never
+    /// interleave. Once `closing` is set the connection is closed after the
+    /// request in flight is answered and queued messages are written.
+    async fn handle_client(
+        stream: UnixStream,
+        state: Arc<RwLock<ServerState>>,
+        notify_tx: broadcast::Sender<Notification>,
+        closing: watch::Receiver<bool>,
+        requests: Arc<watch::Sender<usize>>,
+        connections: Arc<Connections>,
+        id: u64,
+    ) -> Result<()> {
+        let (mut reader, mut writer) = stream.into_split();
+        let (out_tx, mut out_rx) = mpsc::channel::<Outgoing>(32);
+        let mut forwarder: Option<JoinHandle<()>> = None;
 
-        loop {
-            // Read message length (4-byte little-endian)
-            match stream.read_exact(&mut len_buf).await {
-                Ok(_) => {}
-                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                    debug!("client disconnected");
-                    return Ok(());
-                }
-                Err(e) => return Err(e.into()),
+        let write_loop = async move {
+            while let Some(msg) = out_rx.recv().await {
+                write_frame(&mut writer, &msg).await?;
             }
+            Ok::<(), anyhow::Error>(())
+        };
+        tokio::pin!(write_loop);
 
-            let len = u32::from_le_bytes(len_buf) as usize;
-            if len > 1024 * 1024 {
-                warn!(len, "message too large, disconnecting");
-                return Ok(());
-            }
+        // Resolves to true when the daemon is closing the connection
+        let read_loop = async {
+            let mut closing = closing.clone();
+            loop {
+                // Read the next length-prefixed message
+                let max_len = state.read().await.config.ipc.max_message_bytes;
+                let frame = tokio::select! {
+                    frame = read_frame(&mut reader, max_len) => frame,
+                    _ = closing.wait_for(|closing| *closing) => return Ok(true),
+                };
+                let msg_buf = match frame {
+                    Ok(Some(buf)) => buf,
+                    Ok(None) => {
+                        debug!("client disconnected");
+                        return Ok(false);
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                        warn!(?e, "message too large, disconnecting");
+                        return Ok(false);
+                    }
+                    Err(e) => return Err(e.into()),
+                };
 
-            // Read message body
-            let mut msg_buf = vec![0u8; len];
-            stream.read_exact(&mut msg_buf).await?;
+                // Parse request
+                let request: Request = serde_json::from_slice(&msg_buf)
+                    .context("failed to parse request")?;
 
-            // Parse request
-            let request: Request = serde_json::from_slice(&msg_buf)
-                .context("failed to parse request")?;
-            
-            debug!(?request, "received request");
+                debug!(?request, "received request");
+                connections.request(id);
+
+                // Process request and queue the response
+                let in_flight = InFlight::start(&requests);
+                let (response, subscribe) = Self::process_request(request, &state, &notify_tx, id).await;
+                if out_tx.send(Outgoing::Response(response)).await.is_err() {
+                    return Ok(false);
+                }
+                drop(in_flight);
 
-            // Process request
-            let (response, subscribe) = Self::process_request(request, &state).await;
-            if subscribe {
-                is_subscribed = true;
-                debug!("client subscribed to notifications");
+                // Start pushing notifications after the Subscribed response
+                if subscribe && forwarder.is_none() {
+                    debug!("client subscribed to notifications");
+                    connections.subscribed(id);
+                    forwarder = Some(Self::spawn_forwarder(
+                        notify_tx.subscribe(),
+                        out_tx.clone(),
+                        closing.clone(),
+                        Arc::clone(&connections),
+                        id,
+                    ));
+                }
             }
+        };
+
+        let closed_by_daemon = tokio::select! {
+            result = read_loop => result,
+            result = &mut write_loop => result.map(|()| false),
+        };
 
-            // Send response
-            Self::send_message(&mut stream, &response).await?;
+        match closed_by_daemon {
+            Ok(true) => {
+                // The forwarder stops once it has passed on what was sent
+                // before shutdown; then the writer drains and ends
+                debug!("closing client connection for shutdown");
+                if let Some(forwarder) = forwarder {
+                    let _ = forwarder.await;
+                }
+                drop(out_tx);
+                write_loop.await
+            }
+            result => {
+                if let Some(forwarder) = forwarder {
+                    forwarder.abort();
+                }
+                result.map(|_| ())
+            }
         }
     }
 
-    /// Send a length-prefixed JSON message
-    async fn send_message<T: serde::Serialize>(stream: &mut UnixStream, msg: &T) -> Result<()> {
-        let msg_bytes = serde_json::to_vec(msg)?;
-        let msg_len = (msg_bytes.len() as u32).to_le_bytes();
-        
-        stream.write_all(&msg_len).await?;
-        stream.write_all(&msg_bytes).await?;
-        
-        Ok(())
+    /// Forward notifications to one subscribed client, until `closing` is
+    /// set and everything sent before it has been passed on
+    fn spawn_forwarder(
+        mut notify_rx: broadcast::Receiver<Notification>,
+        out_tx: mpsc::Sender<Outgoing>,
+        mut closing: watch::Receiver<bool>,
+        connections: Arc<Connections>,
+        id: u64,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let received = tokio::select! {
+                    biased;
+                    received = notify_rx.recv() => received,
+                    _ = closing.wait_for(|closing| *closing) => break,
+                };
+                match received {
+                    Ok(notification) => {
+                        if out_tx.send(Outgoing::Notification(notification)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!(client = id, skipped = n, "subscriber lagged, notifications dropped");
+                        connections.notifications_dropped(id, n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
     }
 
-    /// Process a request and return a response
+    /// Process a request from client `client` and return a response
     /// Returns (Response, should_subscribe)
-    async fn process_request(request: Request, state: &Arc<RwLock<ServerState>>) -> (Response, bool) {
+    async fn process_request(
+        request: Request,
+        state: &Arc<RwLock<ServerState>>,
+        notify_tx: &broadcast::Sender<Notification>,
+        client: u64,
+    ) -> (Response, bool) {
         match request {
             Request::Ping => (Response::Pong, false),
             
             Request::GetStatus => {
-                let mut state = state.write().await;
-                state.status.uptime_secs = state.start_time.elapsed().as_secs();
-                (Response::Status(state.status.clone()), false)
+                let state = state.read().await;
+                (Response::Status(Box::new(Self::status(&state))), false)
             }
             
             Request::SetMode { mode } => {
-                let mut state = state.write().await;
-                let old_mode = state.status.mode;
-                state.status.mode = mode;
-                info!(?old_mode, ?mode, "mode changed via IPC");
-                (Response::ModeChange { mode, active: mode != Mode::Idle }, false)
+                let state = state.read().await;
+                (Self::set_mode(&state, mode).await, false)
             }
             
             Request::Subscribe => {
                 (Response::Subscribed, true)
             }
+
+            Request::GetStateMachine => {
+                let state = state.read().await;
+                (Response::StateMachine(Self::describe(&state)), false)
+            }
+
+            Request::RenderStateMachine { format } => {
+                let state = state.read().await;
+                let description = Self::describe(&state);
+                let source = render_diagram(&description, format);
+                (Response::Diagram { format, source }, false)
+            }
+
+            Request::SetModeEnabled { mode, enabled } => {
+                let mut state = state.write().await;
+                (Self::set_mode_enabled(&mut state, mode, enabled).await, false)
+            }
+
+            Request::Pause { duration_secs } => {
+                let request = PauseRequest::Pause {
+                    duration: duration_secs.map(Duration::from_secs),
+                };
+                (Self::pause_request(state, request).await, false)
+            }
+
+            Request::Resume => (Self::pause_request(state, PauseRequest::Resume).await, false),
+
+            Request::SetQuietHours { schedules } => {
+                {
+                    let mut guard = state.write().await;
+                    let mut settings = guard.settings.clone();
+                    settings.quiet_hours = schedules.clone();
+                    if let Err(response) = Self::save_settings(&mut guard, settings) {
+                        return (response, false);
+                    }
+                }
+
+                info!(count = schedules.len(), "quiet hours changed via IPC");
+                let request = PauseRequest::SetQuietHours(schedules);
+                (Self::pause_request(state, request).await, false)
+            }
+
+            Request::InjectInput { modifiers } => (Self::inject_input(state, modifiers).await, false),
+
+            Request::InjectKeyPress => (Self::inject_key_press(state).await, false),
+
+            Request::GetBindings => {
+                let snapshot = state.read().await.store.snapshot();
+                let response = Response::Bindings {
+                    bindings: snapshot.bindings,
+                    warnings: Self::hotkey_warnings(&snapshot),
+                };
+                (response, false)
+            }
+
+            Request::SetBindings { bindings } => (Self::set_bindings(state, bindings).await, false),
+
+            Request::CaptureChord { timeout_secs } => {
+                let timeout = match timeout_secs {
+                    Some(secs) => Duration::from_secs(secs),
+                    None => state.read().await.config.timeouts.chord_capture(),
+                };
+                (Self::capture_chord(state, client, timeout).await, false)
+            }
+
+            Request::CommitCapture { mode } => (Self::commit_capture(state, client, mode).await, false),
+
+            Request::GetHotkeyWarnings => {
+                let snapshot = state.read().await.store.snapshot();
+                let warnings = Self::hotkey_warnings(&snapshot);
+                (Response::HotkeyWarnings { warnings }, false)
+            }
+
+            Request::GetConfigStatus => {
+                let state = state.read().await;
+                let response = Response::ConfigStatus {
+                    path: state.paths.as_ref().map(|paths| paths.config_file.clone()),
+                    applied: Self::config_errors(&state) == 0,
+                    diagnostics: state.config_diagnostics.clone(),
+                };
+                (response, false)
+            }
+
+            Request::GetPaths => {
+                let response = match &state.read().await.paths {
+                    Some(paths) => Response::Paths(paths.clone()),
+                    None => Response::Error {
+                        code: "paths_unknown".to_string(),
+                        message: "daemon has not resolved its paths yet".to_string(),
+                    },
+                };
+                (response, false)
+            }
+
+            Request::DumpDiagnostics => {
+                let response = match Self::write_diagnostics(&*state.read().await) {
+                    Ok(path) => Response::DiagnosticsWritten { path },
+                    Err(e) => Response::Error {
+                        code: "diagnostics_failed".to_string(),
+                        message: format!("{:#}", e),
+                    },
+                };
+                (response, false)
+            }
+
+            Request::GetHealth => {
+                let components = match &state.read().await.health {
+                    Some(health) => health.components(),
+                    None => Vec::new(),
+                };
+                let response = Response::Health {
+                    status: overall(&components),
+                    components,
+                };
+                (response, false)
+            }
+
+            Request::GetProfiles => {
+                let state = state.read().await;
+                (Self::profiles(&state), false)
+            }
+
+            Request::SetProfile { profile } => (Self::set_profile(state, profile, notify_tx).await, false),
+
+            Request::SetSecret { name, value } => {
+                let response = Self::change_secrets(state, move |store| {
+                    store.set(&name, &value).map_err(Self::secret_error)?;
+                    info!(name, backend = store.name(), "secret stored via IPC");
+                    Ok(())
+                })
+                .await;
+                (response, false)
+            }
+
+            Request::ListSecrets => (Self::change_secrets(state, |_| Ok(())).await, false),
+
+            Request::DeleteSecret { name } => {
+                let response = Self::change_secrets(state, move |store| {
+                    if !store.delete(&name).map_err(Self::secret_error)? {
+                        return Err(Response::Error {
+                            code: "unknown_secret".to_string(),
+                            message: format!("no secret named {:?}", name),
+                        });
+                    }
+                    info!(name, backend = store.name(), "secret deleted via IPC");
+                    Ok(())
+                })
+                .await;
+                (response, false)
+            }
+        }
+    }
+
+    /// Have the state machine report the next chord pressed to `client`
+    ///
+    /// The server lock is not held while waiting, so other clients (and
+    /// the UI's own second connection) keep working. Only one client can
+    /// capture at a time.
+    async fn capture_chord(state: &Arc<RwLock<ServerState>>, client: u64, timeout: Duration) -> Response {
+        let commands = {
+            let mut state = state.write().await;
+            let Some(commands) = state.commands.clone() else {
+                return Self::unavailable("hotkey capture is not available");
+            };
+            if state.capturing.is_some() {
+                return Response::Error {
+                    code: "capture_in_progress".to_string(),
+                    message: "another client is capturing a chord".to_string(),
+                };
+            }
+            state.capturing = Some(client);
+            commands
+        };
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if commands.send(Command::CaptureChord(reply_tx)).await.is_err() {
+            state.write().await.capturing = None;
+            return Self::unavailable("state machine is not running");
+        }
+
+        let reply = tokio::time::timeout(timeout, reply_rx).await;
+        let mut state = state.write().await;
+        state.capturing = None;
+        match reply {
+            Ok(Ok(chord)) => {
+                state.captured.insert(client, chord);
+                Response::ChordCaptured {
+                    label: chord.to_string(),
+                    chord,
+                }
+            }
+            Ok(Err(_)) => Response::Error {
+                code: "capture_cancelled".to_string(),
+                message: "the daemon is stopping".to_string(),
+            },
+            Err(_) => {
+                let _ = commands.send(Command::CancelCapture).await;
+                Response::Error {
+                    code: "capture_timeout".to_string(),
+                    message: format!("no chord pressed within {}s", timeout.as_secs()),
+                }
+            }
+        }
+    }
+
+    /// Drop what `client` captured, and its capture in progress, once it
+    /// disconnects
+    async fn forget_captures(state: &Arc<RwLock<ServerState>>, client: u64) {
+        let mut state = state.write().await;
+        state.captured.remove(&client);
+        if state.capturing == Some(client) {
+            state.capturing = None;
+            if let Some(commands) = &state.commands {
+                let _ = commands.send(Command::CancelCapture).await;
+            }
+        }
+    }
+
+    /// Bind `mode` to the chord `client` last captured
+    async fn commit_capture(state: &Arc<RwLock<ServerState>>, client: u64, mode: Mode) -> Response {
+        let (captured, mut bindings) = {
+            let state = state.read().await;
+            (state.captured.get(&client).copied(), state.store.snapshot().bindings)
+        };
+        let Some(chord) = captured else {
+            return Response::Error {
+                code: "nothing_captured".to_string(),
+                message: "capture a chord first".to_string(),
+            };
+        };
+
+        match mode {
+            Mode::Dictation => bindings.dictation = chord,
+            Mode::Intelligent => bindings.intelligent = chord,
+            Mode::Agent => bindings.agent = chord,
+            Mode::Idle => {
+                return Response::Error {
+                    code: "invalid_mode".to_string(),
+                    message: "idle cannot be bound to a chord".to_string(),
+                };
+            }
+        }
+
+        let response = Self::set_bindings(state, bindings).await;
+        if matches!(response, Response::Bindings { .. }) {
+            state.write().await.captured.remove(&client);
+        }
+        response
+    }
+
+    /// Describe the running state machine, marking its current state
+    fn describe(state: &ServerState) -> MachineDescription {
+        let snapshot = state.store.snapshot();
+        TransitionTable::new(&snapshot.bindings).describe(&snapshot.enabled_modes, Some(snapshot.state))
+    }
+
+    /// Persist new bindings, apply them and restart the listener if needed
+    async fn set_bindings(state: &Arc<RwLock<ServerState>>, bindings: Bindings) -> Response {
+        let rebind = match Self::commit_bindings(&mut *state.write().await, Some(bindings)).await {
+            Ok(rebind) => rebind,
+            Err(response) => return response,
+        };
+        // Without the state lock: requests and notifications go on while
+        // the listener restarts
+        if let Err(response) = Self::rebind(rebind).await {
+            return response;
+        }
+
+        info!(?bindings, "bindings changed via IPC");
+        let mut snapshot = state.read().await.store.snapshot();
+        snapshot.bindings = bindings;
+        Response::Bindings {
+            bindings,
+            warnings: Self::hotkey_warnings(&snapshot),
+        }
+    }
+
+    /// Persist new bindings and hand them to the state machine, returning
+    /// what the hotkey listener has to be rebound to
+    ///
+    /// `None` goes back to `[hotkeys]` of the active config.
+    async fn commit_bindings(state: &mut ServerState, changed: Option<Bindings>) -> Result<Option<Rebind>, Response> {
+        let Some(commands) = state.commands.clone() else {
+            return Err(Self::unavailable("bindings are not available"));
+        };
+
+        let bindings = changed.unwrap_or_else(|| Self::active_config(state).hotkeys);
+
+        if [bindings.dictation, bindings.intelligent, bindings.agent]
+            .iter()
+            .any(|chord| chord.is_empty())
+        {
+            return Err(Response::Error {
+                code: "invalid_binding".to_string(),
+                message: "every mode needs at least one key".to_string(),
+            });
+        }
+
+        let mut settings = state.settings.clone();
+        settings.bindings = changed;
+        Self::save_settings(state, settings)?;
+
+        if commands.send(Command::SetBindings(bindings)).await.is_err() {
+            warn!("state machine is not running, bindings only persisted");
+        }
+
+        state.bindings_version += 1;
+        Ok(state.listener.clone().map(|listener| Rebind {
+            listener,
+            bindings,
+            version: state.bindings_version,
+        }))
+    }
+
+    /// Restart the hotkey listener for new bindings, if it has to be
+    async fn rebind(rebind: Option<Rebind>) -> Result<(), Response> {
+        let Some(Rebind { listener, bindings, version }) = rebind else {
+            return Ok(());
+        };
+
+        // Restarting joins the listener thread, so keep it off the runtime
+        let result = tokio::task::spawn_blocking(move || listener.rebind(bindings, version)).await;
+        match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => {
+                error!(?e, "failed to restart hotkey listener");
+                Err(Response::Error {
+                    code: "hotkey_restart_failed".to_string(),
+                    message: e.to_string(),
+                })
+            }
+            Err(e) => {
+                error!(?e, "hotkey listener restart panicked");
+                Err(Self::unavailable("hotkey listener restart failed"))
+            }
+        }
+    }
+
+    /// Find chords that collide with known shortcuts, with each other, that
+    /// keep being held while typing, or that can't be held
+    fn hotkey_warnings(snapshot: &Snapshot) -> Vec<HotkeyWarning> {
+        let bound = [
+            (State::DictationActive, snapshot.bindings.dictation),
+            (State::IntelligentActive, snapshot.bindings.intelligent),
+            (State::AgentActive, snapshot.bindings.agent),
+        ];
+
+        let mut warnings = Vec::new();
+        for (state, chord) in bound {
+            let mode = Mode::from(state);
+            for shortcut in system_conflicts(chord) {
+                warnings.push(HotkeyWarning {
+                    mode,
+                    kind: HotkeyWarningKind::SystemShortcut,
+                    message: format!("{} conflicts with a system shortcut: {}", chord, shortcut.description),
+                });
+            }
+
+            for (other_state, other) in bound {
+                if other_state != state && overlaps(chord, other) {
+                    warnings.push(HotkeyWarning {
+                        mode,
+                        kind: HotkeyWarningKind::OtherMode,
+                        message: format!("{} is also held for {:?} mode", chord, Mode::from(other_state)),
+                    });
+                }
+            }
+
+            // Agent mode is a toggle anyway
+            let latching = chord.keys().keys().into_iter().find(|key| key.latches());
+            if let Some(key) = latching.filter(|_| state != State::AgentActive) {
+                warnings.push(HotkeyWarning {
+                    mode,
+                    kind: HotkeyWarningKind::Latches,
+                    message: format!(
+                        "{} reports its lock state, so {} toggles {:?} mode instead of being held; remap {} to F18 and bind F18",
+                        key.label(), chord, mode, key.label()
+                    ),
+                });
+            }
+
+            let uses = snapshot.shortcut_use.count(state);
+            if uses >= SHORTCUT_USE_WARNING {
+                warnings.push(HotkeyWarning {
+                    mode,
+                    kind: HotkeyWarningKind::UsedAsShortcut,
+                    message: format!("{} was held while typing {} times; it may be an app shortcut", chord, uses),
+                });
+            }
+        }
+        warnings
+    }
+
+    /// Feed a modifier change to the synthetic hotkey source
+    async fn inject_input(state: &Arc<RwLock<ServerState>>, modifiers: ModifierState) -> Response {
+        let Some(input) = state.read().await.input.clone() else {
+            return Response::Error {
+                code: "injection_disabled".to_string(),
+                message: "input injection is not enabled".to_string(),
+            };
+        };
+
+        debug!(?modifiers, "injecting input");
+        if input.inject(modifiers).await {
+            Response::InputInjected
+        } else {
+            Self::unavailable("synthetic hotkey source is not running")
+        }
+    }
+
+    /// Feed a regular key press to the synthetic hotkey source
+    async fn inject_key_press(state: &Arc<RwLock<ServerState>>) -> Response {
+        let Some(input) = state.read().await.input.clone() else {
+            return Response::Error {
+                code: "injection_disabled".to_string(),
+                message: "input injection is not enabled".to_string(),
+            };
+        };
+
+        debug!("injecting key press");
+        if input.press_key().await {
+            Response::InputInjected
+        } else {
+            Self::unavailable("synthetic hotkey source is not running")
+        }
+    }
+
+    /// Build a status report from the latest state snapshot
+    fn status(state: &ServerState) -> DaemonStatus {
+        let Snapshot {
+            version,
+            state: current,
+            enabled_modes,
+            hotkey_registered,
+            capturing,
+            tap_disabled_count,
+            tap_recovered_count,
+            ..
+        } = state.store.snapshot();
+
+        DaemonStatus {
+            mode: current.into(),
+            hotkey_registered,
+            enabled_modes: Mode::enabled(&enabled_modes),
+            pause: state.pause.as_ref().map(PauseHandle::status).unwrap_or_default(),
+            state_version: version,
+            capturing,
+            tap_disabled_count,
+            tap_recovered_count,
+            config_errors: Self::config_errors(state),
+            profile: state.settings.profile.clone(),
+            components: state.supervisor.as_ref().map(Supervisor::components).unwrap_or_default(),
+            uptime_secs: state.start_time.elapsed().as_secs(),
+            ..DaemonStatus::default()
+        }
+    }
+
+    /// Write a diagnostic snapshot, returning where it went
+    pub async fn dump_diagnostics(&self) -> Result<PathBuf> {
+        Self::write_diagnostics(&*self.state.read().await)
+    }
+
+    fn write_diagnostics(state: &ServerState) -> Result<PathBuf> {
+        let paths = state
+            .paths
+            .as_ref()
+            .context("daemon has not resolved its paths yet")?;
+        let snapshot = state.store.snapshot();
+        let secrets = match &state.secrets {
+            Some(store) => store.list().unwrap_or_else(|e| {
+                warn!(?e, "failed to list secrets for diagnostics");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+
+        let diagnostics = Diagnostics {
+            version: env!("CARGO_PKG_VERSION"),
+            pid: std::process::id(),
+            written_unix_ms: unix_millis(),
+            uptime_secs: state.start_time.elapsed().as_secs(),
+            state: snapshot.state,
+            paused: snapshot.paused,
+            capturing: snapshot.capturing,
+            hotkey_registered: snapshot.hotkey_registered,
+            tap_disabled_count: snapshot.tap_disabled_count,
+            tap_recovered_count: snapshot.tap_recovered_count,
+            recent_transitions: snapshot.recent_transitions.into(),
+            clients: state.connections.clients(),
+            channel_lag: state.connections.lag(),
+            health: state.health.as_ref().map(HealthRegistry::components).unwrap_or_default(),
+            components: state.supervisor.as_ref().map(Supervisor::components).unwrap_or_default(),
+            paths: paths.clone(),
+            profile: state.settings.profile.clone(),
+            config: redacted_config(&Self::active_config(state)),
+            config_diagnostics: state.config_diagnostics.clone(),
+            secrets,
+        };
+        let path = diagnostics
+            .write(&paths.data_dir.join("diagnostics"))
+            .context("failed to write diagnostics")?;
+        info!(?path, "diagnostics written");
+        Ok(path)
+    }
+
+    /// Report `config.toml` degraded while it has errors
+    fn report_config_health(state: &ServerState) {
+        let Some(health) = &state.health else {
+            return;
+        };
+        match Self::config_errors(state) {
+            0 => health.report("config", HealthState::Ok, "loaded"),
+            1 => health.report("config", HealthState::Degraded, "config.toml has an error and was not applied"),
+            n => health.report(
+                "config",
+                HealthState::Degraded,
+                format!("config.toml has {} errors and was not applied", n),
+            ),
+        }
+    }
+
+    fn config_errors(state: &ServerState) -> usize {
+        state
+            .config_diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+
+    fn profiles(state: &ServerState) -> Response {
+        Response::Profiles {
+            active: state.settings.profile.clone(),
+            available: state.config.profiles.keys().cloned().collect(),
+        }
+    }
+
+    /// Make `profile` active, persist it and apply what it changes
+    async fn set_profile(
+        state: &Arc<RwLock<ServerState>>,
+        profile: Option<String>,
+        notify_tx: &broadcast::Sender<Notification>,
+    ) -> Response {
+        let mut guard = state.write().await;
+        if let Some(name) = &profile {
+            if !guard.config.profiles.contains_key(name) {
+                return Response::Error {
+                    code: "unknown_profile".to_string(),
+                    message: format!("no profile named {:?} in config.toml", name),
+                };
+            }
+        }
+        if profile == guard.settings.profile {
+            return Self::profiles(&guard);
+        }
+
+        let old = Self::active_config(&guard);
+        let mut settings = guard.settings.clone();
+        settings.profile = profile.clone();
+        if let Err(response) = Self::save_settings(&mut guard, settings) {
+            return response;
+        }
+
+        let active = Self::active_config(&guard);
+        let diff = ConfigDiff::between(&old, &active);
+        let rebind = Self::apply_config(&mut guard, &diff).await;
+        let response = Self::profiles(&guard);
+        drop(guard);
+        if let Err(Response::Error { message, .. }) = Self::rebind(rebind).await {
+            warn!(message, "failed to apply hotkeys from profile");
+        }
+
+        info!(?profile, changed = ?diff.changed, "profile changed via IPC");
+        let _ = notify_tx.send(Notification::ProfileChanged {
+            profile,
+            changed: diff.changed,
+        });
+        response
+    }
+
+    /// Apply `change` to the secret store, then list the stored names
+    ///
+    /// Runs off the async runtime: the keychain may wait for the user to
+    /// allow access.
+    async fn change_secrets(
+        state: &Arc<RwLock<ServerState>>,
+        change: impl FnOnce(&dyn SecretStore) -> Result<(), Response> + Send + 'static,
+    ) -> Response {
+        let Some(store) = state.read().await.secrets.clone() else {
+            return Self::unavailable("secrets are not available");
+        };
+
+        let result = tokio::task::spawn_blocking(move || {
+            change(store.as_ref())?;
+            store.list().map_err(Self::secret_error)
+        })
+        .await;
+        match result {
+            Ok(Ok(names)) => Response::Secrets { names },
+            Ok(Err(response)) => response,
+            Err(e) => {
+                error!(?e, "secret store access panicked");
+                Self::unavailable("secret store failed")
+            }
+        }
+    }
+
+    fn secret_error(e: SecretError) -> Response {
+        let code = match e {
+            SecretError::InvalidName(_) => "invalid_secret_name",
+            _ => {
+                warn!(?e, "secret store failed");
+                "secret_store_failed"
+            }
+        };
+        Response::Error {
+            code: code.to_string(),
+            message: e.to_string(),
+        }
+    }
+
+    /// Ask the state machine to enter a mode
+    async fn set_mode(state: &ServerState, mode: Mode) -> Response {
+        let Some(commands) = &state.commands else {
+            return Self::unavailable("mode control is not available");
+        };
+
+        let snapshot = state.store.snapshot();
+        if mode != Mode::Idle && snapshot.paused {
+            return Response::Error {
+                code: "paused".to_string(),
+                message: "hotkeys are paused".to_string(),
+            };
+        }
+        if !snapshot.enabled_modes.allows(mode.state()) {
+            return Response::Error {
+                code: "mode_disabled".to_string(),
+                message: format!("mode {:?} is disabled", mode),
+            };
+        }
+
+        if commands.send(Command::EnterState(mode.state())).await.is_err() {
+            return Self::unavailable("state machine is not running");
+        }
+
+        info!(?mode, "mode requested via IPC");
+        Response::ModeChange { mode, active: mode != Mode::Idle }
+    }
+
+    /// Forward a request to the pause controller
+    async fn pause_request(state: &Arc<RwLock<ServerState>>, request: PauseRequest) -> Response {
+        let Some(pause) = state.read().await.pause.clone() else {
+            return Self::unavailable("pause control is not available");
+        };
+
+        match pause.request(request).await {
+            Some(status) => Response::PauseStatus(status),
+            None => Self::unavailable("pause controller is not running"),
+        }
+    }
+
+    /// Persist new settings, keeping the old ones if writing fails
+    fn save_settings(state: &mut ServerState, settings: Settings) -> Result<(), Response> {
+        let Some(settings_path) = &state.settings_path else {
+            return Err(Self::unavailable("settings are not available"));
+        };
+
+        if let Err(e) = settings.save(settings_path) {
+            warn!(?e, "failed to persist settings");
+            return Err(Response::Error {
+                code: "settings_write_failed".to_string(),
+                message: format!("{:#}", e),
+            });
+        }
+
+        state.settings = settings;
+        Ok(())
+    }
+
+    fn unavailable(message: &str) -> Response {
+        Response::Error {
+            code: "unavailable".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Enable or disable a mode, persist it and tell the state machine
+    async fn set_mode_enabled(state: &mut ServerState, mode: Mode, enabled: bool) -> Response {
+        let mut enabled_modes = state.settings.enabled_modes(&Self::active_config(state));
+        if !enabled_modes.set(mode.state(), enabled) {
+            return Response::Error {
+                code: "invalid_mode".to_string(),
+                message: format!("mode {:?} cannot be enabled or disabled", mode),
+            };
+        }
+
+        let response = Self::set_enabled_modes(state, Some(enabled_modes)).await;
+        if matches!(response, Response::EnabledModes { .. }) {
+            info!(?mode, enabled, "mode availability changed via IPC");
+        }
+        response
+    }
+
+    /// Persist the set of enabled modes and apply it
+    ///
+    /// `None` goes back to `[modes]` of the active config.
+    async fn set_enabled_modes(state: &mut ServerState, changed: Option<EnabledModes>) -> Response {
+        let Some(commands) = state.commands.clone() else {
+            return Self::unavailable("mode settings are not available");
+        };
+
+        let mut settings = state.settings.clone();
+        settings.enabled_modes = changed;
+        if let Err(response) = Self::save_settings(state, settings) {
+            return response;
+        }
+        let enabled_modes = state.settings.enabled_modes(&Self::active_config(state));
+
+        if commands.send(Command::SetEnabledModes(enabled_modes)).await.is_err() {
+            warn!("state machine is not running, mode change only persisted");
+        }
+
+        Response::EnabledModes {
+            modes: Mode::enabled(&enabled_modes),
         }
     }
 
+    /// Stop accepting connections; those already open are served until
+    /// `close`
+    fn stop_accepting(&self) {
+        drop(self.listener.lock().unwrap_or_else(|e| e.into_inner()).take());
+        self.accepting.send_replace(false);
+    }
+
+    /// Tell clients the daemon is shutting down and let them finish
+    ///
+    /// Stops accepting connections and sends `Notification::ShuttingDown`.
+    /// Clients have until `timeout` to get the answers to requests in
+    /// flight, so a chord capture can still be completed; one still
+    /// waiting then is cancelled. Connections stay open for `close`, so
+    /// subscribers also get the exit events of the state machine stopping.
+    pub async fn drain(&self, reason: String, timeout: Duration) {
+        self.stop_accepting();
+        info!(clients = *self.clients.borrow(), reason, "shutting down, finishing requests");
+        let _ = self.notify_tx.send(Notification::ShuttingDown { reason });
+        if self.wait_for_requests(timeout).await {
+            return;
+        }
+
+        if let Some(commands) = self.state.read().await.commands.clone() {
+            let _ = commands.send(Command::CancelCapture).await;
+        }
+        if !self.wait_for_requests(CANCEL_GRACE).await {
+            warn!(requests = *self.requests.borrow(), "requests still unanswered at shutdown");
+        }
+    }
+
+    /// Close client connections once the state machine has stopped
+    ///
+    /// Subscribers first get the events it sent on the way out; connections
+    /// that then don't end in time are dropped.
+    pub async fn close(&self) {
+        let mut forwarded = self.events_forwarded.subscribe();
+        if tokio::time::timeout(CANCEL_GRACE, forwarded.wait_for(|forwarded| *forwarded))
+            .await
+            .is_err()
+        {
+            warn!("state events still being forwarded, subscribers may miss the last ones");
+        }
+
+        info!(clients = *self.clients.borrow(), "closing client connections");
+        self.closing.send_replace(true);
+        if !self.wait_for_clients(CANCEL_GRACE).await {
+            warn!(clients = *self.clients.borrow(), "clients still busy, dropping their connections");
+            let _ = self.shutdown_tx.send(());
+        }
+    }
+
+    /// Wait up to `timeout` for every request in flight to be answered
+    async fn wait_for_requests(&self, timeout: Duration) -> bool {
+        let mut requests = self.requests.subscribe();
+        let answered = tokio::time::timeout(timeout, requests.wait_for(|requests| *requests == 0)).await;
+        answered.is_ok()
+    }
+
+    /// Wait up to `timeout` for every connection to end
+    async fn wait_for_clients(&self, timeout: Duration) -> bool {
+        let mut clients = self.clients.subscribe();
+        let drained = tokio::time::timeout(timeout, clients.wait_for(|clients| *clients == 0)).await;
+        drained.is_ok()
+    }
+
     /// Gracefully shutdown the server
+    ///
+    /// Drops any connection still open and removes the socket; `drain`
+    /// and `close` first to let clients finish.
     pub async fn shutdown(&self) {
         let _ = self.shutdown_tx.send(());
-        
+
         // Remove socket file
         if self.socket_path.exists() {
             if let Err(e) = std::fs::remove_file(&self.socket_path) {
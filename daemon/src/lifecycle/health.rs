@@ -0,0 +1,161 @@
+//! Per-component health, as shown to the user
+//!
+//! Components report whether they work (`Ok`), work with limitations
+//! (`Degraded`) or don't work (`Failed`), with a message saying why.
+//! `since` only moves when the state changes, so repeated reports of the
+//! same failure keep the time it started.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+/// How well a component works
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Ok,
+    Degraded,
+    Failed,
+}
+
+/// The last health report of one component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub state: HealthState,
+    /// What works or what is wrong, for showing to the user
+    pub message: String,
+    /// When the component entered `state`
+    pub since_unix_secs: u64,
+}
+
+/// Where components report their health
+#[derive(Debug, Clone, Default)]
+pub struct HealthRegistry {
+    components: Arc<Mutex<BTreeMap<String, ComponentHealth>>>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the health of component `name`
+    pub fn report(&self, name: &str, state: HealthState, message: impl Into<String>) {
+        let message = message.into();
+        let mut components = self.components.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = components.get(name).map(|health| (health.state, health.since_unix_secs));
+        let since_unix_secs = match previous {
+            Some((previous, since)) if previous == state => since,
+            _ => {
+                match state {
+                    HealthState::Ok => info!(component = name, message, "component healthy"),
+                    HealthState::Degraded => warn!(component = name, message, "component degraded"),
+                    HealthState::Failed => error!(component = name, message, "component failed"),
+                }
+                unix_now()
+            }
+        };
+        components.insert(
+            name.to_string(),
+            ComponentHealth {
+                name: name.to_string(),
+                state,
+                message,
+                since_unix_secs,
+            },
+        );
+    }
+
+    /// Every component's last report, by name
+    pub fn components(&self) -> Vec<ComponentHealth> {
+        let components = self.components.lock().unwrap_or_else(|e| e.into_inner());
+        components.values().cloned().collect()
+    }
+}
+
+/// The worst state among `components`; `Ok` when there are none
+pub fn overall(components: &[ComponentHealth]) -> HealthState {
+    components
+        .iter()
+        .map(|health| health.state)
+        .max()
+        .unwrap_or(HealthState::Ok)
+}
+
+/// One line naming the components that are not `Ok`, for status displays
+pub fn summary(components: &[ComponentHealth]) -> String {
+    let problems: Vec<_> = components
+        .iter()
+        .filter(|health| health.state != HealthState::Ok)
+        .map(|health| {
+            let state = match health.state {
+                HealthState::Ok => "ok",
+                HealthState::Degraded => "degraded",
+                HealthState::Failed => "failed",
+            };
+            format!("{} {}: {}", health.name, state, health.message)
+        })
+        .collect();
+    if problems.is_empty() {
+        "running".to_string()
+    } else {
+        problems.join("; ")
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_since_moves_only_on_state_changes() {
+        let health = HealthRegistry::new();
+        health.report("hotkeys", HealthState::Failed, "no permission");
+        let since = health.components()[0].since_unix_secs;
+        assert!(since > 0);
+
+        // Backdate the report to tell a reset from a kept timestamp
+        health.components.lock().unwrap().get_mut("hotkeys").unwrap().since_unix_secs = 1;
+        health.report("hotkeys", HealthState::Failed, "still no permission");
+        assert_eq!(health.components()[0].since_unix_secs, 1);
+        assert_eq!(health.components()[0].message, "still no permission");
+
+        health.report("hotkeys", HealthState::Ok, "listening");
+        assert!(health.components()[0].since_unix_secs > 1);
+    }
+
+    #[test]
+    fn test_overall_is_the_worst_state() {
+        let health = HealthRegistry::new();
+        assert_eq!(overall(&health.components()), HealthState::Ok);
+        health.report("a", HealthState::Ok, "");
+        health.report("b", HealthState::Degraded, "");
+        assert_eq!(overall(&health.components()), HealthState::Degraded);
+        health.report("c", HealthState::Failed, "");
+        assert_eq!(overall(&health.components()), HealthState::Failed);
+    }
+
+    #[test]
+    fn test_summary_names_unhealthy_components() {
+        let health = HealthRegistry::new();
+        health.report("ipc server", HealthState::Ok, "running");
+        assert_eq!(summary(&health.components()), "running");
+        health.report("config", HealthState::Degraded, "config.toml has an error and was not applied");
+        health.report("hotkey listener", HealthState::Failed, "no permission");
+        assert_eq!(
+            summary(&health.components()),
+            "config degraded: config.toml has an error and was not applied; hotkey listener failed: no permission"
+        );
+    }
+}
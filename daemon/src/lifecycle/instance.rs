@@ -0,0 +1,93 @@
+//! Single-instance enforcement
+//!
+//! The daemon holds an exclusive lock on `daemon.lock` in its data directory
+//! for as long as it runs, with its pid written inside. The lock goes away
+//! with the process, however it exits, so a leftover file never blocks the
+//! next start.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Errors taking the instance lock
+#[derive(Debug, thiserror::Error)]
+pub enum InstanceError {
+    #[error("another instance{} holds {}", pid_suffix(*.pid), .path.display())]
+    AlreadyRunning { path: PathBuf, pid: Option<u32> },
+
+    #[error("failed to lock {}: {source}", .path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+}
+
+fn pid_suffix(pid: Option<u32>) -> String {
+    pid.map(|pid| format!(" (pid {})", pid)).unwrap_or_default()
+}
+
+/// Held for the daemon's lifetime; dropping it releases the lock
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+impl InstanceLock {
+    /// Lock `daemon.lock` in `data_dir`, failing if another instance has it
+    pub fn acquire(data_dir: &Path) -> Result<Self, InstanceError> {
+        let path = data_dir.join("daemon.lock");
+        let io_error = |source| InstanceError::Io {
+            path: path.clone(),
+            source,
+        };
+
+        // Not truncated on open: the pid in it belongs to the holder, if any
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(io_error)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut contents = String::new();
+                let pid = file
+                    .read_to_string(&mut contents)
+                    .ok()
+                    .and_then(|_| contents.trim().parse().ok());
+                return Err(InstanceError::AlreadyRunning { path, pid });
+            }
+            Err(TryLockError::Error(e)) => return Err(io_error(e)),
+        }
+
+        file.set_len(0).map_err(io_error)?;
+        file.rewind().map_err(io_error)?;
+        writeln!(file, "{}", std::process::id()).map_err(io_error)?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let dir = std::env::temp_dir().join(format!("second-brain-instance-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let lock = InstanceLock::acquire(&dir).unwrap();
+        let second = InstanceLock::acquire(&dir);
+        drop(lock);
+        let third = InstanceLock::acquire(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match second {
+            Err(InstanceError::AlreadyRunning { pid, .. }) => assert_eq!(pid, Some(std::process::id())),
+            other => panic!("expected AlreadyRunning, got {:?}", other),
+        }
+        assert!(third.is_ok());
+    }
+}
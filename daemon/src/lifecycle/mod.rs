@@ -1,5 +1,19 @@
 //! Lifecycle management for the daemon
 
-mod shutdown;
+mod backoff;
+mod health;
+mod instance;
+mod pause;
+mod quiet_hours;
+mod service;
+mod signals;
+mod supervisor;
 
-pub use shutdown::ShutdownSignal;
+pub use backoff::Backoff;
+pub use health::{overall, summary, ComponentHealth, HealthRegistry, HealthState};
+pub use instance::{InstanceError, InstanceLock};
+pub use pause::{PauseController, PauseHandle, PauseReason, PauseRequest, PauseStatus};
+pub use quiet_hours::QuietHours;
+pub use service::ServiceNotifier;
+pub use signals::{DiagnosticsSignal, ReloadSignal, ShutdownSignal};
+pub use supervisor::{ComponentStatus, RestartPolicy, Supervisor};
@@ -0,0 +1,329 @@
+//! Daemon-wide pause: manual pauses and quiet hours
+//!
+//! While paused the state machine ignores hotkeys, so no mode can be
+//! entered and the mic never opens. A `PauseController` task combines
+//! manual pause requests with the quiet-hour schedules and publishes the
+//! result on a watch channel.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot, watch};
+use tracing::{info, warn};
+
+use crate::state::Command;
+
+use super::quiet_hours::{self, QuietHours};
+
+/// How often quiet-hour schedules are re-evaluated
+const EVALUATE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Why the daemon is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    /// Paused on request (e.g. from the menu bar)
+    Manual,
+    /// Inside a configured quiet-hour window
+    QuietHours,
+}
+
+/// Current pause state, as reported to clients
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PauseStatus {
+    /// Whether hotkeys are currently disarmed
+    pub paused: bool,
+    /// Why the daemon is paused
+    pub reason: Option<PauseReason>,
+    /// When the pause ends (Unix seconds), if it has an end
+    pub until_unix_secs: Option<u64>,
+}
+
+/// Requests handled by the pause controller
+#[derive(Debug, Clone)]
+pub enum PauseRequest {
+    /// Pause for the given duration, or until resumed
+    Pause { duration: Option<Duration> },
+    /// End a manual pause, or skip the current quiet-hour window
+    Resume,
+    /// Replace the quiet-hour schedules
+    SetQuietHours(Vec<QuietHours>),
+}
+
+type Envelope = (PauseRequest, oneshot::Sender<PauseStatus>);
+
+/// Handle for talking to a running `PauseController`
+#[derive(Clone)]
+pub struct PauseHandle {
+    requests: mpsc::Sender<Envelope>,
+    status: watch::Receiver<PauseStatus>,
+}
+
+impl PauseHandle {
+    /// Send a request and wait for the resulting status
+    ///
+    /// Returns `None` if the controller is no longer running.
+    pub async fn request(&self, request: PauseRequest) -> Option<PauseStatus> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.requests.send((request, reply_tx)).await.ok()?;
+        reply_rx.await.ok()
+    }
+
+    /// The most recently published status
+    pub fn status(&self) -> PauseStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Watch for status changes
+    pub fn subscribe(&self) -> watch::Receiver<PauseStatus> {
+        self.status.clone()
+    }
+}
+
+/// A manual pause, optionally with an end time
+#[derive(Debug, Clone, Copy)]
+struct ManualPause {
+    until: Option<SystemTime>,
+}
+
+/// Decides whether the daemon is paused and tells the state machine
+pub struct PauseController {
+    manual: Option<ManualPause>,
+    quiet_hours: Vec<QuietHours>,
+    /// A resume during quiet hours skips the window until this local time
+    quiet_override_until: Option<NaiveDateTime>,
+    requests: mpsc::Receiver<Envelope>,
+    status_tx: watch::Sender<PauseStatus>,
+    commands: mpsc::Sender<Command>,
+}
+
+impl PauseController {
+    /// Create a controller and the handle used to talk to it
+    pub fn new(quiet_hours: Vec<QuietHours>, commands: mpsc::Sender<Command>) -> (Self, PauseHandle) {
+        let (requests_tx, requests) = mpsc::channel(8);
+        let (status_tx, status) = watch::channel(PauseStatus::default());
+
+        let controller = Self {
+            manual: None,
+            quiet_hours,
+            quiet_override_until: None,
+            requests,
+            status_tx,
+            commands,
+        };
+        let handle = PauseHandle {
+            requests: requests_tx,
+            status,
+        };
+
+        (controller, handle)
+    }
+
+    /// Run until every handle has been dropped
+    pub async fn run(mut self) {
+        self.refresh().await;
+
+        loop {
+            let wake_in = self
+                .manual
+                .and_then(|m| m.until)
+                .and_then(|until| until.duration_since(SystemTime::now()).ok())
+                .map_or(EVALUATE_INTERVAL, |left| left.min(EVALUATE_INTERVAL));
+
+            tokio::select! {
+                request = self.requests.recv() => {
+                    let Some((request, reply)) = request else { break };
+                    self.apply(request, SystemTime::now(), quiet_hours::local_now());
+                    let status = self.refresh().await;
+                    let _ = reply.send(status);
+                }
+                _ = tokio::time::sleep(wake_in) => {
+                    self.refresh().await;
+                }
+            }
+        }
+    }
+
+    /// Apply a request to the controller's state
+    fn apply(&mut self, request: PauseRequest, now: SystemTime, local: NaiveDateTime) {
+        match request {
+            PauseRequest::Pause { duration } => {
+                info!(?duration, "pausing");
+                // A pause too long to represent never ends on its own
+                let until = duration.and_then(|d| now.checked_add(d));
+                if duration.is_some() && until.is_none() {
+                    warn!(?duration, "pause duration out of range, pausing until resumed");
+                }
+                self.manual = Some(ManualPause { until });
+                self.quiet_override_until = None;
+            }
+            PauseRequest::Resume => {
+                info!("resuming");
+                self.manual = None;
+                self.quiet_override_until = quiet_hours::active_until(&self.quiet_hours, local);
+            }
+            PauseRequest::SetQuietHours(schedules) => {
+                info!(count = schedules.len(), "quiet hours updated");
+                self.quiet_hours = schedules;
+                self.quiet_override_until = None;
+            }
+        }
+    }
+
+    /// Compute the pause status at the given time
+    fn evaluate(&mut self, now: SystemTime, local: NaiveDateTime) -> PauseStatus {
+        if self.manual.and_then(|m| m.until).is_some_and(|until| until <= now) {
+            self.manual = None;
+        }
+        if self.quiet_override_until.is_some_and(|until| until <= local) {
+            self.quiet_override_until = None;
+        }
+
+        if let Some(manual) = self.manual {
+            return PauseStatus {
+                paused: true,
+                reason: Some(PauseReason::Manual),
+                until_unix_secs: manual.until.map(unix_secs),
+            };
+        }
+
+        match quiet_hours::active_until(&self.quiet_hours, local) {
+            Some(end) if self.quiet_override_until.is_none() => PauseStatus {
+                paused: true,
+                reason: Some(PauseReason::QuietHours),
+                until_unix_secs: chrono::Local
+                    .from_local_datetime(&end)
+                    .earliest()
+                    .map(|t| t.timestamp().max(0) as u64),
+            },
+            _ => PauseStatus::default(),
+        }
+    }
+
+    /// Re-evaluate, publish changes and update the state machine
+    async fn refresh(&mut self) -> PauseStatus {
+        let status = self.evaluate(SystemTime::now(), quiet_hours::local_now());
+        let previous = self.status_tx.borrow().clone();
+
+        if status.paused != previous.paused {
+            info!(?status, "pause state changed");
+            if self.commands.send(Command::SetPaused(status.paused)).await.is_err() {
+                warn!("state machine is not running, pause not applied");
+            }
+        }
+        if status != previous {
+            self.status_tx.send_replace(status.clone());
+        }
+
+        status
+    }
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn controller(quiet_hours: Vec<QuietHours>) -> PauseController {
+        let (commands, _) = mpsc::channel(1);
+        PauseController::new(quiet_hours, commands).0
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn night() -> QuietHours {
+        QuietHours {
+            start: "22:00".parse().unwrap(),
+            end: "07:00".parse().unwrap(),
+            days: vec![],
+        }
+    }
+
+    #[test]
+    fn test_timed_pause_expires() {
+        let mut c = controller(vec![]);
+        let now = SystemTime::now();
+        c.apply(
+            PauseRequest::Pause {
+                duration: Some(Duration::from_secs(60)),
+            },
+            now,
+            at(1, 12, 0),
+        );
+
+        let status = c.evaluate(now, at(1, 12, 0));
+        assert!(status.paused);
+        assert_eq!(status.reason, Some(PauseReason::Manual));
+        assert_eq!(status.until_unix_secs, Some(unix_secs(now) + 60));
+
+        assert!(!c.evaluate(now + Duration::from_secs(61), at(1, 12, 1)).paused);
+    }
+
+    #[test]
+    fn test_overlong_pause_is_indefinite() {
+        let mut c = controller(vec![]);
+        let now = SystemTime::now();
+        c.apply(
+            PauseRequest::Pause {
+                duration: Some(Duration::from_secs(u64::MAX)),
+            },
+            now,
+            at(1, 12, 0),
+        );
+
+        let status = c.evaluate(now + Duration::from_secs(86_400), at(1, 12, 0));
+        assert!(status.paused);
+        assert_eq!(status.until_unix_secs, None);
+    }
+
+    #[test]
+    fn test_quiet_hours_pause() {
+        let mut c = controller(vec![night()]);
+        let now = SystemTime::now();
+
+        let status = c.evaluate(now, at(1, 23, 0));
+        assert!(status.paused);
+        assert_eq!(status.reason, Some(PauseReason::QuietHours));
+        assert!(!c.evaluate(now, at(1, 12, 0)).paused);
+    }
+
+    #[test]
+    fn test_resume_skips_current_quiet_window() {
+        let mut c = controller(vec![night()]);
+        let now = SystemTime::now();
+
+        c.apply(PauseRequest::Resume, now, at(1, 23, 0));
+        assert!(!c.evaluate(now, at(1, 23, 30)).paused);
+        assert!(!c.evaluate(now, at(2, 6, 59)).paused);
+
+        // The override ends with the window it skipped
+        assert!(!c.evaluate(now, at(2, 8, 0)).paused);
+        assert!(c.quiet_override_until.is_none());
+        assert!(c.evaluate(now, at(2, 22, 0)).paused);
+    }
+
+    #[test]
+    fn test_indefinite_pause_until_resumed() {
+        let mut c = controller(vec![]);
+        let now = SystemTime::now();
+
+        c.apply(PauseRequest::Pause { duration: None }, now, at(1, 12, 0));
+        let status = c.evaluate(now + Duration::from_secs(86_400), at(1, 12, 0));
+        assert!(status.paused);
+        assert_eq!(status.until_unix_secs, None);
+
+        c.apply(PauseRequest::Resume, now, at(1, 12, 0));
+        assert!(!c.evaluate(now, at(1, 12, 0)).paused);
+    }
+}
@@ -0,0 +1,204 @@
+//! Quiet-hour schedules during which the daemon is paused
+//!
+//! A schedule is a daily local-time window, optionally limited to certain
+//! weekdays. Windows where `end` is before `start` run overnight and
+//! belong to the day they start on.
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+/// A local time of day with minute resolution, written as "HH:MM"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    minutes: u16,
+}
+
+impl TimeOfDay {
+    /// Create a time of day, returning `None` if out of range
+    pub fn new(hour: u16, minute: u16) -> Option<Self> {
+        (hour < 24 && minute < 60).then_some(Self {
+            minutes: hour * 60 + minute,
+        })
+    }
+
+    fn to_naive(self) -> NaiveTime {
+        NaiveTime::from_hms_opt(u32::from(self.minutes / 60), u32::from(self.minutes % 60), 0)
+            .expect("TimeOfDay is always in range")
+    }
+}
+
+impl std::str::FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid time '{}' (expected HH:MM)", s);
+        let (hour, minute) = s.split_once(':').ok_or_else(invalid)?;
+        let hour = hour.parse().map_err(|_| invalid())?;
+        let minute = minute.parse().map_err(|_| invalid())?;
+        Self::new(hour, minute).ok_or_else(invalid)
+    }
+}
+
+impl std::fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.minutes / 60, self.minutes % 60)
+    }
+}
+
+impl Serialize for TimeOfDay {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeOfDay {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Day of the week a schedule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(day: chrono::Weekday) -> Self {
+        match day {
+            chrono::Weekday::Mon => Weekday::Mon,
+            chrono::Weekday::Tue => Weekday::Tue,
+            chrono::Weekday::Wed => Weekday::Wed,
+            chrono::Weekday::Thu => Weekday::Thu,
+            chrono::Weekday::Fri => Weekday::Fri,
+            chrono::Weekday::Sat => Weekday::Sat,
+            chrono::Weekday::Sun => Weekday::Sun,
+        }
+    }
+}
+
+/// A recurring window of local time during which hotkeys are disarmed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Start of the window (inclusive)
+    pub start: TimeOfDay,
+    /// End of the window (exclusive); before `start` for overnight windows
+    pub end: TimeOfDay,
+    /// Days the window starts on; empty means every day
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+}
+
+impl QuietHours {
+    /// If `now` falls inside this window, return when the window ends
+    pub fn active_until(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let today = now.date();
+        let time = now.time();
+        let start = self.start.to_naive();
+        let end = self.end.to_naive();
+
+        if self.start == self.end {
+            // A window with no length covers the whole day
+            return self
+                .applies_to(today.weekday())
+                .then(|| (today + Duration::days(1)).and_time(end));
+        }
+
+        if self.start < self.end {
+            return (self.applies_to(today.weekday()) && start <= time && time < end)
+                .then(|| today.and_time(end));
+        }
+
+        // Overnight: either it started today, or it started yesterday
+        if self.applies_to(today.weekday()) && time >= start {
+            Some((today + Duration::days(1)).and_time(end))
+        } else if self.applies_to(today.pred_opt()?.weekday()) && time < end {
+            Some(today.and_time(end))
+        } else {
+            None
+        }
+    }
+
+    fn applies_to(&self, day: chrono::Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&Weekday::from(day))
+    }
+}
+
+/// End of the latest quiet window active at `now`, if any
+pub fn active_until(schedules: &[QuietHours], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    schedules
+        .iter()
+        .filter_map(|q| q.active_until(now))
+        .max()
+}
+
+/// Current local wall-clock time, truncated to the second
+pub fn local_now() -> NaiveDateTime {
+    let now = chrono::Local::now().naive_local();
+    now.with_nanosecond(0).unwrap_or(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2024-01-01 is a Monday
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn window(start: &str, end: &str, days: Vec<Weekday>) -> QuietHours {
+        QuietHours {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            days,
+        }
+    }
+
+    #[test]
+    fn test_time_of_day_parsing() {
+        assert_eq!("07:05".parse::<TimeOfDay>().unwrap().to_string(), "07:05");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("7".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn test_daytime_window() {
+        let q = window("09:00", "17:00", vec![]);
+        assert_eq!(q.active_until(at(1, 12, 0)), Some(at(1, 17, 0)));
+        assert_eq!(q.active_until(at(1, 17, 0)), None);
+        assert_eq!(q.active_until(at(1, 8, 59)), None);
+    }
+
+    #[test]
+    fn test_overnight_window_belongs_to_start_day() {
+        // Friday night only
+        let q = window("22:00", "07:00", vec![Weekday::Fri]);
+        assert_eq!(q.active_until(at(5, 23, 0)), Some(at(6, 7, 0)));
+        assert_eq!(q.active_until(at(6, 6, 0)), Some(at(6, 7, 0)));
+        // Thursday night is not covered
+        assert_eq!(q.active_until(at(4, 23, 0)), None);
+        assert_eq!(q.active_until(at(5, 6, 0)), None);
+    }
+
+    #[test]
+    fn test_schedule_serialization() {
+        let json = r#"{"start":"22:00","end":"07:00","days":["sat","sun"]}"#;
+        let q: QuietHours = serde_json::from_str(json).unwrap();
+        assert_eq!(q.days, [Weekday::Sat, Weekday::Sun]);
+        assert_eq!(serde_json::to_string(&q).unwrap(), json);
+    }
+}
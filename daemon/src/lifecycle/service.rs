@@ -0,0 +1,220 @@
+//! Readiness and watchdog notifications to the service manager
+//!
+//! Under systemd (`Type=notify`, see `second-brain-daemon.service`) the
+//! daemon sends `READY=1` once it serves clients, `WATCHDOG=1` while it is
+//! alive and `STOPPING=1` when it shuts down, as datagrams to
+//! `$NOTIFY_SOCKET`. launchd has no such protocol: it keeps the daemon
+//! alive by restarting it when it exits with a failure (see
+//! `com.secondbrain.daemon.plist`), and without `$NOTIFY_SOCKET` every
+//! notification is skipped.
+
+use std::ffi::OsString;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+/// Errors setting up notifications from the environment
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    #[error("invalid NOTIFY_SOCKET {0:?}")]
+    InvalidSocket(OsString),
+
+    #[error("invalid WATCHDOG_USEC {0:?}")]
+    InvalidWatchdog(String),
+
+    #[error("failed to open a notification socket: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Sends state changes to the service manager, if there is one
+#[derive(Debug, Clone, Default)]
+pub struct ServiceNotifier {
+    socket: Option<Arc<(UnixDatagram, SocketAddr)>>,
+    /// Half the watchdog timeout the service manager enforces
+    watchdog: Option<Duration>,
+}
+
+impl ServiceNotifier {
+    /// Notify the service manager named by `$NOTIFY_SOCKET`, if any
+    ///
+    /// Problems with the environment are logged and leave notifications
+    /// off: the daemon works without them, it is just not supervised.
+    pub fn from_env() -> Self {
+        let result = Self::from_vars(
+            std::env::var_os("NOTIFY_SOCKET"),
+            std::env::var("WATCHDOG_USEC").ok(),
+            std::env::var("WATCHDOG_PID").ok(),
+        );
+        match result {
+            Ok(notifier) => notifier,
+            Err(e) => {
+                warn!(?e, "not notifying the service manager");
+                Self::default()
+            }
+        }
+    }
+
+    pub(super) fn from_vars(
+        socket: Option<OsString>,
+        watchdog_usec: Option<String>,
+        watchdog_pid: Option<String>,
+    ) -> Result<Self, ServiceError> {
+        let Some(socket) = socket.filter(|socket| !socket.is_empty()) else {
+            return Ok(Self::default());
+        };
+        let address = notify_address(&socket)?;
+        let datagram = UnixDatagram::unbound()?;
+        // Never hold up the daemon on a service manager that stopped reading
+        datagram.set_nonblocking(true)?;
+
+        // The timeout is meant for the main process only
+        let ours = watchdog_pid.is_none_or(|pid| pid.trim() == std::process::id().to_string());
+        let watchdog = match watchdog_usec.filter(|_| ours) {
+            Some(usec) => match usec.trim().parse::<u64>() {
+                Ok(0) => None,
+                Ok(usec) => Some(Duration::from_micros(usec) / 2),
+                Err(_) => return Err(ServiceError::InvalidWatchdog(usec)),
+            },
+            None => None,
+        };
+
+        debug!(?socket, ?watchdog, "notifying the service manager");
+        Ok(Self {
+            socket: Some(Arc::new((datagram, address))),
+            watchdog,
+        })
+    }
+
+    /// How often to call `watchdog`, if the service manager expects it
+    pub fn watchdog_interval(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    /// The daemon serves clients; `status` says how well
+    pub fn ready(&self, status: &str) {
+        self.notify(&format!("READY=1\nSTATUS={}", status));
+    }
+
+    /// The daemon is still alive; `status` says how well
+    pub fn watchdog(&self, status: &str) {
+        self.notify(&format!("WATCHDOG=1\nSTATUS={}", status));
+    }
+
+    /// The daemon is shutting down
+    pub fn stopping(&self) {
+        self.notify("STOPPING=1");
+    }
+
+    fn notify(&self, message: &str) {
+        let Some(socket) = &self.socket else {
+            return;
+        };
+        let (datagram, address) = &**socket;
+        if let Err(e) = datagram.send_to_addr(message.as_bytes(), address) {
+            warn!(?e, message, "failed to notify the service manager");
+        }
+    }
+}
+
+/// The address in `$NOTIFY_SOCKET`: a path, or on Linux an abstract name
+/// starting with `@`
+fn notify_address(socket: &OsString) -> Result<SocketAddr, ServiceError> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let invalid = || ServiceError::InvalidSocket(socket.clone());
+    match socket.as_bytes() {
+        [b'@', name @ ..] => abstract_address(name).ok_or_else(invalid),
+        [b'/', ..] => SocketAddr::from_pathname(socket).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_address(name: &[u8]) -> Option<SocketAddr> {
+    use std::os::linux::net::SocketAddrExt;
+
+    SocketAddr::from_abstract_name(name).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn abstract_address(_name: &[u8]) -> Option<SocketAddr> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A service manager's notification socket, removed on drop
+    struct FakeManager {
+        socket: UnixDatagram,
+        dir: std::path::PathBuf,
+    }
+
+    impl Drop for FakeManager {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// A notifier talking to a fake service manager
+    fn notifier(name: &str, watchdog_usec: Option<&str>, watchdog_pid: Option<&str>) -> (ServiceNotifier, FakeManager) {
+        let dir = std::env::temp_dir().join(format!("second-brain-notify-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let manager = FakeManager {
+            socket: UnixDatagram::bind(&path).unwrap(),
+            dir,
+        };
+        let notifier = ServiceNotifier::from_vars(
+            Some(path.into_os_string()),
+            watchdog_usec.map(str::to_string),
+            watchdog_pid.map(str::to_string),
+        )
+        .unwrap();
+        (notifier, manager)
+    }
+
+    #[test]
+    fn test_notifications_reach_the_socket() {
+        let (notifier, manager) = notifier("sent", Some("30000000"), None);
+        assert_eq!(notifier.watchdog_interval(), Some(Duration::from_secs(15)));
+
+        notifier.ready("running");
+        notifier.watchdog("config degraded: bad");
+        notifier.stopping();
+        let mut received = Vec::new();
+        let mut buffer = [0; 256];
+        for _ in 0..3 {
+            let len = manager.socket.recv(&mut buffer).unwrap();
+            received.push(String::from_utf8(buffer[..len].to_vec()).unwrap());
+        }
+        assert_eq!(
+            received,
+            ["READY=1\nSTATUS=running", "WATCHDOG=1\nSTATUS=config degraded: bad", "STOPPING=1"]
+        );
+    }
+
+    #[test]
+    fn test_watchdog_is_only_for_the_named_pid() {
+        let (other, _manager) = notifier("other-pid", Some("30000000"), Some("1"));
+        assert_eq!(other.watchdog_interval(), None);
+
+        let pid = std::process::id().to_string();
+        let (ours, _manager) = notifier("our-pid", Some("30000000"), Some(&pid));
+        assert!(ours.watchdog_interval().is_some());
+    }
+
+    #[test]
+    fn test_without_a_socket_nothing_is_sent() {
+        let notifier = ServiceNotifier::from_vars(None, Some("30000000".to_string()), None).unwrap();
+        assert_eq!(notifier.watchdog_interval(), None);
+        notifier.ready("running");
+
+        let relative = ServiceNotifier::from_vars(Some("notify.sock".into()), None, None);
+        assert!(matches!(relative, Err(ServiceError::InvalidSocket(_))));
+    }
+}
@@ -0,0 +1,90 @@
+//! Signal handling: SIGTERM/SIGINT shut down, SIGHUP reloads the config,
+//! SIGUSR1 dumps diagnostics
+
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tracing::debug;
+
+/// Handles shutdown signals (SIGTERM, SIGINT)
+pub struct ShutdownSignal;
+
+impl ShutdownSignal {
+    /// Create a new shutdown signal handler
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Wait for a shutdown signal, returning its name
+    pub async fn wait(&self) -> &'static str {
+        let mut sigterm = signal(SignalKind::terminate())
+            .expect("failed to register SIGTERM handler");
+        let mut sigint = signal(SignalKind::interrupt())
+            .expect("failed to register SIGINT handler");
+
+        tokio::select! {
+            _ = sigterm.recv() => "SIGTERM",
+            _ = sigint.recv() => "SIGINT",
+        }
+    }
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Delivers SIGHUP, the conventional "reload your config" signal
+///
+/// The handler is installed on creation; from then on SIGHUP no longer
+/// terminates the process.
+pub struct ReloadSignal {
+    sighup: Signal,
+}
+
+impl ReloadSignal {
+    /// Install the SIGHUP handler
+    pub fn new() -> Self {
+        let sighup = signal(SignalKind::hangup()).expect("failed to register SIGHUP handler");
+        Self { sighup }
+    }
+
+    /// Wait for the next SIGHUP
+    pub async fn recv(&mut self) {
+        self.sighup.recv().await;
+        debug!("received SIGHUP");
+    }
+}
+
+impl Default for ReloadSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Delivers SIGUSR1, asking for a diagnostic snapshot
+///
+/// The handler is installed on creation; from then on SIGUSR1 no longer
+/// terminates the process.
+pub struct DiagnosticsSignal {
+    sigusr1: Signal,
+}
+
+impl DiagnosticsSignal {
+    /// Install the SIGUSR1 handler
+    pub fn new() -> Self {
+        let sigusr1 = signal(SignalKind::user_defined1()).expect("failed to register SIGUSR1 handler");
+        Self { sigusr1 }
+    }
+
+    /// Wait for the next SIGUSR1
+    pub async fn recv(&mut self) {
+        self.sigusr1.recv().await;
+        debug!("received SIGUSR1");
+    }
+}
+
+impl Default for DiagnosticsSignal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
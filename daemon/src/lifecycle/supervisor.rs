@@ -0,0 +1,395 @@
+//! Keeps daemon components running
+//!
+//! Each component runs as its own task, created by a factory so it can be
+//! started again. After an error or a panic, and after a clean exit if its
+//! `RestartPolicy` says so, the component is restarted with an exponential
+//! backoff; one that then stays up for `HEALTHY_AFTER` restarts quickly
+//! again next time. A component that is not restarted is reported by
+//! `stopped`, so the daemon can shut down rather than run without it.
+//! Failures and recoveries are reported to the `HealthRegistry`. While the
+//! supervisor runs it pings the service manager's watchdog, but only as
+//! long as none of its components has failed and the daemon still answers,
+//! so a wedged or endlessly restarting daemon gets restarted as a whole.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::task::{JoinError, JoinHandle, JoinSet};
+use tracing::{error, info, warn};
+
+use super::{summary, Backoff, ComponentHealth, HealthRegistry, HealthState, ServiceNotifier};
+
+/// First restart delay, doubled on every restart in a row
+const RESTART_INITIAL: Duration = Duration::from_millis(500);
+const RESTART_MAX: Duration = Duration::from_secs(30);
+
+/// How long a component has to run for its backoff to start over
+const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+
+/// How long a component has to run before it is reported healthy
+const STARTUP: Duration = Duration::from_secs(1);
+
+/// When a component is started again after it exits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Restart whenever the component exits
+    Always,
+    /// Restart after an error or a panic; a clean exit is final
+    OnFailure,
+    /// Never restart
+    // Every component so far can be restarted
+    #[allow(dead_code)]
+    Never,
+}
+
+/// Restart history of one component
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentStatus {
+    pub name: String,
+    /// False while waiting to be restarted, and once stopped for good
+    pub running: bool,
+    /// Restarts since the daemon started
+    pub restarts: u32,
+    /// Why the component last failed, if it ever did
+    pub last_error: Option<String>,
+}
+
+/// Runs components and restarts them when they fail
+#[derive(Clone)]
+pub struct Supervisor {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    /// Delays used for each component's restarts
+    backoff: Backoff,
+    health: HealthRegistry,
+    components: Mutex<BTreeMap<&'static str, ComponentStatus>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+    /// The first component that stopped for good
+    stopped: watch::Sender<Option<&'static str>>,
+}
+
+impl Supervisor {
+    /// Create a supervisor reporting component health to `health`
+    pub fn new(health: HealthRegistry) -> Self {
+        Self::with_backoff(Backoff::new(RESTART_INITIAL, RESTART_MAX), health)
+    }
+
+    fn with_backoff(backoff: Backoff, health: HealthRegistry) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                backoff,
+                health,
+                components: Mutex::new(BTreeMap::new()),
+                tasks: Mutex::new(Vec::new()),
+                stopped: watch::Sender::new(None),
+            }),
+        }
+    }
+
+    /// Run the component `name`, calling `start` for every (re)start
+    pub fn spawn<F, Fut>(&self, name: &'static str, policy: RestartPolicy, mut start: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.update(name, |status| status.running = true);
+        self.inner.health.report(name, HealthState::Ok, "starting");
+        let supervisor = self.clone();
+        let task = tokio::spawn(async move {
+            let mut backoff = supervisor.inner.backoff.clone();
+            loop {
+                // Its own task, so a panic is caught; dropping the set when
+                // the supervisor is shut down aborts it
+                let started = Instant::now();
+                let mut run = JoinSet::new();
+                run.spawn(start());
+                let result = match tokio::time::timeout(STARTUP, run.join_next()).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        supervisor.report_running(name);
+                        run.join_next().await
+                    }
+                };
+                let failure = match result.expect("one task was spawned") {
+                    Ok(Ok(())) => None,
+                    Ok(Err(e)) => Some(format!("{:#}", e)),
+                    Err(e) => Some(panic_message(e)),
+                };
+
+                supervisor.update(name, |status| {
+                    status.running = false;
+                    if let Some(failure) = &failure {
+                        status.last_error = Some(failure.clone());
+                    }
+                });
+                let restart = match policy {
+                    RestartPolicy::Always => true,
+                    RestartPolicy::OnFailure => failure.is_some(),
+                    RestartPolicy::Never => false,
+                };
+                let health = &supervisor.inner.health;
+                match &failure {
+                    Some(failure) => health.report(name, HealthState::Failed, failure.as_str()),
+                    None if !restart => health.report(name, HealthState::Failed, "stopped"),
+                    None => {}
+                }
+                if !restart {
+                    match &failure {
+                        Some(failure) => error!(component = name, failure, "component failed"),
+                        None => info!(component = name, "component stopped"),
+                    }
+                    supervisor.inner.stopped.send_if_modified(|stopped| {
+                        let first = stopped.is_none();
+                        stopped.get_or_insert(name);
+                        first
+                    });
+                    return;
+                }
+
+                if started.elapsed() >= HEALTHY_AFTER {
+                    backoff.reset();
+                }
+                let delay = backoff.next_delay();
+                warn!(
+                    component = name,
+                    failure = failure.as_deref().unwrap_or("exited"),
+                    attempt = backoff.attempts(),
+                    ?delay,
+                    "component stopped, restarting"
+                );
+                tokio::time::sleep(delay).await;
+                supervisor.update(name, |status| {
+                    status.running = true;
+                    status.restarts += 1;
+                });
+            }
+        });
+        self.inner.tasks.lock().unwrap_or_else(|e| e.into_inner()).push(task);
+    }
+
+    /// Ping the watchdog of `notifier` until shut down, with the health
+    /// summary as status; nothing to do if no watchdog is expected
+    ///
+    /// A ping is only sent while no supervised component has failed and
+    /// `responsive` resolves to true within the ping interval.
+    pub fn keep_alive<F, Fut>(&self, notifier: ServiceNotifier, mut responsive: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        let Some(interval) = notifier.watchdog_interval() else {
+            return;
+        };
+        let supervisor = self.clone();
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                let components = supervisor.inner.health.components();
+                let failed = supervisor.failed(&components);
+                let answered = tokio::time::timeout(interval, responsive()).await.unwrap_or(false);
+                match failed {
+                    None if answered => notifier.watchdog(&summary(&components)),
+                    None => warn!("daemon did not answer in time, not pinging the watchdog"),
+                    Some(name) => warn!(component = name, "component failed, not pinging the watchdog"),
+                }
+            }
+        });
+        self.inner.tasks.lock().unwrap_or_else(|e| e.into_inner()).push(task);
+    }
+
+    /// The first supervised component `components` reports failed
+    fn failed<'a>(&self, components: &'a [ComponentHealth]) -> Option<&'a str> {
+        let supervised = self.inner.components.lock().unwrap_or_else(|e| e.into_inner());
+        components
+            .iter()
+            .find(|health| health.state == HealthState::Failed && supervised.contains_key(health.name.as_str()))
+            .map(|health| health.name.as_str())
+    }
+
+    /// Restart history of every component, by name
+    pub fn components(&self) -> Vec<ComponentStatus> {
+        let components = self.inner.components.lock().unwrap_or_else(|e| e.into_inner());
+        components.values().cloned().collect()
+    }
+
+    /// Wait for a component to stop for good, returning its name
+    pub async fn stopped(&self) -> &'static str {
+        let mut stopped = self.inner.stopped.subscribe();
+        let name = stopped.wait_for(Option::is_some).await.map(|name| *name);
+        name.ok().flatten().expect("the supervisor outlives its receivers")
+    }
+
+    /// Stop every component without restarting it
+    pub async fn shutdown(&self) {
+        let tasks = std::mem::take(&mut *self.inner.tasks.lock().unwrap_or_else(|e| e.into_inner()));
+        for task in &tasks {
+            task.abort();
+        }
+        for task in tasks {
+            let _ = task.await;
+        }
+
+        let mut components = self.inner.components.lock().unwrap_or_else(|e| e.into_inner());
+        for status in components.values_mut() {
+            status.running = false;
+        }
+    }
+
+    /// Report `name` healthy once it has got through its startup
+    fn report_running(&self, name: &'static str) {
+        let restarts = self.inner.components.lock().unwrap_or_else(|e| e.into_inner())[name].restarts;
+        let message = match restarts {
+            0 => "running".to_string(),
+            1 => "running, restarted once".to_string(),
+            n => format!("running, restarted {} times", n),
+        };
+        self.inner.health.report(name, HealthState::Ok, message);
+    }
+
+    fn update(&self, name: &'static str, change: impl FnOnce(&mut ComponentStatus)) {
+        let mut components = self.inner.components.lock().unwrap_or_else(|e| e.into_inner());
+        let status = components.entry(name).or_insert_with(|| ComponentStatus {
+            name: name.to_string(),
+            running: false,
+            restarts: 0,
+            last_error: None,
+        });
+        change(status);
+    }
+}
+
+/// Describe a component task that panicked or was cancelled
+fn panic_message(error: JoinError) -> String {
+    if !error.is_panic() {
+        return "cancelled".to_string();
+    }
+    let payload = error.into_panic();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    format!("panicked: {}", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn supervisor() -> Supervisor {
+        let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(5));
+        Supervisor::with_backoff(backoff, HealthRegistry::new())
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_restarted_and_recorded() {
+        let supervisor = supervisor();
+        let starts = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&starts);
+        supervisor.spawn("flaky", RestartPolicy::OnFailure, move || {
+            let start = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match start {
+                    0 => anyhow::bail!("device went away"),
+                    1 => panic!("bad state"),
+                    _ => Ok(()),
+                }
+            }
+        });
+
+        assert_eq!(supervisor.stopped().await, "flaky");
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            supervisor.components(),
+            [ComponentStatus {
+                name: "flaky".to_string(),
+                running: false,
+                restarts: 2,
+                last_error: Some("panicked: bad state".to_string()),
+            }]
+        );
+        let health = supervisor.inner.health.components();
+        assert_eq!(health[0].state, HealthState::Failed);
+        assert_eq!(health[0].message, "stopped");
+    }
+
+    /// A notifier pinging a fake service manager's socket every 10ms
+    fn watchdog(name: &str) -> (ServiceNotifier, std::os::unix::net::UnixDatagram, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("second-brain-watchdog-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let manager = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+        manager.set_nonblocking(true).unwrap();
+        let notifier = ServiceNotifier::from_vars(Some(path.into_os_string()), Some("20000".to_string()), None).unwrap();
+        (notifier, manager, dir)
+    }
+
+    /// Watchdog pings received since the last call
+    fn pings(manager: &std::os::unix::net::UnixDatagram) -> usize {
+        let mut buffer = [0; 256];
+        std::iter::from_fn(|| manager.recv(&mut buffer).ok()).count()
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_is_only_pinged_while_healthy() {
+        let (notifier, manager, dir) = watchdog("healthy");
+        let supervisor = supervisor();
+        let (fail, failing) = watch::channel(false);
+        supervisor.spawn("component", RestartPolicy::Always, move || {
+            let mut failing = failing.clone();
+            async move {
+                let _ = failing.wait_for(|failing| *failing).await;
+                anyhow::bail!("stuck in a restart loop")
+            }
+        });
+        let (answer, answering) = watch::channel(true);
+        supervisor.keep_alive(notifier, move || {
+            let answering = *answering.borrow();
+            async move { answering }
+        });
+
+        // Pings received over 50ms, once a ping already underway is past
+        let pings_for_a_while = || async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            pings(&manager);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            pings(&manager)
+        };
+        assert!(pings_for_a_while().await > 0);
+
+        answer.send_replace(false);
+        assert_eq!(pings_for_a_while().await, 0, "pinged while the daemon did not answer");
+        answer.send_replace(true);
+        assert!(pings_for_a_while().await > 0);
+
+        // Every restart fails right away again
+        fail.send_replace(true);
+        assert_eq!(pings_for_a_while().await, 0, "pinged with a failed component");
+
+        supervisor.shutdown().await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_components() {
+        let supervisor = supervisor();
+        supervisor.spawn("forever", RestartPolicy::Always, || async {
+            std::future::pending::<()>().await;
+            Ok(())
+        });
+        supervisor.shutdown().await;
+
+        let stopped = tokio::time::timeout(Duration::from_millis(50), supervisor.stopped()).await;
+        assert!(stopped.is_err(), "a shut down component is not reported as stopped");
+        assert!(!supervisor.components()[0].running);
+    }
+}
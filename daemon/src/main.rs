@@ -1,7 +1,7 @@
 //! second-brain-daemon: Background daemon for voice-first macOS assistant
 //!
 //! This daemon runs as a LaunchAgent and provides:
-//! - Global hotkey detection via CGEventTap
+//! - Global hotkey detection (CGEventTap on macOS, evdev on Linux)
 //! - Explicit state machine for mode management
 //! - IPC server for menu bar app communication
 //!
@@ -11,43 +11,112 @@
 //! - IPC for status queries and mode notifications
 //! - NO audio capture, LLM calls, or text insertion
 
+mod cli;
 mod config;
 mod events;
 mod hotkey;
 mod ipc;
 mod lifecycle;
+mod secrets;
+mod settings;
 mod state;
 
-use anyhow::Result;
-use tokio::sync::{broadcast, mpsc};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tracing::{error, info, warn};
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
-use crate::config::Config;
+use crate::cli::{Cli, Command, SecretCommand};
+use crate::config::{Config, ConfigWatcher, Diagnostic, Overrides, Paths, Severity};
 use crate::events::StateEvent;
-use crate::hotkey::HotkeyListener;
-use crate::ipc::Server;
-use crate::lifecycle::ShutdownSignal;
-use crate::state::StateMachine;
+use crate::hotkey::{HotkeyListener, HotkeyRecorder, HotkeySource, SyntheticSource};
+use crate::ipc::{Client, DaemonPaths, Request, Response, Server};
+use crate::lifecycle::{
+    summary, DiagnosticsSignal, HealthRegistry, HealthState, InstanceError, InstanceLock, PauseController,
+    ReloadSignal, RestartPolicy, ServiceNotifier, ShutdownSignal, Supervisor,
+};
+use crate::secrets::Secret;
+use crate::settings::Settings;
+use crate::state::{Command as MachineCommand, DiagramFormat, StateMachine, TransitionTable};
+
+/// How long a running daemon gets to answer the startup `Ping`
+const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How long clients, then the state machine, get to finish at shutdown
+const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
+    let cli = Cli::parse();
+    let overrides = cli.overrides.resolve().context("failed to resolve path")?;
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(&overrides).await?,
+        Command::PrintConfig => print_config(&overrides)?,
+        Command::CheckConfig => return check_config(&overrides),
+        Command::Version { json } => print_version(json),
+        Command::Replay { recording } => replay_recording(&recording)?,
+        Command::Diagram { format } => print_diagram(&overrides, format).await?,
+        Command::Secret { action } => manage_secrets(&overrides, action).await?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Run the daemon until a shutdown signal arrives
+async fn run(overrides: &Overrides) -> Result<()> {
+    // Move files out of the locations earlier versions used; config.toml
+    // first, so it is the one loaded
+    let mut migrated = Vec::new();
+    if overrides.config_path.is_none() {
+        migrated.push(Paths::from_env()?.migrate_config());
+    }
+
+    // Load configuration; problems in config.toml are reported over IPC
+    // instead of stopping the daemon
+    let config = Config::load(overrides)?;
+
+    // One daemon per data directory; locked before anything is moved into it
+    config.ensure_dirs().context("failed to create data directory")?;
+    let _instance = lock_instance(&config).await?;
+
+    migrated.push(config.paths.migrate_state(&config.data_dir));
+    let config_watcher = ConfigWatcher::new(&config.config_path);
+    let reload_signal = ReloadSignal::new();
+    let diagnostics_signal = DiagnosticsSignal::new();
+
     // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| EnvFilter::new("info"))
-        )
-        .init();
+    let log_filter = init_logging(&config.file.logging.level);
 
     info!(
         version = env!("CARGO_PKG_VERSION"),
         "second-brain-daemon starting"
     );
 
-    // Load configuration
-    let config = Config::load()?;
-    info!(?config.socket_path, "configuration loaded");
+    for result in migrated {
+        match result {
+            Ok(moved) => {
+                for (from, to) in moved {
+                    info!(?from, ?to, "moved file from old location");
+                }
+            }
+            Err(e) => warn!(?e, "failed to move files from old location"),
+        }
+    }
+
+    info!(?config.socket_path, ?config.config_path, "configuration loaded");
+    log_config_diagnostics(&config.config_path, &config.diagnostics);
+
+    // Load persisted runtime settings; what they don't change comes from
+    // the config file with their profile applied
+    let settings = load_settings(&config);
+    let active = config.file.active(settings.profile.as_deref());
+    let bindings = settings.bindings(&active);
 
     // Create shutdown signal handler
     let shutdown = ShutdownSignal::new();
@@ -55,94 +124,489 @@ async fn main() -> Result<()> {
     // Create channels for inter-component communication
     // Hotkey listener -> State machine
     let (hotkey_tx, hotkey_rx) = mpsc::channel(32);
+    // IPC server -> State machine (runtime commands)
+    let (command_tx, command_rx) = mpsc::channel(8);
+    let machine_commands = command_tx.clone();
     // State machine -> IPC server (for broadcasting state events)
     let (event_tx, _event_rx) = broadcast::channel::<StateEvent>(64);
 
-    // Create the state machine
-    let mut state_machine = StateMachine::new(event_tx.clone());
+    // Create the state machine; its store is the one source of daemon state.
+    // It holds the only sender, so the channel closes once it has stopped.
+    let events = event_tx.downgrade();
+    let mut state_machine = StateMachine::new(event_tx);
+    state_machine.set_enabled_modes(settings.enabled_modes(&active), std::time::Instant::now());
+    state_machine.set_bindings(bindings, std::time::Instant::now());
+    let store = state_machine.store();
+
+    // Optionally record the hotkey event stream for later replay
+    if let Some(path) = &config.record_hotkeys {
+        match HotkeyRecorder::create(path) {
+            Ok(recorder) => {
+                info!(?path, "recording hotkey events");
+                state_machine.set_recorder(recorder);
+            }
+            Err(e) => {
+                warn!(?e, ?path, "failed to create hotkey recording");
+            }
+        }
+    }
 
-    // Create the hotkey listener
-    let hotkey_listener = HotkeyListener::new(hotkey_tx);
+    // Where components report whether they work, for get_health
+    let health = HealthRegistry::new();
 
-    // Start the hotkey listener (runs on dedicated thread)
-    match hotkey_listener.start() {
-        Ok(()) => {
-            info!("hotkey listener started");
+    // Create the hotkey source and start it (runs on dedicated thread).
+    // With input injection enabled, hotkeys come over IPC instead of the keyboard.
+    let mut synthetic_input = None;
+    let hotkey_source = if config.inject_input {
+        let (source, input) = SyntheticSource::new(hotkey_tx);
+        synthetic_input = Some(input);
+        Ok(Box::new(source) as Box<dyn HotkeySource>)
+    } else {
+        hotkey::platform_source(hotkey_tx, &bindings)
+    };
+    // Keep the listener alive even if it fails to start: dropping it closes
+    // the hotkey channel, which stops the state machine
+    let hotkey_listener = hotkey_source
+        .map(|source| Arc::new(HotkeyListener::new(source, bindings, store.clone())));
+    let started = match &hotkey_listener {
+        Ok(listener) => listener.start().map(|()| listener.name()),
+        Err(e) => Err(e.clone()),
+    };
+    match started {
+        Ok(name) => {
+            info!(source = name, "hotkey listener started");
         }
         Err(e) => {
             error!(?e, "failed to start hotkey listener");
-            warn!("continuing without hotkey support - check Accessibility permissions");
+            warn!("continuing without hotkey support - {}", e);
+            if hotkey_listener.is_err() {
+                health.report(HotkeyListener::COMPONENT, HealthState::Failed, e.to_string());
+            }
+        }
+    }
+
+    // Combine manual pauses and quiet hours; tells the state machine when to ignore hotkeys
+    let (pause_controller, pause_handle) =
+        PauseController::new(settings.quiet_hours.clone(), command_tx.clone());
+
+    // Create IPC server
+    let server = Arc::new(Server::new(&config.socket_path)?);
+    server.set_store(store).await;
+    server.set_health(health.clone()).await;
+    server
+        .set_config(config.file.clone(), config.diagnostics.clone())
+        .await;
+    server
+        .set_paths(DaemonPaths {
+            socket: config.socket_path.clone(),
+            config_file: config.config_path.clone(),
+            data_dir: config.data_dir.clone(),
+            settings_file: config.settings_path.clone(),
+        })
+        .await;
+    if let Ok(listener) = &hotkey_listener {
+        server.set_listener(Arc::clone(listener)).await;
+    }
+    server
+        .set_settings(settings, config.settings_path.clone(), command_tx)
+        .await;
+    server.set_pause(pause_handle).await;
+    match secrets::open(config.file.secrets.backend, &config.data_dir) {
+        Ok(store) => {
+            info!(backend = store.name(), "secret store opened");
+            health.report("secrets", HealthState::Ok, format!("using the {} store", store.name()));
+            server.set_secrets(store).await;
+        }
+        Err(e) => {
+            warn!(?e, "secret store unavailable, continuing without secrets");
+            health.report("secrets", HealthState::Failed, e.to_string());
         }
     }
+    if let Some(input) = synthetic_input {
+        server.set_input(input).await;
+    }
 
-    // Create IPC server with event subscription
-    let server = Server::with_events(&config.socket_path, event_tx.subscribe())?;
+    // Components that are restarted if they fail
+    let supervisor = Supervisor::new(health.clone());
+    server.set_supervisor(supervisor.clone()).await;
+    supervisor.spawn("ipc server", RestartPolicy::OnFailure, {
+        let server = Arc::clone(&server);
+        move || {
+            let server = Arc::clone(&server);
+            async move { server.run().await }
+        }
+    });
+    supervisor.spawn("notifications", RestartPolicy::OnFailure, {
+        let server = Arc::clone(&server);
+        move || {
+            let server = Arc::clone(&server);
+            let event_rx = events.upgrade().map(|event_tx| event_tx.subscribe());
+            async move {
+                match event_rx {
+                    Some(event_rx) => server.forward_events(event_rx).await,
+                    None => Ok(()),
+                }
+            }
+        }
+    });
+    if let Ok(listener) = &hotkey_listener {
+        let listener = Arc::clone(listener);
+        let health = health.clone();
+        supervisor.spawn(HotkeyListener::COMPONENT, RestartPolicy::Always, move || {
+            let listener = Arc::clone(&listener);
+            let health = health.clone();
+            async move { Ok(listener.watch(&health).await?) }
+        });
+    }
 
-    // Subscribe to state events for IPC updates
-    let mut ipc_event_rx = event_tx.subscribe();
-    let server_for_events = &server;
+    // The socket is bound and the hotkey source started (or failed to):
+    // tell systemd, and keep telling it while the components are up and the
+    // state machine answers
+    let notifier = ServiceNotifier::from_env();
+    supervisor.keep_alive(notifier.clone(), {
+        let commands = machine_commands.clone();
+        move || {
+            let commands = commands.clone();
+            async move {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                commands.send(MachineCommand::Ping(reply_tx)).await.is_ok() && reply_rx.await.is_ok()
+            }
+        }
+    });
+    notifier.ready(&summary(&health.components()));
 
     info!("daemon initialized, entering main loop");
 
     // Main event loop
-    tokio::select! {
+    let mut machine = tokio::spawn(async move { state_machine.run(hotkey_rx, command_rx).await });
+    let reason = tokio::select! {
         // Run the state machine (processes hotkey events)
-        _ = state_machine.run(hotkey_rx) => {
+        _ = &mut machine => {
             info!("state machine exited");
+            "state machine exited".to_string()
         }
-        
-        // Run the IPC server (accepts client connections)
-        result = server.run() => {
-            if let Err(e) = result {
-                error!(?e, "IPC server error");
-            }
+
+        // Run the pause controller (manual pauses and quiet hours)
+        _ = pause_controller.run() => {
+            info!("pause controller exited");
+            "pause controller exited".to_string()
         }
-        
-        // Handle state events for IPC synchronization
-        _ = async {
-            loop {
-                match ipc_event_rx.recv().await {
-                    Ok(event) => {
-                        info!(?event, "state event received");
-                        // Update the IPC server's view of current state
-                        let new_state = match &event {
-                            StateEvent::DictationStarted => state::State::DictationActive,
-                            StateEvent::DictationComplete { .. } => state::State::Idle,
-                            StateEvent::IntelligentStarted => state::State::IntelligentActive,
-                            StateEvent::IntelligentRequestComplete { .. } => state::State::Idle,
-                            StateEvent::AgentModeEntered => state::State::AgentActive,
-                            StateEvent::AgentModeExited { .. } => state::State::Idle,
-                            StateEvent::AudioCaptureStarted | StateEvent::AudioCaptureStopped => {
-                                continue; // Don't update state for audio events
-                            }
-                        };
-                        server_for_events.set_state(new_state).await;
-                    }
-                    Err(broadcast::error::RecvError::Lagged(n)) => {
-                        warn!(skipped = n, "state event receiver lagged");
-                    }
-                    Err(broadcast::error::RecvError::Closed) => {
-                        break;
-                    }
-                }
-            }
-        } => {
-            info!("state event handler exited");
+
+        // A supervised component that can't be restarted
+        component = supervisor.stopped() => {
+            error!(component, "component stopped");
+            format!("{} stopped", component)
+        }
+
+        // Apply edits to config.toml (runs until shutdown)
+        _ = watch_config(&config, config_watcher, reload_signal, &server, &log_filter) => {
+            "config watcher stopped".to_string()
+        }
+
+        // Write a diagnostic snapshot on SIGUSR1 (runs until shutdown)
+        () = dump_diagnostics_on_signal(diagnostics_signal, &server) => {
+            "diagnostics signal handler stopped".to_string()
         }
-        
+
         // Wait for shutdown signal
-        _ = shutdown.wait() => {
-            info!("shutdown signal received");
+        signal = shutdown.wait() => {
+            info!(signal, "shutdown signal received");
+            format!("received {}", signal)
         }
-    }
+    };
 
-    // Cleanup
+    // Shut down in phases: stop taking new clients and tell the connected
+    // ones, let them finish what they have in flight, then end active
+    // modes while subscribers are still connected to get the exit events,
+    // and finally close the connections and stop the supervised components
     info!("shutting down...");
-    
-    hotkey_listener.stop();
+    notifier.stopping();
+    server.drain(reason, DRAIN_TIMEOUT).await;
+    if !machine.is_finished() {
+        let _ = machine_commands.send(MachineCommand::Stop).await;
+        if tokio::time::timeout(DRAIN_TIMEOUT, machine).await.is_err() {
+            warn!("state machine did not stop in time");
+        }
+    }
+    server.close().await;
+    supervisor.shutdown().await;
+    if let Ok(listener) = &hotkey_listener {
+        listener.stop();
+    }
+
+    // Finally remove the socket
     server.shutdown().await;
-    
+
     info!("second-brain-daemon stopped");
 
     Ok(())
 }
+
+/// Handle for changing the log filter at runtime
+type LogFilter = reload::Handle<EnvFilter, Registry>;
+
+/// Set up logging at `level`, returning a handle for changing it later
+fn init_logging(level: &str) -> LogFilter {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(level));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+    handle
+}
+
+/// Log problems found in `config.toml`
+fn log_config_diagnostics(path: &Path, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        match diagnostic.severity {
+            Severity::Error => error!(?path, "config error: {}", diagnostic),
+            Severity::Warning => warn!(?path, "config warning: {}", diagnostic),
+        }
+    }
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        warn!("config file not applied");
+    }
+}
+
+/// Load the persisted runtime settings, ignoring them if unreadable and
+/// dropping an active profile that is gone from the config
+fn load_settings(config: &Config) -> Settings {
+    let mut settings = Settings::load(&config.settings_path).unwrap_or_else(|e| {
+        warn!(?e, "failed to load settings, using the config file alone");
+        Settings::default()
+    });
+    if let Some(profile) = &settings.profile {
+        if config.file.profiles.contains_key(profile) {
+            info!(profile, "profile active");
+        } else {
+            warn!(profile, "active profile not in config, using none");
+            settings.profile = None;
+        }
+    }
+    settings
+}
+
+/// Re-read `config.toml` whenever it changes or on SIGHUP, and apply it
+///
+/// Flags and environment variables keep taking precedence over the file.
+async fn watch_config(
+    config: &Config,
+    mut watcher: ConfigWatcher,
+    mut reload_signal: ReloadSignal,
+    server: &Server,
+    log_filter: &LogFilter,
+) {
+    loop {
+        tokio::select! {
+            _ = watcher.changed() => info!("config file changed"),
+            _ = reload_signal.recv() => info!("reloading config on SIGHUP"),
+        }
+
+        let (file, diagnostics) = config.reload_file();
+        log_config_diagnostics(&config.config_path, &diagnostics);
+        let level = file.logging.level.clone();
+        let Some(diff) = server.reload_config(file, diagnostics).await else {
+            continue;
+        };
+
+        if diff.touches("logging") {
+            if let Err(e) = log_filter.reload(EnvFilter::new(&level)) {
+                warn!(?e, "failed to change log level");
+            }
+        }
+        if !diff.restart_required.is_empty() {
+            warn!(keys = ?diff.restart_required, "restart the daemon to apply these config changes");
+        }
+    }
+}
+
+/// Write a diagnostic snapshot whenever SIGUSR1 arrives
+async fn dump_diagnostics_on_signal(mut signal: DiagnosticsSignal, server: &Server) {
+    loop {
+        signal.recv().await;
+        info!("writing diagnostics on SIGUSR1");
+        if let Err(e) = server.dump_diagnostics().await {
+            warn!(?e, "failed to write diagnostics");
+        }
+    }
+}
+
+/// Print the effective configuration as TOML
+///
+/// Problems in `config.toml` go to stderr; the printed config is what the
+/// daemon would run with.
+fn print_config(overrides: &Overrides) -> Result<()> {
+    let config = Config::load(overrides)?;
+    for diagnostic in &config.diagnostics {
+        eprintln!("{}", format_diagnostic(&config.config_path, diagnostic));
+    }
+    print!("{}", toml::to_string_pretty(&config.effective())?);
+    Ok(())
+}
+
+/// Validate `config.toml`, failing if it has errors
+fn check_config(overrides: &Overrides) -> Result<ExitCode> {
+    let config = Config::load(overrides)?;
+    let path = &config.config_path;
+    if !path.exists() {
+        println!("{}: not found, defaults apply", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for diagnostic in &config.diagnostics {
+        println!("{}", format_diagnostic(path, diagnostic));
+    }
+    if config.diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("{}: ok", path.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `path:line:column: severity: message`, like compiler output
+fn format_diagnostic(path: &Path, diagnostic: &Diagnostic) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    match (diagnostic.line, diagnostic.column) {
+        (Some(line), Some(column)) => format!(
+            "{}:{}:{}: {}: {}",
+            path.display(),
+            line,
+            column,
+            severity,
+            diagnostic.message
+        ),
+        _ => format!("{}: {}: {}", path.display(), severity, diagnostic.message),
+    }
+}
+
+/// Print the name and version, optionally with the platform as JSON
+fn print_version(json: bool) {
+    if json {
+        let version = serde_json::json!({
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+        });
+        println!("{}", version);
+    } else {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    }
+}
+
+/// Replay a hotkey recording and print the resulting state events as JSONL
+fn replay_recording(path: &Path) -> Result<()> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("failed to open recording {}", path.display()))?;
+    let recording = hotkey::read_recording(file)?;
+
+    for event in state::replay(&recording)? {
+        println!("{}", serde_json::to_string(&event)?);
+    }
+
+    Ok(())
+}
+
+/// Take the instance lock, or explain which daemon already holds it
+async fn lock_instance(config: &Config) -> Result<InstanceLock> {
+    let held = match InstanceLock::acquire(&config.data_dir) {
+        Ok(lock) => return Ok(lock),
+        Err(e @ InstanceError::Io { .. }) => return Err(e.into()),
+        Err(e) => e,
+    };
+
+    let socket = config.socket_path.display();
+    if ping(&config.socket_path).await {
+        anyhow::bail!("already running: {} and answers on {}", held, socket);
+    }
+    anyhow::bail!(
+        "{}, but nothing answers on {} - it may still be starting or be using another socket",
+        held,
+        socket
+    )
+}
+
+/// Whether a daemon answers `Ping` on `socket_path`
+async fn ping(socket_path: &Path) -> bool {
+    let probe = async {
+        let mut client = Client::connect(socket_path).await?;
+        client.request(&Request::Ping).await
+    };
+    matches!(
+        tokio::time::timeout(PING_TIMEOUT, probe).await,
+        Ok(Ok(Response::Pong))
+    )
+}
+
+/// Set, list or delete secrets through the running daemon
+///
+/// Going through the daemon keeps it the only writer of the store, and
+/// lets it ask for keychain access once on behalf of the CLI.
+async fn manage_secrets(overrides: &Overrides, action: SecretCommand) -> Result<()> {
+    let request = match action {
+        SecretCommand::Set { name } => {
+            let mut value = String::new();
+            std::io::stdin()
+                .read_line(&mut value)
+                .context("failed to read secret from stdin")?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            anyhow::ensure!(!value.is_empty(), "no secret given on stdin");
+            Request::SetSecret {
+                name,
+                value: Secret::new(value),
+            }
+        }
+        SecretCommand::List => Request::ListSecrets,
+        SecretCommand::Delete { name } => Request::DeleteSecret { name },
+    };
+
+    let config = Config::load(overrides)?;
+    let mut client = Client::connect(&config.socket_path)
+        .await
+        .context("daemon not reachable")?;
+    match client.request(&request).await? {
+        Response::Secrets { names } => {
+            if matches!(request, Request::ListSecrets) {
+                for name in names {
+                    println!("{}", name);
+                }
+            }
+            Ok(())
+        }
+        Response::Error { message, .. } => anyhow::bail!(message),
+        other => anyhow::bail!("unexpected response from daemon: {:?}", other),
+    }
+}
+
+/// Print the state machine as a Mermaid or DOT diagram
+///
+/// Asks the running daemon so the current state is highlighted, and falls
+/// back to rendering locally what it would start with if it isn't
+/// reachable: `config.toml` with the persisted settings applied, as `run`
+/// does.
+async fn print_diagram(overrides: &Overrides, format: DiagramFormat) -> Result<()> {
+    let config = Config::load(overrides)?;
+    let request = Request::RenderStateMachine { format };
+    let response = match Client::connect(&config.socket_path).await {
+        Ok(mut client) => client.request(&request).await,
+        Err(e) => Err(e),
+    };
+
+    match response {
+        Ok(Response::Diagram { source, .. }) => print!("{}", source),
+        Ok(other) => anyhow::bail!("unexpected response from daemon: {:?}", other),
+        Err(e) => {
+            eprintln!("daemon not reachable ({:#}), rendering configured state machine", e);
+            let settings = load_settings(&config);
+            let active = config.file.active(settings.profile.as_deref());
+            let table = TransitionTable::new(&settings.bindings(&active));
+            let description = table.describe(&settings.enabled_modes(&active), None);
+            print!("{}", state::render_diagram(&description, format));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+//! Secrets in a file encrypted with a local master key
+//!
+//! `secrets.key` holds a random ChaCha20-Poly1305 key and `secrets.enc` the
+//! encrypted name-to-value map, both readable only by the user. This keeps
+//! secrets out of plain-text config and backups of it, but not away from
+//! anything running as the user; the keychain does better where there is one.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use zeroize::Zeroizing;
+
+use super::{validate_name, Secret, SecretError, SecretStore};
+
+/// Start of the encrypted file, also authenticated with the contents
+const MAGIC: &[u8] = b"SBSECRETS1";
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Secrets encrypted into a file in the data directory
+pub struct EncryptedFile {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+    /// Serializes read-modify-write cycles
+    lock: Mutex<()>,
+}
+
+impl fmt::Debug for EncryptedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedFile").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+impl EncryptedFile {
+    /// Open the store in `dir`, creating the master key on first use
+    pub fn open(dir: &Path) -> Result<Self, SecretError> {
+        let path = dir.join("secrets.enc");
+        let key_path = dir.join("secrets.key");
+
+        let key = match fs::read(&key_path) {
+            Ok(bytes) if bytes.len() == KEY_LEN => Zeroizing::new(bytes),
+            Ok(_) => return Err(SecretError::Corrupt),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if path.exists() {
+                    return Err(SecretError::KeyMissing(key_path.display().to_string()));
+                }
+                fs::create_dir_all(dir)?;
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private(&key_path, &key, true)?;
+                Zeroizing::new(key.to_vec())
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, Secret>, SecretError> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let rest = bytes.strip_prefix(MAGIC).ok_or(SecretError::Corrupt)?;
+        if rest.len() < NONCE_LEN {
+            return Err(SecretError::Corrupt);
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: MAGIC,
+        };
+        let plaintext = Zeroizing::new(
+            self.cipher
+                .decrypt(Nonce::from_slice(nonce), payload)
+                .map_err(|_| SecretError::Corrupt)?,
+        );
+        serde_json::from_slice(&plaintext).map_err(|_| SecretError::Corrupt)
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, Secret>) -> Result<(), SecretError> {
+        let plaintext = Zeroizing::new(serde_json::to_vec(secrets).expect("secrets serialize"));
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: plaintext.as_slice(),
+            aad: MAGIC,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .expect("encrypting an in-memory buffer can't fail");
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+
+        let tmp_path = self.path.with_extension("enc.tmp");
+        write_private(&tmp_path, &bytes, false)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    /// Apply `change` to the stored secrets and write them back
+    fn update<T>(&self, change: impl FnOnce(&mut BTreeMap<String, Secret>) -> T) -> Result<T, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut secrets = self.load()?;
+        let result = change(&mut secrets);
+        self.save(&secrets)?;
+        Ok(result)
+    }
+}
+
+impl SecretStore for EncryptedFile {
+    fn name(&self) -> &'static str {
+        "encrypted file"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &Secret) -> Result<(), SecretError> {
+        validate_name(name)?;
+        self.update(|secrets| {
+            secrets.insert(name.to_string(), value.clone());
+        })
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, SecretError> {
+        self.update(|secrets| secrets.remove(name).is_some())
+    }
+
+    fn list(&self) -> Result<Vec<String>, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.into_keys().collect())
+    }
+}
+
+/// Write `bytes` to a file only the user can read
+///
+/// With `create_new`, fails instead of replacing an existing file.
+fn write_private(path: &Path, bytes: &[u8], create_new: bool) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).mode(0o600);
+    if create_new {
+        options.create_new(true);
+    } else {
+        options.create(true).truncate(true);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("second-brain-secrets-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = temp_dir("round-trip");
+        let store = EncryptedFile::open(&dir).unwrap();
+        store.set("openai", &Secret::new("sk-1")).unwrap();
+        store.set("deepgram", &Secret::new("dg-2")).unwrap();
+        assert!(store.delete("deepgram").unwrap());
+        assert!(!store.delete("deepgram").unwrap());
+
+        // A second instance reads what the first wrote
+        let reopened = EncryptedFile::open(&dir).unwrap();
+        let value = reopened.get("openai").unwrap();
+        let names = reopened.list().unwrap();
+        let contents = fs::read(dir.join("secrets.enc")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(value.as_ref().map(Secret::expose), Some("sk-1"));
+        assert_eq!(names, ["openai"]);
+        assert!(!contents.windows(4).any(|w| w == b"sk-1"));
+    }
+
+    #[test]
+    fn test_wrong_key_is_detected() {
+        let dir = temp_dir("wrong-key");
+        EncryptedFile::open(&dir).unwrap().set("a", &Secret::new("1")).unwrap();
+
+        fs::remove_file(dir.join("secrets.key")).unwrap();
+        let missing = EncryptedFile::open(&dir);
+
+        fs::write(dir.join("secrets.key"), [7u8; KEY_LEN]).unwrap();
+        let listed = EncryptedFile::open(&dir).unwrap().list();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(missing, Err(SecretError::KeyMissing(_))));
+        assert!(matches!(listed, Err(SecretError::Corrupt)));
+    }
+}
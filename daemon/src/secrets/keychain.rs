@@ -0,0 +1,91 @@
+//! Secrets in the macOS login keychain
+//!
+//! All secrets share one generic-password item holding a JSON map, so the
+//! user is asked at most once to let the daemon use it.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use security_framework::passwords::{delete_generic_password, get_generic_password, set_generic_password};
+use zeroize::Zeroizing;
+
+use super::{validate_name, Secret, SecretError, SecretStore};
+
+const SERVICE: &str = "com.secondbrain.daemon";
+const ACCOUNT: &str = "secrets";
+
+/// `errSecItemNotFound`
+const ITEM_NOT_FOUND: i32 = -25300;
+
+/// Secrets in the user's keychain
+#[derive(Debug, Default)]
+pub struct Keychain {
+    /// Serializes read-modify-write cycles
+    lock: Mutex<()>,
+}
+
+impl Keychain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, Secret>, SecretError> {
+        match get_generic_password(SERVICE, ACCOUNT) {
+            Ok(bytes) => {
+                let bytes = Zeroizing::new(bytes);
+                serde_json::from_slice(&bytes).map_err(|_| SecretError::Corrupt)
+            }
+            Err(e) if e.code() == ITEM_NOT_FOUND => Ok(BTreeMap::new()),
+            Err(e) => Err(SecretError::Keychain(e.to_string())),
+        }
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, Secret>) -> Result<(), SecretError> {
+        let result = if secrets.is_empty() {
+            match delete_generic_password(SERVICE, ACCOUNT) {
+                Err(e) if e.code() == ITEM_NOT_FOUND => Ok(()),
+                result => result,
+            }
+        } else {
+            let bytes = Zeroizing::new(serde_json::to_vec(secrets).expect("secrets serialize"));
+            set_generic_password(SERVICE, ACCOUNT, &bytes)
+        };
+        result.map_err(|e| SecretError::Keychain(e.to_string()))
+    }
+
+    /// Apply `change` to the stored secrets and write them back
+    fn update<T>(&self, change: impl FnOnce(&mut BTreeMap<String, Secret>) -> T) -> Result<T, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut secrets = self.load()?;
+        let result = change(&mut secrets);
+        self.save(&secrets)?;
+        Ok(result)
+    }
+}
+
+impl SecretStore for Keychain {
+    fn name(&self) -> &'static str {
+        "keychain"
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Secret>, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.remove(name))
+    }
+
+    fn set(&self, name: &str, value: &Secret) -> Result<(), SecretError> {
+        validate_name(name)?;
+        self.update(|secrets| {
+            secrets.insert(name.to_string(), value.clone());
+        })
+    }
+
+    fn delete(&self, name: &str) -> Result<bool, SecretError> {
+        self.update(|secrets| secrets.remove(name).is_some())
+    }
+
+    fn list(&self) -> Result<Vec<String>, SecretError> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.into_keys().collect())
+    }
+}
@@ -0,0 +1,159 @@
+//! Credentials for providers, kept out of `config.toml`
+//!
+//! Secrets are stored by name in a `SecretStore`: the macOS keychain where
+//! there is one, otherwise a file encrypted with a master key kept next to
+//! it. Values only ever travel as `Secret`, whose `Debug` output is
+//! redacted, so logging a request or a config never prints one. Clients can
+//! set, list and delete secrets but never read them back.
+
+mod file;
+#[cfg(target_os = "macos")]
+mod keychain;
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+pub use file::EncryptedFile;
+#[cfg(target_os = "macos")]
+pub use keychain::Keychain;
+
+/// Longest secret name accepted
+const MAX_NAME_LEN: usize = 64;
+
+/// A secret value, wiped from memory when dropped
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The value itself, for handing to a provider
+    // Nothing uses secrets yet; providers will
+    #[allow(dead_code)]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Where secrets are kept
+pub trait SecretStore: Send + Sync {
+    /// Short backend name, for logs
+    fn name(&self) -> &'static str;
+
+    /// The secret stored under `name`, if any
+    // Nothing uses secrets yet; providers will
+    #[allow(dead_code)]
+    fn get(&self, name: &str) -> Result<Option<Secret>, SecretError>;
+
+    /// Store `value` under `name`, replacing any previous value
+    fn set(&self, name: &str, value: &Secret) -> Result<(), SecretError>;
+
+    /// Remove the secret stored under `name`; false if there was none
+    fn delete(&self, name: &str) -> Result<bool, SecretError>;
+
+    /// Names of the stored secrets, sorted
+    fn list(&self) -> Result<Vec<String>, SecretError>;
+}
+
+/// Which `SecretStore` to use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    /// The keychain on macOS, the encrypted file elsewhere
+    #[default]
+    Auto,
+    /// `EncryptedFile` in the data directory
+    File,
+    /// The macOS keychain
+    Keychain,
+}
+
+/// Errors from a secret store
+#[derive(Debug, thiserror::Error)]
+pub enum SecretError {
+    #[error("invalid secret name {0:?} - use up to 64 letters, digits, '_', '-' or '.'")]
+    InvalidName(String),
+
+    #[error("failed to access secrets file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("secrets file is corrupt or was encrypted with a different key")]
+    Corrupt,
+
+    #[error("master key {0} is missing, the existing secrets can't be decrypted")]
+    KeyMissing(String),
+
+    #[cfg(target_os = "macos")]
+    #[error("keychain error: {0}")]
+    Keychain(String),
+
+    #[cfg(not(target_os = "macos"))]
+    #[error("the keychain is not available on this platform")]
+    Unsupported,
+}
+
+/// Check that `name` is usable as a secret name
+pub fn validate_name(name: &str) -> Result<(), SecretError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SecretError::InvalidName(name.to_string()))
+    }
+}
+
+/// Open the store for `backend`, keeping files in `data_dir`
+pub fn open(backend: SecretBackend, data_dir: &Path) -> Result<Box<dyn SecretStore>, SecretError> {
+    match backend {
+        #[cfg(target_os = "macos")]
+        SecretBackend::Auto | SecretBackend::Keychain => Ok(Box::new(Keychain::new())),
+        #[cfg(not(target_os = "macos"))]
+        SecretBackend::Auto => Ok(Box::new(EncryptedFile::open(data_dir)?)),
+        #[cfg(not(target_os = "macos"))]
+        SecretBackend::Keychain => Err(SecretError::Unsupported),
+        SecretBackend::File => Ok(Box::new(EncryptedFile::open(data_dir)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::new("sk-live-1234");
+        assert_eq!(format!("{:?}", secret), "Secret(<redacted>)");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"sk-live-1234\"");
+        assert_eq!(secret.expose(), "sk-live-1234");
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(validate_name("openai.api_key").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../key").is_err());
+        assert!(validate_name(&"a".repeat(65)).is_err());
+    }
+}
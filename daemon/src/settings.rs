@@ -0,0 +1,139 @@
+//! Runtime settings persisted in the data directory
+//!
+//! Unlike `Config`, these are changed at runtime (over IPC, e.g. during
+//! onboarding) and written back to `settings.json` so they survive
+//! restarts. Only what was changed at runtime is stored: bindings and modes
+//! left alone keep following `config.toml`, including edits made while the
+//! daemon was down.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigFile;
+use crate::hotkey::Bindings;
+use crate::lifecycle::QuietHours;
+use crate::state::EnabledModes;
+
+/// Settings changed at runtime and persisted across restarts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Modes the user has enabled, if changed from `[modes]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled_modes: Option<EnabledModes>,
+
+    /// Windows of local time during which the daemon is paused
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quiet_hours: Vec<QuietHours>,
+
+    /// Chords that trigger each mode, if changed from `[hotkeys]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<Bindings>,
+
+    /// Active profile from `config.toml`, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl Settings {
+    /// The modes enabled with `config` as the active config
+    pub fn enabled_modes(&self, config: &ConfigFile) -> EnabledModes {
+        self.enabled_modes.unwrap_or(config.modes)
+    }
+
+    /// The bindings in effect with `config` as the active config
+    pub fn bindings(&self, config: &ConfigFile) -> Bindings {
+        self.bindings.unwrap_or(config.hotkeys)
+    }
+
+    /// Load settings from `path`; a missing file yields no changes
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+
+        serde_json::from_slice(&bytes).with_context(|| format!("invalid settings file {}", path.display()))
+    }
+
+    /// Write settings to `path`, replacing the old file atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to replace {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    #[test]
+    fn test_missing_file_uses_defaults() {
+        let path = std::env::temp_dir().join("second-brain-settings-missing.json");
+        assert_eq!(Settings::load(&path).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!(
+            "second-brain-settings-{}.json",
+            std::process::id()
+        ));
+
+        let mut enabled_modes = EnabledModes::default();
+        enabled_modes.set(State::AgentActive, false);
+        let settings = Settings {
+            enabled_modes: Some(enabled_modes),
+            ..Settings::default()
+        };
+        settings.save(&path).unwrap();
+
+        let loaded = Settings::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_config_edits_apply_to_unchanged_settings() {
+        let path = std::env::temp_dir().join(format!(
+            "second-brain-settings-edited-{}.json",
+            std::process::id()
+        ));
+
+        // Quiet hours and modes are changed at runtime, bindings are not
+        let (old, _) = ConfigFile::parse("").unwrap();
+        let mut enabled_modes = EnabledModes::default();
+        enabled_modes.set(State::DictationActive, false);
+        let settings = Settings {
+            enabled_modes: Some(enabled_modes),
+            quiet_hours: vec![serde_json::from_str(r#"{"start":"22:00","end":"07:00"}"#).unwrap()],
+            ..Settings::default()
+        };
+        settings.save(&path).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("bindings"), "unchanged bindings were saved: {}", saved);
+
+        // config.toml is edited while the daemon is down
+        let (new, _) = ConfigFile::parse("[hotkeys]\ndictation = [\"f18\"]\n\n[modes]\ndictation = true\nagent = false\n").unwrap();
+        assert_ne!(new.hotkeys, old.hotkeys);
+        let loaded = Settings::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.bindings(&new), new.hotkeys);
+        assert_eq!(loaded.enabled_modes(&new), enabled_modes);
+        assert_eq!(loaded.quiet_hours, settings.quiet_hours);
+    }
+}
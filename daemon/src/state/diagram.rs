@@ -0,0 +1,110 @@
+//! Diagram rendering for the state machine
+//!
+//! Renders a `MachineDescription` as Mermaid or Graphviz DOT source,
+//! highlighting the current state when it is known.
+
+use serde::{Deserialize, Serialize};
+
+use super::transitions::MachineDescription;
+
+/// Output format for state machine diagrams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramFormat {
+    /// Mermaid `stateDiagram-v2`
+    Mermaid,
+    /// Graphviz DOT
+    Dot,
+}
+
+impl std::str::FromStr for DiagramFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mermaid" => Ok(Self::Mermaid),
+            "dot" | "graphviz" => Ok(Self::Dot),
+            other => Err(format!("unknown diagram format '{}' (expected mermaid or dot)", other)),
+        }
+    }
+}
+
+/// Fill colour used to highlight the current state
+const HIGHLIGHT: &str = "#f9d949";
+
+/// Render the description in the requested format
+pub fn render(description: &MachineDescription, format: DiagramFormat) -> String {
+    match format {
+        DiagramFormat::Mermaid => render_mermaid(description),
+        DiagramFormat::Dot => render_dot(description),
+    }
+}
+
+fn render_mermaid(description: &MachineDescription) -> String {
+    let mut out = String::from("stateDiagram-v2\n");
+    out.push_str(&format!("    [*] --> {}\n", description.initial));
+
+    for t in &description.transitions {
+        out.push_str(&format!("    {} --> {}: {}\n", t.from, t.to, t.label()));
+    }
+
+    if let Some(current) = description.current {
+        out.push_str(&format!("    classDef current fill:{},stroke-width:2px\n", HIGHLIGHT));
+        out.push_str(&format!("    class {} current\n", current));
+    }
+
+    out
+}
+
+fn render_dot(description: &MachineDescription) -> String {
+    let mut out = String::from("digraph second_brain {\n");
+    out.push_str("    rankdir=LR;\n");
+    out.push_str("    node [shape=box, style=rounded];\n");
+
+    for state in &description.states {
+        if Some(*state) == description.current {
+            out.push_str(&format!(
+                "    {} [style=\"rounded,filled\", fillcolor=\"{}\"];\n",
+                state, HIGHLIGHT
+            ));
+        } else {
+            out.push_str(&format!("    {};\n", state));
+        }
+    }
+
+    for t in &description.transitions {
+        out.push_str(&format!("    {} -> {} [label=\"{}\"];\n", t.from, t.to, t.label()));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{EnabledModes, State, TransitionTable};
+
+    #[test]
+    fn test_mermaid_highlights_current() {
+        let description = TransitionTable::default().describe(&EnabledModes::all(), Some(State::AgentActive));
+        let mermaid = render(&description, DiagramFormat::Mermaid);
+        assert!(mermaid.starts_with("stateDiagram-v2\n"));
+        assert!(mermaid.contains("Idle --> AgentActive: press Ctrl+Cmd"));
+        assert!(mermaid.contains("class AgentActive current"));
+    }
+
+    #[test]
+    fn test_dot_without_current() {
+        let description = TransitionTable::default().describe(&EnabledModes::all(), None);
+        let dot = render(&description, DiagramFormat::Dot);
+        assert!(dot.contains("DictationActive -> Idle [label=\"release Ctrl\"];"));
+        assert!(!dot.contains("filled"));
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!("dot".parse::<DiagramFormat>(), Ok(DiagramFormat::Dot));
+        assert!("svg".parse::<DiagramFormat>().is_err());
+    }
+}
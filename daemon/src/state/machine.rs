@@ -5,16 +5,23 @@
 
 use std::time::Instant;
 
-use tokio::sync::{broadcast, mpsc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tracing::{debug, info, warn};
 
 use crate::events::StateEvent;
-use crate::hotkey::{HotkeyEvent, ModifierState};
+use crate::hotkey::{Bindings, Chord, HotkeyEvent, HotkeyRecorder, ModifierState};
+
+use super::modes::EnabledModes;
+use super::store::{StateStore, TransitionRecord};
+use super::transitions::TransitionTable;
 
 /// The four possible states of the daemon
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum State {
     /// No active mode, waiting for hotkey
+    #[default]
     Idle,
     /// Dictation mode: Control is held
     DictationActive,
@@ -24,10 +31,14 @@ pub enum State {
     AgentActive,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        Self::Idle
-    }
+impl State {
+    /// All states, in display order
+    pub const ALL: [State; 4] = [
+        State::Idle,
+        State::DictationActive,
+        State::IntelligentActive,
+        State::AgentActive,
+    ];
 }
 
 impl std::fmt::Display for State {
@@ -41,16 +52,64 @@ impl std::fmt::Display for State {
     }
 }
 
+/// Commands sent to a running state machine by other components
+#[derive(Debug)]
+pub enum Command {
+    /// Replace the set of modes the machine may enter
+    SetEnabledModes(EnabledModes),
+    /// Replace the chords that trigger each mode
+    SetBindings(Bindings),
+    /// Disarm (`true`) or re-arm (`false`) the hotkeys
+    SetPaused(bool),
+    /// Enter a state directly, e.g. when a client selects a mode
+    ///
+    /// Ignored while paused or if the target mode is disabled.
+    EnterState(State),
+    /// Report the next chord pressed instead of acting on it
+    ///
+    /// The chord is every key held between the first press and the
+    /// release of all keys. Dropping the receiver abandons the capture at
+    /// the next key event.
+    CaptureChord(oneshot::Sender<Chord>),
+    /// Stop capturing and act on chords again
+    CancelCapture,
+    /// End any active mode with its exit event and stop running
+    Stop,
+    /// Answer right away, showing that commands are being processed
+    Ping(oneshot::Sender<()>),
+}
+
+/// A chord capture in progress
+struct Capture {
+    /// Every key held since the capture started
+    chord: Chord,
+    reply: oneshot::Sender<Chord>,
+}
+
 /// The state machine that manages mode transitions
 pub struct StateMachine {
     /// Current state
     state: State,
     /// Previous modifier state (for edge detection)
     prev_modifiers: ModifierState,
+    /// Chords that trigger each mode
+    bindings: Bindings,
+    /// Transitions evaluated on every modifier change, built from `bindings`
+    transitions: TransitionTable,
+    /// Modes the machine may enter
+    enabled_modes: EnabledModes,
+    /// While paused, hotkeys are ignored
+    paused: bool,
     /// Time when current non-Idle state was entered
     state_entered_at: Option<Instant>,
     /// Channel for emitting state events
     event_tx: broadcast::Sender<StateEvent>,
+    /// Optional recorder for the incoming hotkey event stream
+    recorder: Option<HotkeyRecorder>,
+    /// Where the current state is published for other components
+    store: StateStore,
+    /// While capturing, key presses are reported instead of acted on
+    capture: Option<Capture>,
 }
 
 impl StateMachine {
@@ -59,27 +118,113 @@ impl StateMachine {
         Self {
             state: State::Idle,
             prev_modifiers: ModifierState::default(),
+            bindings: Bindings::default(),
+            transitions: TransitionTable::default(),
+            enabled_modes: EnabledModes::default(),
+            paused: false,
             state_entered_at: None,
             event_tx,
+            recorder: None,
+            store: StateStore::new(),
+            capture: None,
         }
     }
 
-    /// Get the current state
-    pub fn state(&self) -> State {
-        self.state
+    /// Get the store this machine publishes its state to
+    pub fn store(&self) -> StateStore {
+        self.store.clone()
     }
 
-    /// Run the state machine, processing hotkey events
-    pub async fn run(&mut self, mut hotkey_rx: mpsc::Receiver<HotkeyEvent>) {
+    /// Record every hotkey event received by `run` with the given recorder
+    pub fn set_recorder(&mut self, recorder: HotkeyRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Replace the chords that trigger each mode
+    ///
+    /// A held mode ends, since its old chord may never be released in a
+    /// way the new transitions recognize. Agent mode stays on and is left
+    /// with the new toggle.
+    /// Shortcut-use counts of rebound modes start over.
+    pub fn set_bindings(&mut self, bindings: Bindings, now: Instant) {
+        let old = std::mem::replace(&mut self.bindings, bindings);
+        self.transitions = TransitionTable::new(&bindings);
+        self.store.update(|s| {
+            s.bindings = bindings;
+            s.shortcut_use.rebind(&old, &bindings);
+        });
+        info!(?bindings, "bindings updated");
+
+        if matches!(self.state, State::DictationActive | State::IntelligentActive) {
+            self.transition_to(State::Idle, now);
+        }
+    }
+
+    /// Replace the set of enabled modes
+    ///
+    /// If the active mode gets disabled the machine returns to Idle.
+    pub fn set_enabled_modes(&mut self, enabled_modes: EnabledModes, now: Instant) {
+        self.enabled_modes = enabled_modes;
+        self.store.update(|s| s.enabled_modes = enabled_modes);
+        info!(?enabled_modes, "enabled modes updated");
+
+        if !enabled_modes.allows(self.state) {
+            self.transition_to(State::Idle, now);
+        }
+    }
+
+    /// Disarm or re-arm the hotkeys
+    ///
+    /// Pausing ends any active mode, so capture stops immediately.
+    pub fn set_paused(&mut self, paused: bool, now: Instant) {
+        if self.paused == paused {
+            return;
+        }
+
+        self.paused = paused;
+        self.store.update(|s| s.paused = paused);
+        info!(paused, "hotkeys {}", if paused { "disarmed" } else { "re-armed" });
+
+        if paused && self.state != State::Idle {
+            self.transition_to(State::Idle, now);
+        }
+    }
+
+    /// Run the state machine, processing hotkey events and commands
+    ///
+    /// Runs until `Command::Stop` or the hotkey channel closes, ending any
+    /// active mode on the way out.
+    pub async fn run(
+        &mut self,
+        mut hotkey_rx: mpsc::Receiver<HotkeyEvent>,
+        mut command_rx: mpsc::Receiver<Command>,
+    ) {
         info!("state machine started in Idle state");
 
-        while let Some(event) = hotkey_rx.recv().await {
-            match event {
-                HotkeyEvent::ModifierChanged(modifiers) => {
-                    self.handle_modifier_change(modifiers);
+        loop {
+            tokio::select! {
+                event = hotkey_rx.recv() => {
+                    let Some(event) = event else {
+                        self.end_active(Instant::now());
+                        break;
+                    };
+                    let now = Instant::now();
+
+                    if let Some(recorder) = self.recorder.as_mut() {
+                        if let Err(e) = recorder.record(&event, now) {
+                            warn!(?e, "failed to record hotkey event, recording stopped");
+                            self.recorder = None;
+                        }
+                    }
+
+                    self.handle_event(event, now);
                 }
-                HotkeyEvent::TapDisabled => {
-                    warn!("hotkey tap disabled, events may be missed");
+                Some(command) = command_rx.recv() => {
+                    let stop = matches!(command, Command::Stop);
+                    self.handle_command(command, Instant::now());
+                    if stop {
+                        break;
+                    }
                 }
             }
         }
@@ -87,93 +232,170 @@ impl StateMachine {
         info!("state machine stopped");
     }
 
-    /// Handle a modifier state change
-    fn handle_modifier_change(&mut self, modifiers: ModifierState) {
-        let old_state = self.state;
-        let new_state = self.compute_next_state(&modifiers);
-
-        if new_state != old_state {
-            self.transition_to(new_state);
+    /// Apply a command from another component
+    fn handle_command(&mut self, command: Command, now: Instant) {
+        debug!(?command, "state machine command");
+        match command {
+            Command::SetEnabledModes(enabled_modes) => {
+                self.set_enabled_modes(enabled_modes, now);
+            }
+            Command::SetBindings(bindings) => {
+                self.set_bindings(bindings, now);
+            }
+            Command::SetPaused(paused) => {
+                self.set_paused(paused, now);
+            }
+            Command::CaptureChord(reply) => {
+                info!("capturing next chord");
+                // A held mode would never see its release while capturing
+                if matches!(self.state, State::DictationActive | State::IntelligentActive) {
+                    self.transition_to(State::Idle, now);
+                }
+                self.capture = Some(Capture {
+                    chord: Chord::default(),
+                    reply,
+                });
+                self.store.update(|s| s.capturing = true);
+            }
+            Command::CancelCapture => {
+                if self.capture.take().is_some() {
+                    info!("chord capture cancelled");
+                    self.store.update(|s| s.capturing = false);
+                }
+            }
+            Command::EnterState(state) => {
+                if self.paused || !self.enabled_modes.allows(state) {
+                    warn!(%state, paused = self.paused, "ignoring request to enter unavailable state");
+                } else if state != self.state {
+                    self.transition_to(state, now);
+                }
+            }
+            Command::Stop => self.end_active(now),
+            Command::Ping(reply) => {
+                let _ = reply.send(());
+            }
         }
+    }
 
-        self.prev_modifiers = modifiers;
+    /// Abandon any capture and return to Idle, emitting the exit event of
+    /// the mode that was active
+    fn end_active(&mut self, now: Instant) {
+        if self.capture.take().is_some() {
+            self.store.update(|s| s.capturing = false);
+        }
+        if self.state != State::Idle {
+            self.transition_to(State::Idle, now);
+        }
     }
 
-    /// Compute the next state based on current state and modifier keys
-    fn compute_next_state(&self, modifiers: &ModifierState) -> State {
-        match self.state {
-            State::Idle => self.compute_from_idle(modifiers),
-            State::DictationActive => self.compute_from_dictation(modifiers),
-            State::IntelligentActive => self.compute_from_intelligent(modifiers),
-            State::AgentActive => self.compute_from_agent(modifiers),
+    /// Handle a single hotkey event observed at `now`
+    ///
+    /// Durations in emitted events are measured against `now`, so feeding
+    /// recorded timestamps here reproduces a session deterministically.
+    pub fn handle_event(&mut self, event: HotkeyEvent, now: Instant) {
+        match event {
+            HotkeyEvent::ModifierChanged(modifiers) => {
+                self.handle_modifier_change(modifiers, now);
+            }
+            HotkeyEvent::TapDisabled => self.handle_tap_disabled(now),
+            HotkeyEvent::TapRecovered => {
+                info!("hotkey tap recovered");
+                self.store.update(|s| s.tap_recovered_count += 1);
+            }
+            HotkeyEvent::KeyPressed => self.handle_key_pressed(),
         }
     }
 
-    /// Compute next state when currently Idle
-    fn compute_from_idle(&self, modifiers: &ModifierState) -> State {
-        // Priority order: Agent > Intelligent > Dictation
-        if self.is_rising_edge_control_command(modifiers) {
-            State::AgentActive
-        } else if modifiers.is_control_option() {
-            State::IntelligentActive
-        } else if modifiers.is_control_only() {
-            State::DictationActive
-        } else {
-            State::Idle
+    /// Handle the hotkey tap being disabled
+    ///
+    /// Releases may be missed while the tap is down, so held modes end now
+    /// rather than running until the next key event. Agent mode is a toggle
+    /// and stays on.
+    fn handle_tap_disabled(&mut self, now: Instant) {
+        warn!(state = %self.state, "hotkey tap disabled, events may be missed");
+        self.store.update(|s| s.tap_disabled_count += 1);
+
+        self.prev_modifiers = ModifierState::default();
+        if matches!(self.state, State::DictationActive | State::IntelligentActive) {
+            self.transition_to(State::Idle, now);
         }
     }
 
-    /// Compute next state when in DictationActive
-    fn compute_from_dictation(&self, modifiers: &ModifierState) -> State {
-        // If Option is added, upgrade to Intelligent
-        if modifiers.is_control_option() {
-            State::IntelligentActive
+    /// Count bindings held while a regular key was pressed
+    ///
+    /// Counts even while paused: it says how the chord is used, not
+    /// whether a mode started.
+    fn handle_key_pressed(&mut self) {
+        let held = [
+            (State::DictationActive, self.bindings.dictation),
+            (State::IntelligentActive, self.bindings.intelligent),
+            (State::AgentActive, self.bindings.agent),
+        ]
+        .into_iter()
+        .filter(|(_, chord)| self.prev_modifiers.holds(*chord));
+
+        for (state, chord) in held {
+            debug!(%state, %chord, "binding held with a regular key");
+            self.store.update(|s| s.shortcut_use.record(state));
         }
-        // If Control is released, go back to Idle
-        else if !modifiers.control {
-            State::Idle
+    }
+
+    /// Handle a modifier state change
+    fn handle_modifier_change(&mut self, modifiers: ModifierState, now: Instant) {
+        if self.capture_key_change(modifiers) {
+            self.prev_modifiers = modifiers;
+            return;
         }
-        // Stay in Dictation
-        else {
-            State::DictationActive
+
+        if self.paused {
+            // Keep tracking keys so edges are correct once re-armed
+            self.prev_modifiers = modifiers;
+            return;
         }
-    }
 
-    /// Compute next state when in IntelligentActive
-    fn compute_from_intelligent(&self, modifiers: &ModifierState) -> State {
-        // If either Control or Option is released, go to Idle
-        if !modifiers.control || !modifiers.option {
-            State::Idle
-        } else {
-            State::IntelligentActive
+        let old_state = self.state;
+        let new_state = self
+            .transitions
+            .next_state(old_state, &self.prev_modifiers, &modifiers, &self.enabled_modes);
+
+        if new_state != old_state {
+            self.transition_to(new_state, now);
         }
+
+        self.prev_modifiers = modifiers;
     }
 
-    /// Compute next state when in AgentActive
-    fn compute_from_agent(&self, modifiers: &ModifierState) -> State {
-        // Only Control+Command toggle can exit Agent mode
-        if self.is_rising_edge_control_command(modifiers) {
-            State::Idle
-        } else {
-            // All other key combinations are ignored
-            State::AgentActive
+    /// Feed a key change to a capture in progress
+    ///
+    /// Returns whether the change was consumed by the capture.
+    fn capture_key_change(&mut self, modifiers: ModifierState) -> bool {
+        let Some(capture) = self.capture.as_mut() else {
+            return false;
+        };
+        if capture.reply.is_closed() {
+            debug!("chord capture abandoned");
+            self.capture = None;
+            self.store.update(|s| s.capturing = false);
+            return false;
         }
-    }
 
-    /// Detect rising edge of Control+Command (just pressed together)
-    fn is_rising_edge_control_command(&self, modifiers: &ModifierState) -> bool {
-        // Both Control and Command are now pressed
-        modifiers.is_control_command()
-            // And at least one of them was not pressed before
-            && (!self.prev_modifiers.control || !self.prev_modifiers.command)
+        let held = modifiers.chord();
+        capture.chord = capture.chord.union(held);
+        if held.is_empty() && !capture.chord.is_empty() {
+            let capture = self.capture.take().expect("capture in progress");
+            info!(chord = %capture.chord, "chord captured");
+            let _ = capture.reply.send(capture.chord);
+            self.store.update(|s| s.capturing = false);
+        }
+        true
     }
 
     /// Perform a state transition
-    fn transition_to(&mut self, new_state: State) {
+    fn transition_to(&mut self, new_state: State, now: Instant) {
         let old_state = self.state;
         let duration_ms = self
             .state_entered_at
-            .map(|t| t.elapsed().as_millis() as u64)
+            .map(|t| now.saturating_duration_since(t).as_millis() as u64)
             .unwrap_or(0);
 
         info!(
@@ -188,8 +410,12 @@ impl StateMachine {
 
         // Update state
         self.state = new_state;
+        self.store.update(|s| {
+            s.state = new_state;
+            s.record_transition(TransitionRecord::now(old_state, new_state, duration_ms));
+        });
         self.state_entered_at = if new_state != State::Idle {
-            Some(Instant::now())
+            Some(now)
         } else {
             None
         };
@@ -229,15 +455,18 @@ impl StateMachine {
 mod tests {
     use super::*;
 
+    /// A machine with every mode enabled
     fn create_state_machine() -> (StateMachine, broadcast::Receiver<StateEvent>) {
         let (tx, rx) = broadcast::channel(16);
-        (StateMachine::new(tx), rx)
+        let mut sm = StateMachine::new(tx);
+        sm.set_enabled_modes(EnabledModes::all(), Instant::now());
+        (sm, rx)
     }
 
     #[test]
     fn test_initial_state() {
         let (sm, _) = create_state_machine();
-        assert_eq!(sm.state(), State::Idle);
+        assert_eq!(sm.state, State::Idle);
     }
 
     #[test]
@@ -248,10 +477,11 @@ mod tests {
             control: true,
             option: false,
             command: false,
+            ..Default::default()
         };
         
-        sm.handle_modifier_change(modifiers);
-        assert_eq!(sm.state(), State::DictationActive);
+        sm.handle_modifier_change(modifiers, Instant::now());
+        assert_eq!(sm.state, State::DictationActive);
     }
 
     #[test]
@@ -262,10 +492,11 @@ mod tests {
             control: true,
             option: true,
             command: false,
+            ..Default::default()
         };
         
-        sm.handle_modifier_change(modifiers);
-        assert_eq!(sm.state(), State::IntelligentActive);
+        sm.handle_modifier_change(modifiers, Instant::now());
+        assert_eq!(sm.state, State::IntelligentActive);
     }
 
     #[test]
@@ -276,10 +507,11 @@ mod tests {
             control: true,
             option: false,
             command: true,
+            ..Default::default()
         };
         
-        sm.handle_modifier_change(modifiers);
-        assert_eq!(sm.state(), State::AgentActive);
+        sm.handle_modifier_change(modifiers, Instant::now());
+        assert_eq!(sm.state, State::AgentActive);
     }
 
     #[test]
@@ -291,16 +523,18 @@ mod tests {
             control: true,
             option: false,
             command: false,
-        });
-        assert_eq!(sm.state(), State::DictationActive);
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::DictationActive);
         
         // Add Option -> upgrade to Intelligent
         sm.handle_modifier_change(ModifierState {
             control: true,
             option: true,
             command: false,
-        });
-        assert_eq!(sm.state(), State::IntelligentActive);
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::IntelligentActive);
     }
 
     #[test]
@@ -312,24 +546,27 @@ mod tests {
             control: true,
             option: false,
             command: true,
-        });
-        assert_eq!(sm.state(), State::AgentActive);
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::AgentActive);
         
         // Release Command, keep Control -> still Agent
         sm.handle_modifier_change(ModifierState {
             control: true,
             option: false,
             command: false,
-        });
-        assert_eq!(sm.state(), State::AgentActive);
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::AgentActive);
         
         // Press Control+Option -> still Agent
         sm.handle_modifier_change(ModifierState {
             control: true,
             option: true,
             command: false,
-        });
-        assert_eq!(sm.state(), State::AgentActive);
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::AgentActive);
     }
 
     #[test]
@@ -341,19 +578,296 @@ mod tests {
             control: true,
             option: false,
             command: true,
-        });
-        assert_eq!(sm.state(), State::AgentActive);
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::AgentActive);
         
         // Release both
-        sm.handle_modifier_change(ModifierState::default());
-        assert_eq!(sm.state(), State::AgentActive); // Still in Agent
+        sm.handle_modifier_change(ModifierState::default(), Instant::now());
+        assert_eq!(sm.state, State::AgentActive); // Still in Agent
         
         // Press Control+Command again -> toggle off
         sm.handle_modifier_change(ModifierState {
             control: true,
             option: false,
             command: true,
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::Idle);
+    }
+
+    #[test]
+    fn test_disabled_agent_is_not_entered() {
+        let (mut sm, _) = create_state_machine();
+        let mut modes = EnabledModes::default();
+        modes.set(State::AgentActive, false);
+        sm.set_enabled_modes(modes, Instant::now());
+
+        sm.handle_modifier_change(ModifierState {
+            control: true,
+            option: false,
+            command: true,
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::Idle);
+    }
+
+    #[test]
+    fn test_disabling_active_mode_returns_to_idle() {
+        let (mut sm, mut rx) = create_state_machine();
+
+        sm.handle_modifier_change(ModifierState {
+            control: true,
+            option: false,
+            command: false,
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::DictationActive);
+
+        let mut modes = EnabledModes::default();
+        modes.set(State::DictationActive, false);
+        sm.handle_command(Command::SetEnabledModes(modes), Instant::now());
+
+        assert_eq!(sm.state, State::Idle);
+        assert_eq!(rx.try_recv().unwrap(), StateEvent::DictationStarted);
+        assert!(matches!(rx.try_recv().unwrap(), StateEvent::DictationComplete { .. }));
+    }
+
+    #[test]
+    fn test_pause_ends_mode_and_ignores_hotkeys() {
+        let (mut sm, _) = create_state_machine();
+
+        sm.handle_modifier_change(ModifierState {
+            control: true,
+            option: false,
+            command: true,
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::AgentActive);
+
+        sm.handle_command(Command::SetPaused(true), Instant::now());
+        assert_eq!(sm.state, State::Idle);
+
+        sm.handle_modifier_change(ModifierState {
+            control: true,
+            option: false,
+            command: false,
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::Idle);
+
+        // Once re-armed, the next change is evaluated normally
+        sm.handle_command(Command::SetPaused(false), Instant::now());
+        sm.handle_modifier_change(ModifierState {
+            control: true,
+            option: true,
+            command: false,
+            ..Default::default()
+        }, Instant::now());
+        assert_eq!(sm.state, State::IntelligentActive);
+    }
+
+    #[test]
+    fn test_stop_ends_active_mode() {
+        let (mut sm, mut rx) = create_state_machine();
+        let (reply_tx, mut reply_rx) = oneshot::channel();
+
+        sm.handle_command(Command::EnterState(State::AgentActive), Instant::now());
+        sm.capture = Some(Capture {
+            chord: Chord::default(),
+            reply: reply_tx,
         });
-        assert_eq!(sm.state(), State::Idle);
+        sm.handle_command(Command::Stop, Instant::now());
+
+        assert_eq!(sm.state, State::Idle);
+        assert!(reply_rx.try_recv().is_err());
+        assert!(matches!(rx.try_recv().unwrap(), StateEvent::AgentModeEntered));
+        assert!(matches!(rx.try_recv().unwrap(), StateEvent::AgentModeExited { .. }));
+    }
+
+    #[test]
+    fn test_store_tracks_state() {
+        let (mut sm, _) = create_state_machine();
+        let store = sm.store();
+        let version = store.snapshot().version;
+
+        sm.handle_modifier_change(ModifierState {
+            control: true,
+            option: false,
+            command: false,
+            ..Default::default()
+        }, Instant::now());
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.state, State::DictationActive);
+        assert_eq!(snapshot.version, version + 1);
+
+        sm.handle_command(Command::SetPaused(true), Instant::now());
+        let snapshot = store.snapshot();
+        assert_eq!(snapshot.state, State::Idle);
+        assert!(snapshot.paused);
+        let transitions: Vec<_> = snapshot.recent_transitions.iter().map(|t| (t.from, t.to)).collect();
+        assert_eq!(
+            transitions,
+            [(State::Idle, State::DictationActive), (State::DictationActive, State::Idle)]
+        );
+    }
+
+    #[test]
+    fn test_tap_disabled_ends_held_modes() {
+        let (mut sm, mut rx) = create_state_machine();
+        let store = sm.store();
+        let now = Instant::now();
+
+        sm.handle_modifier_change(
+            ModifierState {
+                control: true,
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(rx.try_recv().unwrap(), StateEvent::DictationStarted);
+
+        sm.handle_event(HotkeyEvent::TapDisabled, now);
+        assert_eq!(sm.state, State::Idle);
+        assert!(matches!(rx.try_recv().unwrap(), StateEvent::DictationComplete { .. }));
+
+        sm.handle_event(HotkeyEvent::TapRecovered, now);
+        let snapshot = store.snapshot();
+        assert_eq!((snapshot.tap_disabled_count, snapshot.tap_recovered_count), (1, 1));
+    }
+
+    #[test]
+    fn test_tap_disabled_keeps_agent_mode() {
+        let (mut sm, _) = create_state_machine();
+        let now = Instant::now();
+        sm.handle_modifier_change(
+            ModifierState {
+                control: true,
+                command: true,
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(sm.state, State::AgentActive);
+
+        sm.handle_event(HotkeyEvent::TapDisabled, now);
+        assert_eq!(sm.state, State::AgentActive);
+    }
+
+    #[test]
+    fn test_rebinding_ends_held_mode() {
+        let (mut sm, _) = create_state_machine();
+        let now = Instant::now();
+        let control = ModifierState {
+            control: true,
+            ..Default::default()
+        };
+        sm.handle_modifier_change(control, now);
+        assert_eq!(sm.state, State::DictationActive);
+
+        let bindings: Bindings = serde_json::from_str(r#"{"dictation":["f18"]}"#).unwrap();
+        sm.set_bindings(bindings, now);
+        assert_eq!(sm.state, State::Idle);
+        assert_eq!(sm.store().snapshot().bindings, bindings);
+
+        // Control alone no longer starts dictation
+        sm.handle_modifier_change(ModifierState::default(), now);
+        sm.handle_modifier_change(control, now);
+        assert_eq!(sm.state, State::Idle);
+    }
+
+    #[test]
+    fn test_capture_reports_chord_without_transitions() {
+        let (mut sm, _) = create_state_machine();
+        let now = Instant::now();
+        let (reply_tx, mut reply_rx) = oneshot::channel();
+        sm.handle_command(Command::CaptureChord(reply_tx), now);
+        assert!(sm.store().snapshot().capturing);
+
+        let control: ModifierState = serde_json::from_str(r#"{"control":true,"left_control":true}"#).unwrap();
+        let modifiers: ModifierState = serde_json::from_str(
+            r#"{"control":true,"left_control":true,"command":true,"right_command":true}"#,
+        )
+        .unwrap();
+        sm.handle_modifier_change(control, now);
+        sm.handle_modifier_change(modifiers, now);
+        assert_eq!(sm.state, State::Idle);
+        assert!(reply_rx.try_recv().is_err());
+
+        sm.handle_modifier_change(ModifierState::default(), now);
+        assert_eq!(reply_rx.try_recv().unwrap().to_string(), "LCtrl+RCmd");
+        assert!(!sm.store().snapshot().capturing);
+
+        // Capture is over: the chord acts as a trigger again
+        sm.handle_modifier_change(modifiers, now);
+        assert_eq!(sm.state, State::AgentActive);
+    }
+
+    #[test]
+    fn test_abandoned_capture_is_dropped() {
+        let (mut sm, _) = create_state_machine();
+        let now = Instant::now();
+        let (reply_tx, reply_rx) = oneshot::channel();
+        sm.handle_command(Command::CaptureChord(reply_tx), now);
+        drop(reply_rx);
+
+        sm.handle_modifier_change(
+            ModifierState {
+                control: true,
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(sm.state, State::DictationActive);
+    }
+
+    #[test]
+    fn test_key_press_counts_held_bindings() {
+        let (mut sm, _) = create_state_machine();
+        let now = Instant::now();
+
+        // No binding held: nothing to count
+        sm.handle_event(HotkeyEvent::KeyPressed, now);
+
+        // Control+Option holds both the Dictation and Intelligent chords
+        sm.handle_modifier_change(
+            ModifierState {
+                control: true,
+                option: true,
+                ..Default::default()
+            },
+            now,
+        );
+        sm.handle_event(HotkeyEvent::KeyPressed, now);
+
+        let usage = sm.store().snapshot().shortcut_use;
+        assert_eq!(usage.count(State::DictationActive), 1);
+        assert_eq!(usage.count(State::IntelligentActive), 1);
+        assert_eq!(usage.count(State::AgentActive), 0);
+
+        // Rebinding Dictation forgets its count only
+        let bindings = Bindings {
+            dictation: Chord::CONTROL_COMMAND,
+            ..Bindings::default()
+        };
+        sm.set_bindings(bindings, now);
+        let usage = sm.store().snapshot().shortcut_use;
+        assert_eq!(usage.count(State::DictationActive), 0);
+        assert_eq!(usage.count(State::IntelligentActive), 1);
+    }
+
+    #[test]
+    fn test_set_state_respects_enabled_modes() {
+        let (mut sm, _) = create_state_machine();
+
+        let mut modes = EnabledModes::default();
+        modes.set(State::AgentActive, false);
+        sm.handle_command(Command::SetEnabledModes(modes), Instant::now());
+
+        sm.handle_command(Command::EnterState(State::AgentActive), Instant::now());
+        assert_eq!(sm.state, State::Idle);
+
+        sm.handle_command(Command::EnterState(State::DictationActive), Instant::now());
+        assert_eq!(sm.state, State::DictationActive);
     }
 }
@@ -5,7 +5,24 @@
 //! - DictationActive: Momentary, while Control is held
 //! - IntelligentActive: Momentary, while Control+Option are held
 //! - AgentActive: Toggle, persists until toggled off
+//!
+//! Each mode can be disabled at runtime via `EnabledModes`.
+//! Transitions are described as data in a `TransitionTable`, which can be
+//! rendered as a Mermaid or Graphviz diagram. Recorded hotkey sessions can
+//! be replayed deterministically via `replay`. The machine publishes its
+//! state to a `StateStore`, which every other component reads.
 
+mod diagram;
 mod machine;
+mod modes;
+mod replay;
+mod store;
+mod transitions;
+mod usage;
 
-pub use machine::{State, StateMachine};
+pub use diagram::{render as render_diagram, DiagramFormat};
+pub use machine::{Command, State, StateMachine};
+pub use modes::EnabledModes;
+pub use replay::replay;
+pub use store::{Snapshot, StateStore, TransitionRecord};
+pub use transitions::{MachineDescription, TransitionTable};
@@ -0,0 +1,92 @@
+//! Set of modes the state machine is allowed to enter
+
+use serde::{Deserialize, Serialize};
+
+use super::machine::State;
+
+/// Which modes are enabled
+///
+/// Idle is always allowed. Transitions into a disabled mode never fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnabledModes {
+    pub dictation: bool,
+    pub intelligent: bool,
+    pub agent: bool,
+}
+
+impl EnabledModes {
+    /// Every mode enabled
+    pub fn all() -> Self {
+        Self {
+            dictation: true,
+            intelligent: true,
+            agent: true,
+        }
+    }
+
+    /// Check whether the machine may enter `state`
+    pub fn allows(&self, state: State) -> bool {
+        match state {
+            State::Idle => true,
+            State::DictationActive => self.dictation,
+            State::IntelligentActive => self.intelligent,
+            State::AgentActive => self.agent,
+        }
+    }
+
+    /// Enable or disable the mode entered via `state`
+    ///
+    /// Returns `false` for Idle, which cannot be disabled.
+    pub fn set(&mut self, state: State, enabled: bool) -> bool {
+        let flag = match state {
+            State::Idle => return false,
+            State::DictationActive => &mut self.dictation,
+            State::IntelligentActive => &mut self.intelligent,
+            State::AgentActive => &mut self.agent,
+        };
+        *flag = enabled;
+        true
+    }
+
+    /// The enabled mode states, in display order
+    pub fn states(&self) -> Vec<State> {
+        State::ALL
+            .into_iter()
+            .filter(|s| *s != State::Idle && self.allows(*s))
+            .collect()
+    }
+}
+
+impl Default for EnabledModes {
+    /// Dictation and Intelligent, until the user picks a selection during
+    /// onboarding; Agent has to be opted into
+    fn default() -> Self {
+        Self {
+            dictation: true,
+            intelligent: true,
+            agent: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_cannot_be_disabled() {
+        let mut modes = EnabledModes::default();
+        assert!(!modes.set(State::Idle, false));
+        assert!(modes.allows(State::Idle));
+    }
+
+    #[test]
+    fn test_states_lists_enabled_modes() {
+        assert_eq!(EnabledModes::default().states(), [State::DictationActive, State::IntelligentActive]);
+
+        let mut modes = EnabledModes::all();
+        modes.set(State::IntelligentActive, false);
+        assert_eq!(modes.states(), [State::DictationActive, State::AgentActive]);
+    }
+}
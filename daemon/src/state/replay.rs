@@ -0,0 +1,107 @@
+//! Deterministic replay of recorded hotkey sessions
+//!
+//! Feeds a recording into a fresh `StateMachine` with every mode enabled,
+//! using the recorded timestamps as the clock, and collects the
+//! `StateEvent`s it emits.
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+use crate::events::StateEvent;
+use crate::hotkey::RecordedEvent;
+
+use super::machine::StateMachine;
+use super::modes::EnabledModes;
+
+/// Room for the state events of one hotkey event: the machine makes at
+/// most one transition per hotkey event, emitting an exit and an entry
+/// event
+const EVENTS_PER_STEP: usize = 2;
+
+/// Errors replaying a recording
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("replay missed {skipped} state events after line {line}")]
+    Lagged { line: usize, skipped: u64 },
+}
+
+/// Replay a recording and return the resulting state events in order
+pub fn replay(recording: &[RecordedEvent]) -> Result<Vec<StateEvent>, ReplayError> {
+    let (event_tx, mut event_rx) = broadcast::channel(EVENTS_PER_STEP);
+    let mut state_machine = StateMachine::new(event_tx);
+    let base = Instant::now();
+    state_machine.set_enabled_modes(EnabledModes::all(), base);
+
+    // Collected after every step, so the channel never has to hold more
+    // than one step's events
+    let mut events = Vec::new();
+    for (index, recorded) in recording.iter().enumerate() {
+        let at = base + Duration::from_micros(recorded.elapsed_us);
+        state_machine.handle_event(recorded.event.clone(), at);
+        loop {
+            match event_rx.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Lagged(skipped)) => {
+                    return Err(ReplayError::Lagged { line: index + 1, skipped });
+                }
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::read_recording;
+
+    /// Replay a golden recording and compare against its expected output
+    fn assert_golden(recording: &str, expected: &str) {
+        let recording = read_recording(recording.as_bytes()).unwrap();
+        let actual: Vec<String> = replay(&recording).unwrap().iter().map(|e| e.to_string()).collect();
+        let expected: Vec<&str> = expected.lines().filter(|l| !l.trim().is_empty()).collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_replay_empty_recording() {
+        assert!(replay(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_golden_ctrl_shortcuts() {
+        assert_golden(
+            include_str!("../../tests/recordings/ctrl_shortcuts.jsonl"),
+            include_str!("../../tests/recordings/ctrl_shortcuts.expected"),
+        );
+    }
+
+    #[test]
+    fn test_golden_fast_chords() {
+        assert_golden(
+            include_str!("../../tests/recordings/fast_chords.jsonl"),
+            include_str!("../../tests/recordings/fast_chords.expected"),
+        );
+    }
+
+    /// Recorded by a daemon with `SECOND_BRAIN_RECORD_HOTKEYS`, rather than
+    /// written by hand like the others
+    #[test]
+    fn test_golden_recorded_session() {
+        assert_golden(
+            include_str!("../../tests/recordings/recorded_session.jsonl"),
+            include_str!("../../tests/recordings/recorded_session.expected"),
+        );
+    }
+
+    #[test]
+    fn test_golden_agent_toggles() {
+        assert_golden(
+            include_str!("../../tests/recordings/agent_toggles.jsonl"),
+            include_str!("../../tests/recordings/agent_toggles.expected"),
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Authoritative, versioned snapshot of the daemon's state
+//!
+//! The state machine writes everything except `hotkey_registered`, which
+//! the hotkey listener reports. Everyone else (IPC, future pipelines) holds
+//! a `watch::Receiver` and reads the latest snapshot instead of
+//! reconstructing state from events.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::hotkey::Bindings;
+
+use super::machine::State;
+use super::modes::EnabledModes;
+use super::usage::ShortcutUse;
+
+/// Everything readers need to know about the daemon's current state
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Incremented on every change, so readers can tell snapshots apart
+    pub version: u64,
+    /// Current state machine state
+    pub state: State,
+    /// Modes the machine may enter
+    pub enabled_modes: EnabledModes,
+    /// Chords the machine's transitions are built from
+    pub bindings: Bindings,
+    /// Whether hotkeys are disarmed
+    pub paused: bool,
+    /// Whether the next chord is being captured instead of acted on
+    pub capturing: bool,
+    /// Whether the hotkey listener is running
+    pub hotkey_registered: bool,
+    /// How often the OS disabled the hotkey tap
+    pub tap_disabled_count: u64,
+    /// How often the hotkey tap was re-enabled afterwards
+    pub tap_recovered_count: u64,
+    /// How often each binding was held while a regular key was pressed
+    pub shortcut_use: ShortcutUse,
+    /// The last `TRANSITION_HISTORY` transitions, oldest first
+    pub recent_transitions: VecDeque<TransitionRecord>,
+}
+
+/// Transitions kept in `Snapshot::recent_transitions`
+pub const TRANSITION_HISTORY: usize = 32;
+
+/// One state change, for diagnosing a mode that got stuck
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransitionRecord {
+    pub from: State,
+    pub to: State,
+    /// How long `from` was active
+    pub duration_ms: u64,
+    /// When the transition happened
+    pub at_unix_ms: u64,
+}
+
+impl TransitionRecord {
+    /// A transition happening now
+    pub fn now(from: State, to: State, duration_ms: u64) -> Self {
+        let at_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        Self {
+            from,
+            to,
+            duration_ms,
+            at_unix_ms,
+        }
+    }
+}
+
+impl Snapshot {
+    /// Add `transition` to the history, forgetting the oldest one when full
+    pub fn record_transition(&mut self, transition: TransitionRecord) {
+        if self.recent_transitions.len() == TRANSITION_HISTORY {
+            self.recent_transitions.pop_front();
+        }
+        self.recent_transitions.push_back(transition);
+    }
+}
+
+/// Shared handle to the state store
+#[derive(Debug, Clone)]
+pub struct StateStore {
+    tx: Arc<watch::Sender<Snapshot>>,
+}
+
+impl StateStore {
+    /// Create a store holding the initial snapshot
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(Snapshot::default());
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// Modify the snapshot, notifying readers only if something changed
+    ///
+    /// Returns whether the snapshot changed.
+    pub fn update(&self, f: impl FnOnce(&mut Snapshot)) -> bool {
+        self.tx.send_if_modified(|snapshot| {
+            let before = snapshot.clone();
+            f(snapshot);
+            snapshot.version = before.version;
+
+            if *snapshot == before {
+                return false;
+            }
+            snapshot.version += 1;
+            true
+        })
+    }
+
+    /// The latest snapshot
+    pub fn snapshot(&self) -> Snapshot {
+        self.tx.borrow().clone()
+    }
+
+    /// Watch for snapshot changes
+    pub fn subscribe(&self) -> watch::Receiver<Snapshot> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for StateStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_bumps_only_on_change() {
+        let store = StateStore::new();
+        assert!(store.update(|s| s.state = State::DictationActive));
+        assert_eq!(store.snapshot().version, 1);
+
+        assert!(!store.update(|s| s.state = State::DictationActive));
+        assert_eq!(store.snapshot().version, 1);
+    }
+
+    #[test]
+    fn test_transition_history_is_bounded() {
+        let mut snapshot = Snapshot::default();
+        for duration_ms in 0..TRANSITION_HISTORY as u64 + 2 {
+            snapshot.record_transition(TransitionRecord::now(State::Idle, State::DictationActive, duration_ms));
+        }
+        assert_eq!(snapshot.recent_transitions.len(), TRANSITION_HISTORY);
+        assert_eq!(snapshot.recent_transitions[0].duration_ms, 2);
+    }
+
+    #[tokio::test]
+    async fn test_readers_observe_updates() {
+        let store = StateStore::new();
+        let mut rx = store.subscribe();
+
+        store.update(|s| s.paused = true);
+        rx.changed().await.unwrap();
+        let snapshot = rx.borrow_and_update().clone();
+        assert!(snapshot.paused);
+        assert_eq!(snapshot.version, 1);
+    }
+}
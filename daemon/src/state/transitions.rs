@@ -0,0 +1,238 @@
+//! Transition table for the state machine
+//!
+//! The table is the single source of truth for mode transitions: the
+//! state machine evaluates it on every modifier change, and the same data
+//! is exposed over IPC and rendered as diagrams.
+
+use serde::{Deserialize, Serialize};
+
+use crate::hotkey::{Bindings, Chord, ModifierState};
+
+use super::machine::State;
+use super::modes::EnabledModes;
+
+/// What the modifier keys have to do for a transition to fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Trigger {
+    /// Exactly the chord's keys are held
+    Held { chord: Chord },
+    /// At least one key of the chord is no longer held
+    Released { chord: Chord },
+}
+
+/// Extra conditions checked before a transition fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Guard {
+    /// The trigger chord was not already held on the previous event
+    RisingEdge,
+}
+
+/// A single edge of the state machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: State,
+    pub to: State,
+    pub trigger: Trigger,
+    pub guard: Option<Guard>,
+}
+
+impl Transition {
+    /// Check whether this transition fires for the given key states
+    fn fires(&self, prev: &ModifierState, modifiers: &ModifierState) -> bool {
+        let (triggered, chord) = match self.trigger {
+            Trigger::Held { chord } => (modifiers.holds_exactly(chord), chord),
+            Trigger::Released { chord } => (!modifiers.holds(chord), chord),
+        };
+
+        triggered
+            && match self.guard {
+                None => true,
+                Some(Guard::RisingEdge) => !prev.holds(chord),
+            }
+    }
+
+    /// Human-readable label, e.g. "hold Ctrl+Opt" or "press Ctrl+Cmd"
+    pub fn label(&self) -> String {
+        match (self.trigger, self.guard) {
+            (Trigger::Held { chord }, Some(Guard::RisingEdge)) => format!("press {}", chord),
+            (Trigger::Held { chord }, None) => format!("hold {}", chord),
+            (Trigger::Released { chord }, _) => format!("release {}", chord),
+        }
+    }
+}
+
+/// Ordered list of transitions; for a given state the first match wins
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionTable {
+    transitions: Vec<Transition>,
+}
+
+impl TransitionTable {
+    /// Build the table for the given mode bindings
+    pub fn new(bindings: &Bindings) -> Self {
+        let held = |chord| Trigger::Held { chord };
+        let released = |chord| Trigger::Released { chord };
+        let edge = Some(Guard::RisingEdge);
+        let t = |from, to, trigger, guard| Transition {
+            from,
+            to,
+            trigger,
+            guard,
+        };
+
+        let transitions = vec![
+            // From Idle, priority order: Agent > Intelligent > Dictation
+            t(State::Idle, State::AgentActive, held(bindings.agent), edge),
+            t(State::Idle, State::IntelligentActive, held(bindings.intelligent), None),
+            t(State::Idle, State::DictationActive, held(bindings.dictation), None),
+            // Dictation upgrades to Intelligent, or ends on release
+            t(State::DictationActive, State::IntelligentActive, held(bindings.intelligent), None),
+            t(State::DictationActive, State::Idle, released(bindings.dictation), None),
+            // Intelligent ends as soon as its chord is broken
+            t(State::IntelligentActive, State::Idle, released(bindings.intelligent), None),
+            // Only the Agent toggle can exit Agent mode
+            t(State::AgentActive, State::Idle, held(bindings.agent), edge),
+        ];
+
+        Self { transitions }
+    }
+
+    /// Compute the next state, staying put when no transition fires
+    ///
+    /// Transitions into a mode that is not enabled are skipped, so a lower
+    /// priority transition may fire instead.
+    pub fn next_state(
+        &self,
+        from: State,
+        prev: &ModifierState,
+        modifiers: &ModifierState,
+        enabled: &EnabledModes,
+    ) -> State {
+        self.transitions
+            .iter()
+            .filter(|t| t.from == from && enabled.allows(t.to))
+            .find(|t| t.fires(prev, modifiers))
+            .map(|t| t.to)
+            .unwrap_or(from)
+    }
+
+    /// Describe the machine as data, optionally marking the current state
+    ///
+    /// Modes that are not `enabled` are left out, along with their
+    /// transitions, since the machine never enters them.
+    pub fn describe(&self, enabled: &EnabledModes, current: Option<State>) -> MachineDescription {
+        MachineDescription {
+            states: State::ALL.into_iter().filter(|s| enabled.allows(*s)).collect(),
+            initial: State::Idle,
+            current,
+            transitions: self
+                .transitions
+                .iter()
+                .filter(|t| enabled.allows(t.from) && enabled.allows(t.to))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl Default for TransitionTable {
+    fn default() -> Self {
+        Self::new(&Bindings::default())
+    }
+}
+
+/// Serializable description of the state machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineDescription {
+    /// Every state the machine can be in
+    pub states: Vec<State>,
+    /// State the machine starts in
+    pub initial: State,
+    /// State the machine is in right now, if known
+    pub current: Option<State>,
+    /// Transitions in evaluation order
+    pub transitions: Vec<Transition>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_state_has_an_exit() {
+        let table = TransitionTable::default();
+        for state in State::ALL {
+            assert!(table.transitions.iter().any(|t| t.from == state));
+        }
+    }
+
+    #[test]
+    fn test_disabled_mode_is_skipped() {
+        let table = TransitionTable::default();
+        let mut enabled = EnabledModes::default();
+        enabled.set(State::IntelligentActive, false);
+
+        let prev = ModifierState::default();
+        let modifiers = ModifierState {
+            control: true,
+            option: true,
+            command: false,
+            ..Default::default()
+        };
+        assert_eq!(table.next_state(State::Idle, &prev, &modifiers, &enabled), State::Idle);
+        assert_eq!(
+            table.next_state(State::DictationActive, &prev, &modifiers, &enabled),
+            State::DictationActive
+        );
+    }
+
+    #[test]
+    fn test_sided_binding() {
+        let bindings = Bindings {
+            dictation: serde_json::from_str(r#"["right_option"]"#).unwrap(),
+            ..Bindings::default()
+        };
+        let table = TransitionTable::new(&bindings);
+        let enabled = EnabledModes::default();
+        let prev = ModifierState::default();
+
+        let right: ModifierState = serde_json::from_str(r#"{"option":true,"right_option":true}"#).unwrap();
+        assert_eq!(table.next_state(State::Idle, &prev, &right, &enabled), State::DictationActive);
+
+        let left: ModifierState = serde_json::from_str(r#"{"option":true,"left_option":true}"#).unwrap();
+        assert_eq!(table.next_state(State::Idle, &prev, &left, &enabled), State::Idle);
+    }
+
+    #[test]
+    fn test_labels() {
+        let table = TransitionTable::default();
+        let labels: Vec<String> = table
+            .transitions
+            .iter()
+            .filter(|t| t.from == State::Idle)
+            .map(Transition::label)
+            .collect();
+        assert_eq!(labels, ["press Ctrl+Cmd", "hold Ctrl+Opt", "hold Ctrl"]);
+    }
+
+    #[test]
+    fn test_description_leaves_out_disabled_modes() {
+        let description = TransitionTable::default().describe(&EnabledModes::default(), None);
+        assert_eq!(description.states, [State::Idle, State::DictationActive, State::IntelligentActive]);
+        assert!(description
+            .transitions
+            .iter()
+            .all(|t| t.from != State::AgentActive && t.to != State::AgentActive));
+        assert!(description.transitions.iter().any(|t| t.to == State::IntelligentActive));
+    }
+
+    #[test]
+    fn test_description_serialization() {
+        let description = TransitionTable::default().describe(&EnabledModes::all(), Some(State::Idle));
+        let json = serde_json::to_string(&description).unwrap();
+        assert!(json.contains(r#""current":"idle""#));
+        assert!(json.contains(r#""guard":"rising_edge""#));
+    }
+}
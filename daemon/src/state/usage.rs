@@ -0,0 +1,50 @@
+//! How often bound chords double as app shortcuts
+
+use super::machine::State;
+use crate::hotkey::Bindings;
+
+/// Per mode, how often its chord was held while a regular key was pressed
+///
+/// A high count suggests the chord is also an app shortcut, so using it
+/// as a hotkey starts modes by accident.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShortcutUse {
+    pub dictation: u64,
+    pub intelligent: u64,
+    pub agent: u64,
+}
+
+impl ShortcutUse {
+    /// How often the chord of the mode entered via `state` was used
+    pub fn count(&self, state: State) -> u64 {
+        match state {
+            State::Idle => 0,
+            State::DictationActive => self.dictation,
+            State::IntelligentActive => self.intelligent,
+            State::AgentActive => self.agent,
+        }
+    }
+
+    /// Count one more use of the chord of the mode entered via `state`
+    pub fn record(&mut self, state: State) {
+        match state {
+            State::Idle => {}
+            State::DictationActive => self.dictation += 1,
+            State::IntelligentActive => self.intelligent += 1,
+            State::AgentActive => self.agent += 1,
+        }
+    }
+
+    /// Forget the counts of modes whose chord differs between the bindings
+    pub fn rebind(&mut self, old: &Bindings, new: &Bindings) {
+        if old.dictation != new.dictation {
+            self.dictation = 0;
+        }
+        if old.intelligent != new.intelligent {
+            self.intelligent = 0;
+        }
+        if old.agent != new.agent {
+            self.agent = 0;
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! End-to-end tests for command-line flags and subcommands
+
+mod common;
+
+use std::path::PathBuf;
+use std::process::Output;
+
+use serde_json::{json, Value};
+
+use common::{command, write_config, DaemonBuilder};
+
+/// An empty home directory, removed on drop
+struct Home(PathBuf);
+
+impl Home {
+    fn new(name: &str) -> Self {
+        let home = std::env::temp_dir().join(format!("sb-cli-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        Self(home)
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        command(&self.0).args(args).output().unwrap()
+    }
+}
+
+impl Drop for Home {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn version_prints_json() {
+    let home = Home::new("version");
+    let output = home.run(&["version", "--json"]);
+    assert!(output.status.success());
+
+    let version: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(version["name"], "second-brain-daemon");
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(version["os"], std::env::consts::OS);
+}
+
+#[test]
+fn check_config_reports_errors_with_lines() {
+    let home = Home::new("check");
+    let output = home.run(&["check-config"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("not found"));
+
+    write_config(&home.0, "[ipc]\nmax_clients = 0\n");
+    let output = home.run(&["check-config"]);
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains("config.toml:2:"), "{}", report);
+    assert!(report.contains(": error: "), "{}", report);
+
+    write_config(&home.0, "[ipc]\nmax_clients = 4\nunknown = 1\n");
+    let output = home.run(&["check-config"]);
+    assert!(output.status.success());
+    let report = stdout(&output);
+    assert!(report.contains("config.toml:3:"), "{}", report);
+    assert!(report.contains(": warning: "), "{}", report);
+    assert!(report.ends_with(": ok\n"), "{}", report);
+}
+
+#[test]
+fn flags_override_config_file() {
+    let home = Home::new("print");
+    write_config(&home.0, "[paths]\nsocket = \"/tmp/from-file.sock\"\n\n[logging]\nlevel = \"warn\"\n");
+
+    let output = home.run(&["print-config", "--socket", "/tmp/from-flag.sock"]);
+    assert!(output.status.success());
+    let effective: toml::Table = stdout(&output).parse().unwrap();
+    assert_eq!(effective["paths"]["socket"].as_str(), Some("/tmp/from-flag.sock"));
+
+    // SECOND_BRAIN_LOG_LEVEL beats RUST_LOG, which beats the file
+    let output = command(&home.0)
+        .env("SECOND_BRAIN_LOG_LEVEL", "debug")
+        .arg("print-config")
+        .output()
+        .unwrap();
+    let effective: toml::Table = stdout(&output).parse().unwrap();
+    assert_eq!(effective["logging"]["level"].as_str(), Some("debug"));
+    assert_eq!(effective["paths"]["socket"].as_str(), Some("/tmp/from-file.sock"));
+
+    let output = home.run(&["print-config", "--log-level", "debug,="]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn run_listens_on_socket_from_flag() {
+    let dir = std::env::temp_dir().join(format!("sb-cli-run-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let socket = dir.join("custom.sock");
+    let data_dir = dir.join("data");
+
+    let daemon = DaemonBuilder::new("cli-run")
+        .arg("run")
+        .arg("--socket")
+        .arg(&socket)
+        .arg("--data-dir")
+        .arg(&data_dir)
+        .socket(socket.clone())
+        .spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "ping" }));
+    assert_eq!(client.recv(), json!({ "type": "pong" }));
+    assert!(data_dir.is_dir());
+
+    drop(daemon);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn diagram_without_daemon_uses_persisted_settings() {
+    let home = Home::new("diagram");
+    write_config(&home.0, "[profiles.call.hotkeys]\ndictation = [\"f18\"]\n");
+    let settings = home.0.join(".local/state/second-brain/settings.json");
+    std::fs::create_dir_all(settings.parent().unwrap()).unwrap();
+    std::fs::write(&settings, r#"{ "profile": "call", "enabled_modes": { "intelligent": false } }"#).unwrap();
+
+    let output = home.run(&["diagram", "mermaid"]);
+    assert!(output.status.success());
+    let diagram = stdout(&output);
+
+    // Bindings from the active profile; intelligent disabled at runtime and
+    // agent by default
+    assert!(diagram.contains("Idle --> DictationActive: hold F18"), "{}", diagram);
+    assert!(!diagram.contains("IntelligentActive") && !diagram.contains("AgentActive"), "{}", diagram);
+}
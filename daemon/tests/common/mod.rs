@@ -0,0 +1,296 @@
+//! Shared harness for end-to-end tests: spawns the daemon binary with its
+//! own home directory and talks to it over the IPC socket
+
+#![allow(dead_code)]
+
+use std::ffi::{OsStr, OsString};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A daemon process with its own home directory, killed on drop
+pub struct Daemon {
+    child: Child,
+    pub home: PathBuf,
+    socket: PathBuf,
+}
+
+/// How to start a `Daemon`: its arguments, environment and the files in
+/// its home directory
+pub struct DaemonBuilder {
+    home: PathBuf,
+    inject_input: bool,
+    args: Vec<OsString>,
+    env: Vec<(String, OsString)>,
+    socket: Option<PathBuf>,
+}
+
+impl DaemonBuilder {
+    /// A daemon in a fresh, empty home directory named after `name`
+    pub fn new(name: &str) -> Self {
+        let home = std::env::temp_dir().join(format!("sb-e2e-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        Self {
+            home,
+            inject_input: false,
+            args: Vec::new(),
+            env: Vec::new(),
+            socket: None,
+        }
+    }
+
+    /// Take hotkeys from `inject_input` requests instead of the keyboard
+    pub fn inject_input(mut self) -> Self {
+        self.inject_input = true;
+        self
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: impl AsRef<OsStr>) -> Self {
+        self.env.push((key.to_string(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Use `config` as its `config.toml`
+    pub fn config(self, config: &str) -> Self {
+        write_config(&self.home, config);
+        self
+    }
+
+    /// Put `contents` at `path`, relative to its home
+    pub fn file(self, path: &str, contents: &str) -> Self {
+        let path = self.home.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+        self
+    }
+
+    /// Connect on `socket`, e.g. one passed with `--socket`
+    pub fn socket(mut self, socket: PathBuf) -> Self {
+        self.socket = Some(socket);
+        self
+    }
+
+    pub fn spawn(self) -> Daemon {
+        let mut command = command(&self.home);
+        command
+            .args(&self.args)
+            .envs(self.env)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if self.inject_input {
+            command.env("SECOND_BRAIN_DEBUG_INJECT_INPUT", "1");
+        }
+
+        Daemon {
+            child: command.spawn().unwrap(),
+            socket: self.socket.unwrap_or_else(|| self.home.join("run/second-brain/daemon.sock")),
+            home: self.home,
+        }
+    }
+}
+
+impl Daemon {
+    /// Replace the daemon's `config.toml`
+    pub fn write_config(&self, config: &str) {
+        write_config(&self.home, config);
+    }
+
+    /// Send a signal, e.g. "HUP"
+    pub fn signal(&self, name: &str) {
+        let status = Command::new("kill")
+            .args(["-s", name, &self.child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Wait for the daemon to exit on its own
+    pub fn wait(&mut self) -> ExitStatus {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            match self.child.try_wait().unwrap() {
+                Some(status) => return status,
+                None if Instant::now() > deadline => panic!("daemon never exited"),
+                None => std::thread::sleep(Duration::from_millis(20)),
+            }
+        }
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        self.socket.clone()
+    }
+
+    /// Connect once the daemon is listening
+    pub fn connect(&self) -> Client {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            match UnixStream::connect(self.socket_path()) {
+                Ok(stream) => return Client::new(stream),
+                Err(e) if Instant::now() > deadline => panic!("daemon never listened: {}", e),
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.home);
+    }
+}
+
+/// The daemon binary with `home` as its home directory and none of the
+/// caller's overrides
+///
+/// The XDG directories are set inside `home` so tests see the same layout
+/// on every platform and never share a socket.
+pub fn command(home: &std::path::Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_second-brain-daemon"));
+    command
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_STATE_HOME", home.join(".local/state"))
+        .env("XDG_RUNTIME_DIR", home.join("run"))
+        .env_remove("XDG_DATA_HOME")
+        .env("RUST_LOG", "warn")
+        .env_remove("SECOND_BRAIN_RECORD_HOTKEYS")
+        .env_remove("SECOND_BRAIN_CONFIG")
+        .env_remove("SECOND_BRAIN_SOCKET")
+        .env_remove("SECOND_BRAIN_DATA_DIR")
+        .env_remove("SECOND_BRAIN_LOG_LEVEL")
+        .env_remove("NOTIFY_SOCKET")
+        .env_remove("WATCHDOG_USEC")
+        .env_remove("WATCHDOG_PID");
+    command
+}
+
+pub fn write_config(home: &std::path::Path, config: &str) {
+    let config_dir = home.join(".config/second-brain");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(config_dir.join("config.toml"), config).unwrap();
+}
+
+/// Minimal blocking client speaking the length-prefixed JSON protocol
+pub struct Client {
+    stream: UnixStream,
+}
+
+impl Client {
+    fn new(stream: UnixStream) -> Self {
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        Self { stream }
+    }
+
+    pub fn send(&mut self, message: Value) {
+        let bytes = serde_json::to_vec(&message).unwrap();
+        self.stream.write_all(&(bytes.len() as u32).to_le_bytes()).unwrap();
+        self.stream.write_all(&bytes).unwrap();
+    }
+
+    pub fn recv(&mut self) -> Value {
+        let mut len = [0u8; 4];
+        self.stream.read_exact(&mut len).unwrap();
+        let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+        self.stream.read_exact(&mut bytes).unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Read a message if one arrives within `timeout`
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Value> {
+        self.stream.set_read_timeout(Some(timeout)).unwrap();
+        let mut len = [0u8; 4];
+        let result = self.stream.read_exact(&mut len);
+        self.stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        match result {
+            Ok(()) => {
+                let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+                self.stream.read_exact(&mut bytes).unwrap();
+                Some(serde_json::from_slice(&bytes).unwrap())
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => None,
+            Err(e) => panic!("read failed: {}", e),
+        }
+    }
+
+    /// Read messages until the daemon closes the connection
+    pub fn recv_until_closed(&mut self) -> Vec<Value> {
+        let mut messages = Vec::new();
+        loop {
+            let mut len = [0u8; 4];
+            match self.stream.read_exact(&mut len) {
+                Ok(()) => {
+                    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+                    self.stream.read_exact(&mut bytes).unwrap();
+                    messages.push(serde_json::from_slice(&bytes).unwrap());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return messages,
+                Err(e) => panic!("read failed: {}", e),
+            }
+        }
+    }
+
+    /// Whether the daemon has closed the connection
+    pub fn closed(&mut self) -> bool {
+        let mut byte = [0u8; 1];
+        matches!(self.stream.read(&mut byte), Ok(0))
+    }
+
+    /// Read messages until every expected one has arrived, in any order
+    pub fn expect_all(&mut self, expected: &[Value]) {
+        let mut missing: Vec<&Value> = expected.iter().collect();
+        while !missing.is_empty() {
+            let message = self.recv();
+            missing.retain(|m| !matches(m, &message));
+        }
+    }
+
+    pub fn inject(&mut self, control: bool, option: bool, command: bool) {
+        self.send(json!({
+            "type": "inject_input",
+            "modifiers": { "control": control, "option": option, "command": command },
+        }));
+    }
+}
+
+/// Poll `get_status` until `field` has the expected value
+pub fn wait_for_status(client: &mut Client, field: &str, expected: Value) {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        client.send(json!({ "type": "get_status" }));
+        if client.recv()[field] == expected {
+            return;
+        }
+        assert!(Instant::now() < deadline, "{} never became {}", field, expected);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Whether `actual` contains every field of `expected`
+///
+/// Lets tests ignore fields like `duration_ms` whose values vary.
+pub fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|a| matches(value, a))),
+        _ => expected == actual,
+    }
+}
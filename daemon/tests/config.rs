@@ -0,0 +1,240 @@
+//! End-to-end tests for `config.toml`: applied settings, and errors
+//! reported over IPC instead of stopping the daemon
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use common::{wait_for_status, DaemonBuilder, TIMEOUT};
+
+#[test]
+fn config_file_bindings_and_modes_apply() {
+    let daemon = DaemonBuilder::new("config-applied")
+        .inject_input()
+        .config("[hotkeys]\ndictation = [\"f18\"]\n\n[modes]\nagent = false\n")
+        .spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "get_status" }));
+    let status = client.recv();
+    assert_eq!(status["config_errors"], 0);
+    assert_eq!(status["enabled_modes"], json!(["dictation", "intelligent"]));
+
+    client.send(json!({ "type": "inject_input", "modifiers": { "keys": ["f18"] } }));
+    assert_eq!(client.recv(), json!({ "type": "input_injected" }));
+    wait_for_status(&mut client, "mode", json!("dictation"));
+}
+
+#[test]
+fn invalid_config_is_reported_over_ipc() {
+    let daemon = DaemonBuilder::new("config-invalid")
+        .inject_input()
+        .config("[hotkeys]\ndictation = [\"f18\"]\n\n[ipc]\nmax_clients = \"many\"\n")
+        .spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "get_status" }));
+    assert_eq!(client.recv()["config_errors"], 1);
+
+    client.send(json!({ "type": "get_config_status" }));
+    let response = client.recv();
+    assert_eq!(response["type"], "config_status");
+    assert_eq!(response["applied"], false);
+    assert!(response["path"].as_str().unwrap().ends_with(".config/second-brain/config.toml"));
+    let diagnostic = &response["diagnostics"][0];
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["line"], 5);
+
+    // Nothing from the file was applied
+    client.send(json!({ "type": "get_bindings" }));
+    assert_eq!(client.recv()["bindings"]["dictation"], json!(["control"]));
+}
+
+#[test]
+fn clients_over_the_limit_are_turned_away() {
+    let daemon = DaemonBuilder::new("config-clients")
+        .inject_input()
+        .config("[ipc]\nmax_clients = 1\n")
+        .spawn();
+    let mut first = daemon.connect();
+    first.send(json!({ "type": "ping" }));
+    assert_eq!(first.recv(), json!({ "type": "pong" }));
+
+    let mut second = daemon.connect();
+    assert_eq!(second.recv()["code"], "too_many_clients");
+
+    // The slot frees up once the first client leaves; turned-away
+    // clients hear about it right away, accepted ones get nothing unasked
+    drop(first);
+    let deadline = Instant::now() + TIMEOUT;
+    while daemon.connect().recv_timeout(Duration::from_millis(200)).is_some() {
+        assert!(Instant::now() < deadline, "slot never freed");
+    }
+}
+
+#[test]
+fn edits_are_applied_and_announced() {
+    let daemon = DaemonBuilder::new("config-reload")
+        .inject_input()
+        .config("[ipc]\nmax_clients = 4\n")
+        .spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    daemon.write_config("[ipc]\nmax_clients = 4\n\n[hotkeys]\ndictation = [\"f18\"]\n");
+    assert_eq!(
+        client.recv(),
+        json!({
+            "type": "config_reloaded",
+            "applied": true,
+            "changed": ["hotkeys.dictation"],
+            "restart_required": [],
+            "diagnostics": [],
+        })
+    );
+
+    client.send(json!({ "type": "inject_input", "modifiers": { "keys": ["f18"] } }));
+    client.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "mode_changed", "mode": "dictation", "previous": "idle" }),
+    ]);
+}
+
+#[test]
+fn broken_edits_keep_the_running_config() {
+    let daemon = DaemonBuilder::new("config-broken")
+        .inject_input()
+        .config("[hotkeys]\ndictation = [\"f18\"]\n")
+        .spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    daemon.write_config("[hotkeys]\ndictation = [\"f18\"\n");
+    let notification = client.recv();
+    assert_eq!(notification["type"], "config_reloaded");
+    assert_eq!(notification["applied"], false);
+    assert_eq!(notification["diagnostics"][0]["severity"], "error");
+
+    wait_for_status(&mut client, "config_errors", json!(1));
+    client.send(json!({ "type": "get_bindings" }));
+    assert_eq!(client.recv()["bindings"]["dictation"], json!(["f18"]));
+}
+
+#[test]
+fn sighup_reloads_and_reports_restart_only_keys() {
+    let daemon = DaemonBuilder::new("config-sighup").inject_input().config("").spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    daemon.write_config("[paths]\nsocket = \"/tmp/elsewhere.sock\"\n");
+    daemon.signal("HUP");
+    let notification = client.recv();
+    assert_eq!(notification["type"], "config_reloaded");
+    assert_eq!(notification["changed"], json!([]));
+    assert_eq!(notification["restart_required"], json!(["paths.socket"]));
+
+    // Still serving on the old socket
+    client.send(json!({ "type": "ping" }));
+    assert_eq!(client.recv(), json!({ "type": "pong" }));
+}
+
+#[test]
+fn paths_are_reported_and_old_settings_moved() {
+    let daemon = DaemonBuilder::new("config-paths")
+        .inject_input()
+        .file(
+            ".local/share/second-brain/settings.json",
+            r#"{ "enabled_modes": { "dictation": true, "intelligent": false, "agent": true } }"#,
+        )
+        .spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "get_paths" }));
+    let state_dir = daemon.home.join(".local/state/second-brain");
+    assert_eq!(
+        client.recv(),
+        json!({
+            "type": "paths",
+            "socket": daemon.socket_path(),
+            "config_file": daemon.home.join(".config/second-brain/config.toml"),
+            "data_dir": state_dir,
+            "settings_file": state_dir.join("settings.json"),
+        })
+    );
+
+    // Settings from the old location moved and applied
+    assert!(state_dir.join("settings.json").is_file());
+    assert!(!daemon.home.join(".local/share/second-brain/settings.json").exists());
+    client.send(json!({ "type": "get_status" }));
+    assert_eq!(client.recv()["enabled_modes"], json!(["dictation", "agent"]));
+}
+
+#[test]
+fn profiles_switch_over_ipc() {
+    let profiles = "[profiles.call.hotkeys]\ndictation = [\"f18\"]\n\n[profiles.call.privacy]\nallow_cloud = false\n";
+    let daemon = DaemonBuilder::new("config-profiles").inject_input().config(profiles).spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "get_profiles" }));
+    assert_eq!(client.recv(), json!({ "type": "profiles", "active": null, "available": ["call"] }));
+
+    client.send(json!({ "type": "set_profile", "profile": "home" }));
+    assert_eq!(client.recv()["code"], "unknown_profile");
+
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+    client.send(json!({ "type": "set_profile", "profile": "call" }));
+    client.expect_all(&[
+        json!({ "type": "profiles", "active": "call", "available": ["call"] }),
+        json!({
+            "type": "profile_changed",
+            "profile": "call",
+            "changed": ["hotkeys.dictation", "privacy.allow_cloud"],
+        }),
+    ]);
+
+    client.send(json!({ "type": "get_status" }));
+    assert_eq!(client.recv()["profile"], "call");
+    client.send(json!({ "type": "inject_input", "modifiers": { "keys": ["f18"] } }));
+    client.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "mode_changed", "mode": "dictation", "previous": "idle" }),
+    ]);
+
+    // Removing the active profile from the file switches back to none
+    daemon.write_config("[hotkeys]\ndictation = [\"f18\"]\n");
+    client.expect_all(&[
+        json!({
+            "type": "profile_changed",
+            "profile": null,
+            "changed": ["privacy.allow_cloud", "profiles.call"],
+        }),
+        json!({ "type": "config_reloaded", "applied": true }),
+    ]);
+    client.send(json!({ "type": "get_status" }));
+    assert_eq!(client.recv()["profile"], json!(null));
+}
+
+#[test]
+fn removed_profiles_are_forgotten() {
+    let profiles = "[profiles.call.privacy]\nallow_cloud = false\n\n[profiles.home.privacy]\nallow_cloud = false\n";
+    let daemon = DaemonBuilder::new("config-profile-removed").inject_input().config(profiles).spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    daemon.write_config("[profiles.home.privacy]\nallow_cloud = false\n");
+    let notification = client.recv();
+    assert_eq!(notification["type"], "config_reloaded");
+    assert_eq!(notification["changed"], json!(["profiles.call"]));
+
+    client.send(json!({ "type": "get_profiles" }));
+    assert_eq!(client.recv(), json!({ "type": "profiles", "active": null, "available": ["home"] }));
+    client.send(json!({ "type": "set_profile", "profile": "call" }));
+    assert_eq!(client.recv()["code"], "unknown_profile");
+}
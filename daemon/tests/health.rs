@@ -0,0 +1,84 @@
+//! End-to-end tests for component health
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use common::{Client, DaemonBuilder};
+
+/// Poll `get_health` until `accept` is satisfied with the response
+fn wait_for_health(client: &mut Client, accept: impl Fn(&Value) -> bool) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        client.send(json!({ "type": "get_health" }));
+        let health = client.recv();
+        if accept(&health) {
+            return health;
+        }
+        assert!(Instant::now() < deadline, "health never settled: {}", health);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn states(health: &Value) -> Vec<(&str, &str)> {
+    health["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["name"].as_str().unwrap(), c["state"].as_str().unwrap()))
+        .collect()
+}
+
+#[test]
+fn running_components_are_healthy() {
+    let daemon = DaemonBuilder::new("health-ok").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    let health = wait_for_health(&mut client, |health| {
+        health["components"]
+            .as_array()
+            .is_some_and(|components| components.iter().all(|c| c["message"] != "starting"))
+    });
+    assert_eq!(health["type"], "health");
+    assert_eq!(health["status"], "ok");
+    assert_eq!(
+        states(&health),
+        [
+            ("config", "ok"),
+            ("hotkey listener", "ok"),
+            ("ipc server", "ok"),
+            ("notifications", "ok"),
+            ("secrets", "ok"),
+        ]
+    );
+    let listener = &health["components"][1];
+    assert_eq!(listener["message"], "running");
+    assert!(listener["since_unix_secs"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn config_errors_degrade_health() {
+    let daemon = DaemonBuilder::new("health-config")
+        .inject_input()
+        .config("[ipc]\nmax_clients = \"many\"\n")
+        .spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "get_health" }));
+    let health = client.recv();
+    assert_eq!(health["status"], "degraded");
+    let config = health["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "config")
+        .unwrap();
+    assert_eq!(config["state"], "degraded");
+    assert_eq!(config["message"], "config.toml has an error and was not applied");
+
+    daemon.write_config("[ipc]\nmax_clients = 4\n");
+    let health = wait_for_health(&mut client, |health| states(health).contains(&("config", "ok")));
+    assert_eq!(health["status"], "ok");
+}
@@ -0,0 +1,257 @@
+//! End-to-end tests driving the daemon binary over its IPC socket
+//!
+//! The daemon runs with input injection enabled, so hotkeys come from
+//! `inject_input` requests and the whole hotkey → state machine → IPC path
+//! is exercised without Accessibility permissions or input devices.
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use common::{command, matches, wait_for_status, DaemonBuilder, TIMEOUT};
+
+#[test]
+fn injected_chords_drive_modes_and_notifications() {
+    // Agent mode is off unless enabled
+    let daemon = DaemonBuilder::new("modes").inject_input().config("[modes]\nagent = true\n").spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    // Hold Control: dictation starts
+    client.inject(true, false, false);
+    client.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "state_event", "event": { "type": "dictation_started" } }),
+        json!({ "type": "mode_changed", "mode": "dictation", "previous": "idle" }),
+    ]);
+
+    // Release: dictation completes
+    client.inject(false, false, false);
+    client.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "state_event", "event": { "type": "dictation_complete" } }),
+        json!({ "type": "mode_changed", "mode": "idle", "previous": "dictation" }),
+    ]);
+
+    // Control+Command toggles agent mode on
+    client.inject(true, false, true);
+    client.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "state_event", "event": { "type": "agent_mode_entered" } }),
+        json!({ "type": "mode_changed", "mode": "agent", "previous": "idle" }),
+    ]);
+
+    let mut status_client = daemon.connect();
+    status_client.send(json!({ "type": "get_status" }));
+    let status = status_client.recv();
+    assert_eq!(status["mode"], "agent");
+    assert_eq!(status["hotkey_registered"], true);
+}
+
+#[test]
+fn injection_is_rejected_unless_enabled() {
+    let daemon = DaemonBuilder::new("disabled").spawn();
+    let mut client = daemon.connect();
+
+    client.inject(true, false, false);
+    let response = client.recv();
+    assert_eq!(response["type"], "error");
+    assert_eq!(response["code"], "injection_disabled");
+}
+
+#[test]
+fn rebinding_applies_without_restart() {
+    let daemon = DaemonBuilder::new("rebind").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "set_bindings", "bindings": { "dictation": ["f18"] } }));
+    let response = client.recv();
+    assert_eq!(response["type"], "bindings");
+    assert_eq!(response["bindings"]["dictation"], json!(["f18"]));
+
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    // The old chord does nothing, the new key starts dictation
+    client.inject(true, false, false);
+    assert_eq!(client.recv(), json!({ "type": "input_injected" }));
+    client.inject(false, false, false);
+    assert_eq!(client.recv(), json!({ "type": "input_injected" }));
+
+    client.send(json!({ "type": "inject_input", "modifiers": { "keys": ["f18"] } }));
+    client.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "mode_changed", "mode": "dictation", "previous": "idle" }),
+    ]);
+
+    // The binding survives in settings
+    let settings = std::fs::read_to_string(daemon.home.join(".local/state/second-brain/settings.json")).unwrap();
+    assert!(settings.contains("f18"));
+}
+
+#[test]
+fn empty_binding_is_rejected() {
+    let daemon = DaemonBuilder::new("empty-binding").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "set_bindings", "bindings": { "agent": [] } }));
+    let response = client.recv();
+    assert_eq!(response["type"], "error");
+    assert_eq!(response["code"], "invalid_binding");
+}
+
+#[test]
+fn captured_chord_can_be_committed_as_binding() {
+    let daemon = DaemonBuilder::new("capture").inject_input().spawn();
+    let mut ui = daemon.connect();
+    let mut keyboard = daemon.connect();
+
+    ui.send(json!({ "type": "capture_chord", "timeout_secs": 10 }));
+    wait_for_status(&mut keyboard, "capturing", json!(true));
+
+    // Right Option + Shift, pressed one after the other, then released
+    let right_option = json!({ "option": true, "right_option": true });
+    let with_shift = json!({ "option": true, "right_option": true, "shift": true, "left_shift": true });
+    for modifiers in [right_option.clone(), with_shift.clone(), json!({})] {
+        keyboard.send(json!({ "type": "inject_input", "modifiers": modifiers }));
+        assert_eq!(keyboard.recv(), json!({ "type": "input_injected" }));
+    }
+
+    let captured = ui.recv();
+    assert_eq!(captured["type"], "chord_captured");
+    assert_eq!(captured["chord"], json!(["right_option", "left_shift"]));
+    assert_eq!(captured["label"], "ROpt+LShift");
+
+    ui.send(json!({ "type": "commit_capture", "mode": "dictation" }));
+    let response = ui.recv();
+    assert_eq!(response["type"], "bindings");
+    assert_eq!(response["bindings"]["dictation"], json!(["right_option", "left_shift"]));
+
+    // The captured chord now triggers dictation
+    keyboard.send(json!({ "type": "inject_input", "modifiers": with_shift }));
+    assert_eq!(keyboard.recv(), json!({ "type": "input_injected" }));
+    wait_for_status(&mut keyboard, "mode", json!("dictation"));
+}
+
+#[test]
+fn captures_belong_to_one_client() {
+    let daemon = DaemonBuilder::new("capture-clients").inject_input().spawn();
+    let mut ui = daemon.connect();
+    let mut other = daemon.connect();
+    let mut keyboard = daemon.connect();
+
+    ui.send(json!({ "type": "capture_chord", "timeout_secs": 10 }));
+    wait_for_status(&mut keyboard, "capturing", json!(true));
+
+    // A second capture is refused rather than taking over the first
+    other.send(json!({ "type": "capture_chord", "timeout_secs": 10 }));
+    assert_eq!(other.recv()["code"], "capture_in_progress");
+
+    for modifiers in [json!({ "shift": true, "left_shift": true }), json!({})] {
+        keyboard.send(json!({ "type": "inject_input", "modifiers": modifiers }));
+        assert_eq!(keyboard.recv(), json!({ "type": "input_injected" }));
+    }
+    assert_eq!(ui.recv()["type"], "chord_captured");
+
+    // Only the client that captured the chord can commit it
+    other.send(json!({ "type": "commit_capture", "mode": "agent" }));
+    assert_eq!(other.recv()["code"], "nothing_captured");
+    ui.send(json!({ "type": "commit_capture", "mode": "agent" }));
+    assert_eq!(ui.recv()["bindings"]["agent"], json!(["left_shift"]));
+}
+
+#[test]
+fn capture_times_out() {
+    let daemon = DaemonBuilder::new("capture-timeout").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "capture_chord", "timeout_secs": 1 }));
+    let response = client.recv();
+    assert_eq!(response["code"], "capture_timeout");
+
+    client.send(json!({ "type": "commit_capture", "mode": "agent" }));
+    assert_eq!(client.recv()["code"], "nothing_captured");
+}
+
+#[test]
+fn chords_held_while_typing_are_reported() {
+    let daemon = DaemonBuilder::new("shortcut-use").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    // Control+C, Control+V, Control+Z
+    client.inject(true, false, false);
+    assert_eq!(client.recv(), json!({ "type": "input_injected" }));
+    for _ in 0..3 {
+        client.send(json!({ "type": "inject_key_press" }));
+        assert_eq!(client.recv(), json!({ "type": "input_injected" }));
+    }
+
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        client.send(json!({ "type": "get_hotkey_warnings" }));
+        let response = client.recv();
+        assert_eq!(response["type"], "hotkey_warnings");
+        let warnings = response["warnings"].as_array().unwrap();
+        if warnings.iter().any(|w| matches(&json!({ "mode": "dictation", "kind": "used_as_shortcut" }), w)) {
+            break;
+        }
+        assert!(Instant::now() < deadline, "no shortcut warning in {:?}", warnings);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn overlapping_bindings_are_reported() {
+    let daemon = DaemonBuilder::new("overlap").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "set_bindings", "bindings": { "agent": ["control"] } }));
+    let response = client.recv();
+    assert_eq!(response["type"], "bindings");
+    let warnings = response["warnings"].as_array().unwrap();
+    for mode in ["dictation", "agent"] {
+        let expected = json!({ "mode": mode, "kind": "other_mode" });
+        assert!(warnings.iter().any(|w| matches(&expected, w)), "{:?}", warnings);
+    }
+}
+
+#[test]
+fn recording_replays_to_the_events_seen_live() {
+    let recording = std::env::temp_dir().join(format!("sb-e2e-recording-{}.jsonl", std::process::id()));
+    let daemon = DaemonBuilder::new("recording")
+        .inject_input()
+        .env("SECOND_BRAIN_RECORD_HOTKEYS", &recording)
+        .config("[modes]\nagent = true\n")
+        .spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+
+    // Dictation, then agent mode on and off
+    let mut live = Vec::new();
+    for (control, command) in [(true, false), (false, false), (true, true), (false, false), (true, true), (false, false)] {
+        client.inject(control, false, command);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    while live.len() < 4 {
+        let message = client.recv();
+        if message["type"] == "state_event" {
+            live.push(message["event"].clone());
+        }
+    }
+    drop(daemon);
+
+    let output = command(&std::env::temp_dir()).arg("replay").arg(&recording).output().unwrap();
+    let _ = std::fs::remove_file(&recording);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let replayed: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(replayed, live);
+}
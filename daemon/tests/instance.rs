@@ -0,0 +1,58 @@
+//! End-to-end tests for single-instance enforcement
+
+mod common;
+
+use std::os::unix::net::UnixListener;
+
+use serde_json::json;
+
+use common::{command, DaemonBuilder};
+
+#[test]
+fn second_instance_exits_without_taking_the_socket() {
+    let daemon = DaemonBuilder::new("instance-second").spawn();
+    let mut client = daemon.connect();
+
+    let output = command(&daemon.home).arg("run").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("already running"), "{}", stderr);
+    assert!(stderr.contains("(pid "), "{}", stderr);
+
+    // Another data directory gets past the lock, but not the live socket
+    let data_dir = daemon.home.join("other");
+    let output = command(&daemon.home)
+        .args(["run", "--data-dir", data_dir.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("in use by another process"), "{}", stderr);
+
+    client.send(json!({ "type": "ping" }));
+    assert_eq!(client.recv(), json!({ "type": "pong" }));
+}
+
+#[test]
+fn stale_socket_is_replaced() {
+    // A socket file nothing listens on, as left by a crashed daemon
+    let dir = std::env::temp_dir().join(format!("sb-instance-stale-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("daemon.sock");
+    drop(UnixListener::bind(&socket).unwrap());
+    assert!(socket.exists());
+
+    let daemon = DaemonBuilder::new("instance-stale")
+        .arg("run")
+        .arg("--socket")
+        .arg(&socket)
+        .socket(socket.clone())
+        .spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "ping" }));
+    assert_eq!(client.recv(), json!({ "type": "pong" }));
+
+    drop(daemon);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,60 @@
+//! End-to-end tests for the secret store
+
+mod common;
+
+use std::io::Write;
+use std::process::Stdio;
+
+use serde_json::json;
+
+use common::{command, DaemonBuilder};
+
+const FILE_BACKEND: &str = "[secrets]\nbackend = \"file\"\n";
+
+#[test]
+fn secrets_are_set_listed_and_deleted_over_ipc() {
+    let daemon = DaemonBuilder::new("secrets-ipc").inject_input().config(FILE_BACKEND).spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "list_secrets" }));
+    assert_eq!(client.recv(), json!({ "type": "secrets", "names": [] }));
+
+    client.send(json!({ "type": "set_secret", "name": "openai", "value": "sk-e2e-value" }));
+    assert_eq!(client.recv(), json!({ "type": "secrets", "names": ["openai"] }));
+    client.send(json!({ "type": "set_secret", "name": "../openai", "value": "x" }));
+    assert_eq!(client.recv()["code"], "invalid_secret_name");
+
+    let stored = std::fs::read(daemon.home.join(".local/state/second-brain/secrets.enc")).unwrap();
+    assert!(!stored.windows(12).any(|w| w == b"sk-e2e-value"));
+
+    client.send(json!({ "type": "delete_secret", "name": "openai" }));
+    assert_eq!(client.recv(), json!({ "type": "secrets", "names": [] }));
+    client.send(json!({ "type": "delete_secret", "name": "openai" }));
+    assert_eq!(client.recv()["code"], "unknown_secret");
+}
+
+#[test]
+fn secret_subcommand_talks_to_the_daemon() {
+    let daemon = DaemonBuilder::new("secrets-cli").inject_input().config(FILE_BACKEND).spawn();
+    daemon.connect();
+
+    let mut set = command(&daemon.home)
+        .args(["secret", "set", "deepgram"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    set.stdin.take().unwrap().write_all(b"dg-e2e-value\n").unwrap();
+    let output = set.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+
+    let output = command(&daemon.home).args(["secret", "list"]).output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "deepgram\n");
+
+    let output = command(&daemon.home).args(["secret", "delete", "openai"]).output().unwrap();
+    assert!(!output.status.success());
+    let output = command(&daemon.home).args(["secret", "delete", "deepgram"]).output().unwrap();
+    assert!(output.status.success());
+}
@@ -0,0 +1,51 @@
+//! End-to-end tests for service manager notifications
+
+mod common;
+
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use serde_json::json;
+
+use common::DaemonBuilder;
+
+/// The next notification the daemon sent to the fake service manager
+fn recv(manager: &UnixDatagram) -> String {
+    let mut buffer = [0; 1024];
+    let len = manager.recv(&mut buffer).expect("no notification from the daemon");
+    String::from_utf8(buffer[..len].to_vec()).unwrap()
+}
+
+#[test]
+fn readiness_watchdog_and_stopping_are_notified() {
+    let dir = std::env::temp_dir().join(format!("sb-service-notify-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("notify.sock");
+    let manager = UnixDatagram::bind(&path).unwrap();
+    manager.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+    let mut daemon = DaemonBuilder::new("service-notify")
+        .inject_input()
+        .env("NOTIFY_SOCKET", &path)
+        .env("WATCHDOG_USEC", "400000")
+        .spawn();
+
+    // Ready only once clients can connect
+    let ready = recv(&manager);
+    assert!(ready.starts_with("READY=1\nSTATUS="), "{:?}", ready);
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "ping" }));
+    assert_eq!(client.recv(), json!({ "type": "pong" }));
+
+    for _ in 0..2 {
+        let ping = recv(&manager);
+        assert!(ping.starts_with("WATCHDOG=1\nSTATUS="), "{:?}", ping);
+    }
+
+    daemon.signal("TERM");
+    while recv(&manager) != "STOPPING=1" {}
+    assert!(daemon.wait().success());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,113 @@
+//! End-to-end tests for graceful shutdown
+
+mod common;
+
+use serde_json::{json, Value};
+
+use common::{wait_for_status, Client, DaemonBuilder};
+
+/// Read until `shutting_down`, returning the messages before it
+fn expect_shutting_down(client: &mut Client) -> Vec<Value> {
+    let mut before = Vec::new();
+    loop {
+        let message = client.recv();
+        if message["type"] == "shutting_down" {
+            assert_eq!(message["reason"], "received SIGTERM");
+            return before;
+        }
+        before.push(message);
+    }
+}
+
+#[test]
+fn sigterm_notifies_subscribers_and_removes_socket() {
+    let mut daemon = DaemonBuilder::new("shutdown-notify")
+        .inject_input()
+        .config("[modes]\nagent = true\n")
+        .spawn();
+    let mut subscriber = daemon.connect();
+    let mut idle = daemon.connect();
+    idle.send(json!({ "type": "ping" }));
+    assert_eq!(idle.recv(), json!({ "type": "pong" }));
+
+    subscriber.send(json!({ "type": "subscribe" }));
+    assert_eq!(subscriber.recv(), json!({ "type": "subscribed" }));
+    subscriber.inject(true, false, true);
+    subscriber.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "mode_changed", "mode": "agent", "previous": "idle" }),
+    ]);
+
+    daemon.signal("TERM");
+    expect_shutting_down(&mut subscriber);
+    let after = subscriber.recv_until_closed();
+    assert!(after.contains(&json!({ "type": "mode_changed", "mode": "idle", "previous": "agent" })), "{:?}", after);
+    assert!(idle.closed());
+
+    assert!(daemon.wait().success());
+    assert!(!daemon.socket_path().exists());
+}
+
+#[test]
+fn active_mode_ends_with_exit_events_after_shutting_down() {
+    let mut daemon = DaemonBuilder::new("shutdown-hold").inject_input().spawn();
+    let mut subscriber = daemon.connect();
+    subscriber.send(json!({ "type": "subscribe" }));
+    assert_eq!(subscriber.recv(), json!({ "type": "subscribed" }));
+
+    // Dictation held through the shutdown
+    subscriber.inject(true, false, false);
+    subscriber.expect_all(&[
+        json!({ "type": "input_injected" }),
+        json!({ "type": "mode_changed", "mode": "dictation", "previous": "idle" }),
+    ]);
+
+    // Subscribers are told first, then get the exit events before the
+    // connection closes
+    daemon.signal("TERM");
+    let exit_event = |m: &Value| m["type"] == "state_event" && m["event"]["type"] == "dictation_complete";
+    let before = expect_shutting_down(&mut subscriber);
+    assert!(!before.iter().any(exit_event), "{:?}", before);
+    let after = subscriber.recv_until_closed();
+    assert!(after.iter().any(exit_event), "no exit event after shutting_down: {:?}", after);
+    assert!(after.contains(&json!({ "type": "mode_changed", "mode": "idle", "previous": "dictation" })));
+    assert!(daemon.wait().success());
+}
+
+#[test]
+fn capture_in_progress_can_finish_during_shutdown() {
+    let mut daemon = DaemonBuilder::new("shutdown-capture").inject_input().spawn();
+    let mut client = daemon.connect();
+    let mut keyboard = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+    client.send(json!({ "type": "capture_chord", "timeout_secs": 30 }));
+    wait_for_status(&mut keyboard, "capturing", json!(true));
+
+    // The chord is pressed after the daemon announced it is stopping
+    daemon.signal("TERM");
+    expect_shutting_down(&mut client);
+    for modifiers in [json!({ "shift": true, "left_shift": true }), json!({})] {
+        keyboard.send(json!({ "type": "inject_input", "modifiers": modifiers }));
+        assert_eq!(keyboard.recv(), json!({ "type": "input_injected" }));
+    }
+    let after = client.recv_until_closed();
+    assert!(after.iter().any(|m| m["type"] == "chord_captured"), "{:?}", after);
+    assert!(daemon.wait().success());
+}
+
+#[test]
+fn capture_left_waiting_is_cancelled_at_the_deadline() {
+    let mut daemon = DaemonBuilder::new("shutdown-capture-deadline").inject_input().spawn();
+    let mut client = daemon.connect();
+    client.send(json!({ "type": "subscribe" }));
+    assert_eq!(client.recv(), json!({ "type": "subscribed" }));
+    client.send(json!({ "type": "capture_chord", "timeout_secs": 30 }));
+    wait_for_status(&mut daemon.connect(), "capturing", json!(true));
+
+    daemon.signal("TERM");
+    expect_shutting_down(&mut client);
+    let after = client.recv_until_closed();
+    assert!(after.iter().any(|m| m["code"] == "capture_cancelled"), "{:?}", after);
+    assert!(daemon.wait().success());
+}
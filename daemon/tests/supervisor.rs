@@ -0,0 +1,26 @@
+//! End-to-end tests for component supervision
+
+mod common;
+
+use serde_json::json;
+
+use common::DaemonBuilder;
+
+#[test]
+fn supervised_components_are_reported_in_status() {
+    let daemon = DaemonBuilder::new("supervisor-status").inject_input().spawn();
+    let mut client = daemon.connect();
+
+    client.send(json!({ "type": "get_status" }));
+    let status = client.recv();
+    let components: Vec<_> = status["components"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["name"].as_str().unwrap(), c["running"].as_bool().unwrap(), c["restarts"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(
+        components,
+        [("hotkey listener", true, 0), ("ipc server", true, 0), ("notifications", true, 0)]
+    );
+}
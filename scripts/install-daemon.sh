@@ -1,5 +1,5 @@
 #!/bin/bash
-# Install second-brain daemon as a LaunchAgent
+# Install second-brain daemon as a LaunchAgent (macOS) or systemd user service (Linux)
 set -euo pipefail
 
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
@@ -8,6 +8,8 @@ PROJECT_ROOT="$(dirname "$SCRIPT_DIR")"
 DAEMON_BINARY="$PROJECT_ROOT/daemon/target/release/second-brain-daemon"
 PLIST_SOURCE="$PROJECT_ROOT/daemon/resources/com.secondbrain.daemon.plist"
 PLIST_DEST="$HOME/Library/LaunchAgents/com.secondbrain.daemon.plist"
+UNIT_SOURCE="$PROJECT_ROOT/daemon/resources/second-brain-daemon.service"
+UNIT_DEST="${XDG_CONFIG_HOME:-$HOME/.config}/systemd/user/second-brain-daemon.service"
 INSTALL_DIR="/usr/local/bin"
 
 echo "=== Installing second-brain daemon ==="
@@ -19,8 +21,13 @@ if [[ ! -f "$DAEMON_BINARY" ]]; then
     exit 1
 fi
 
-# Unload existing daemon if running
-if launchctl list | grep -q com.secondbrain.daemon; then
+# Stop the existing daemon if running
+if [[ "$(uname)" == "Linux" ]]; then
+    if systemctl --user is-active --quiet second-brain-daemon; then
+        echo "Stopping existing daemon..."
+        systemctl --user stop second-brain-daemon
+    fi
+elif launchctl list | grep -q com.secondbrain.daemon; then
     echo "Stopping existing daemon..."
     launchctl bootout "gui/$(id -u)" "$PLIST_DEST" 2>/dev/null || true
 fi
@@ -36,6 +43,28 @@ echo "Installing binary to $INSTALL_DIR..."
 sudo cp "$DAEMON_BINARY" "$INSTALL_DIR/second-brain-daemon"
 sudo chmod 755 "$INSTALL_DIR/second-brain-daemon"
 
+if [[ "$(uname)" == "Linux" ]]; then
+    # Copy unit; systemd waits for the daemon's readiness notification
+    echo "Installing systemd user unit..."
+    mkdir -p "$(dirname "$UNIT_DEST")"
+    cp "$UNIT_SOURCE" "$UNIT_DEST"
+
+    # Start the daemon, now and at login
+    echo "Starting daemon..."
+    systemctl --user daemon-reload
+    if systemctl --user enable --now second-brain-daemon; then
+        echo "✓ Daemon installed and running"
+        echo ""
+        echo "Logs: journalctl --user -u second-brain-daemon -f"
+        echo "Stop: systemctl --user stop second-brain-daemon"
+    else
+        echo "✗ Daemon failed to start"
+        echo "Check logs: journalctl --user -u second-brain-daemon"
+        exit 1
+    fi
+    exit 0
+fi
+
 # Create LaunchAgents directory if needed
 mkdir -p "$HOME/Library/LaunchAgents"
 
@@ -3,13 +3,30 @@
 set -euo pipefail
 
 PLIST_DEST="$HOME/Library/LaunchAgents/com.secondbrain.daemon.plist"
+UNIT_DEST="${XDG_CONFIG_HOME:-$HOME/.config}/systemd/user/second-brain-daemon.service"
 BINARY_PATH="/usr/local/bin/second-brain-daemon"
-SOCKET_PATH="$HOME/.local/share/second-brain/daemon.sock"
+if [[ "$(uname)" == "Linux" ]]; then
+    # systemd user services get $XDG_RUNTIME_DIR
+    SOCKET_PATH="${XDG_RUNTIME_DIR:-/run/user/$(id -u)}/second-brain/daemon.sock"
+else
+    # launchd agents get the per-user temporary directory as $TMPDIR
+    SOCKET_PATH="$(getconf DARWIN_USER_TEMP_DIR)second-brain/daemon.sock"
+fi
+LEGACY_SOCKET_PATH="$HOME/.local/share/second-brain/daemon.sock"
 
 echo "=== Uninstalling second-brain daemon ==="
 
+# Stop and remove the systemd user unit
+if [[ "$(uname)" == "Linux" ]]; then
+    if [[ -f "$UNIT_DEST" ]]; then
+        echo "Stopping daemon..."
+        systemctl --user disable --now second-brain-daemon 2>/dev/null || true
+        echo "Removing systemd user unit..."
+        rm -f "$UNIT_DEST"
+        systemctl --user daemon-reload
+    fi
 # Unload from launchd
-if launchctl list | grep -q com.secondbrain.daemon; then
+elif launchctl list | grep -q com.secondbrain.daemon; then
     echo "Stopping daemon..."
     launchctl bootout "gui/$(id -u)" "$PLIST_DEST" 2>/dev/null || true
 fi
@@ -27,9 +44,11 @@ if [[ -f "$BINARY_PATH" ]]; then
 fi
 
 # Remove socket if it exists
-if [[ -e "$SOCKET_PATH" ]]; then
-    echo "Removing socket file..."
-    rm -f "$SOCKET_PATH"
-fi
+for socket in "$SOCKET_PATH" "$LEGACY_SOCKET_PATH"; do
+    if [[ -e "$socket" ]]; then
+        echo "Removing socket file..."
+        rm -f "$socket"
+    fi
+done
 
 echo "✓ Daemon uninstalled"